@@ -1,12 +1,294 @@
 fn main() {
-    embuild::espidf::sysenv::output();
-
-    slint_build::compile_with_config(
-        "ui/main.slint",
-        slint_build::CompilerConfiguration::new()
-            .embed_resources(slint_build::EmbedResourcesKind::EmbedForSoftwareRenderer)
-            .with_sdf_fonts(true)
-            .with_scale_factor(2.0),
+    // The simulator is built for the host, without ESP-IDF
+    if std::env::var_os("CARGO_FEATURE_SIM").is_none() {
+        embuild::espidf::sysenv::output();
+        // Set by ESP-IDF when the component is added, for the `diagnostics-trace` feature
+        println!("cargo::rustc-check-cfg=cfg(esp_idf_comp_espressif__esp_diagnostics_enabled)");
+
+        if std::env::var_os("CARGO_FEATURE_IRAM_VTABLE").is_some() {
+            link_slint_vtables_in_internal_ram();
+        }
+    }
+
+    compile_slint("ui/main.slint");
+
+    check_rust_callbacks("ui/rust_callbacks.toml");
+
+    // The light background of the fluent style
+    embed_animations(&[("SPINNER", "ui/images/spinner.gif")], [0xfa, 0xfa, 0xfa]);
+
+    generate_assets_image("assets", "partitions.csv");
+
+    // The microphone takes the pins of the RGB status LED, which is then a WS2812
+    println!("cargo::rustc-check-cfg=cfg(status_led_rgb)");
+    if std::env::var_os("CARGO_FEATURE_STATUS_LED_RGB").is_some()
+        && std::env::var_os("CARGO_FEATURE_MICROPHONE").is_none()
+    {
+        println!("cargo::rustc-cfg=status_led_rgb");
+    }
+}
+
+/// Writes a linker script to `OUT_DIR` that moves the vtables of Slint from the flash to the
+/// internal RAM, after `.dram0.data`, between the `_slint_vtables_start` and `_slint_vtables_end`
+/// symbols. Every call to an item or item tree, e.g. to lay out or render it, loads a pointer from
+/// one of them, which misses the flash cache when it was evicted by the frame buffers of the PSRAM.
+///
+/// The ESP32-S3 can't load data through the instruction bus of the IRAM, so they are placed in
+/// the same SRAM through the data bus. They are matched by the names of their statics: the
+/// `*VTable` ones of `i-slint-core` and the `VT` one of each generated item tree, since the
+/// section of each static is named after it without LTO. The link fails if none matches, e.g.
+/// with LTO or the v0 mangling of the symbols, instead of leaving them in flash.
+fn link_slint_vtables_in_internal_ram() {
+    let script = std::path::Path::new(&std::env::var("OUT_DIR").unwrap()).join("slint_vtables.x");
+    std::fs::write(
+        &script,
+        "SECTIONS
+{
+  .dram0.slint_vtables : ALIGN(4)
+  {
+    _slint_vtables_start = ABSOLUTE(.);
+    *(.rodata.*VTable* .rodata.*[0-9]VT17h*)
+    . = ALIGN(4);
+    _slint_vtables_end = ABSOLUTE(.);
+  } > dram0_0_seg
+  ASSERT(_slint_vtables_end > _slint_vtables_start,
+         \"iram-vtable: no Slint vtable matched, they are still in flash\")
+}
+INSERT AFTER .dram0.data;
+",
     )
     .unwrap();
+    println!("cargo:rustc-link-arg=-T{}", script.display());
+}
+
+/// Compiles the UI from `main`. The imports are also looked up in `ui`, e.g.
+/// `import { Drawer } from "drawer.slint"` from any directory. The `SLINT_STYLE` environment
+/// variable selects another style of the widgets than the default one, e.g. `material`. The fonts
+/// of `fonts` are embedded too, see [`import_fonts`].
+fn compile_slint(main: &str) {
+    let manifest_dir = std::path::PathBuf::from(std::env::var("CARGO_MANIFEST_DIR").unwrap());
+    let ui_dir = manifest_dir.join("ui");
+    let main = import_fonts(&manifest_dir.join(main), &manifest_dir.join("fonts"));
+
+    let mut config = slint_build::CompilerConfiguration::new()
+        .with_include_paths(vec![ui_dir])
+        .embed_resources(slint_build::EmbedResourcesKind::EmbedForSoftwareRenderer)
+        // The compiler already subsets the fonts: it only embeds the glyphs of the characters
+        // in the string literals, ASCII, '●' and '…', in the font sizes that are used. A
+        // character that only appears in strings set from Rust isn't embedded and isn't shown.
+        .with_sdf_fonts(true)
+        .with_scale_factor(2.0);
+    println!("cargo:rerun-if-env-changed=SLINT_STYLE");
+    if let Ok(style) = std::env::var("SLINT_STYLE") {
+        config = config.with_style(style);
+    }
+    // Read by the compiler: more font sizes, in logical pixels, e.g. `SLINT_FONT_SIZES=12,48` for
+    // the sizes of a font that are only set from Rust. The distance fields of the glyphs are
+    // rendered for the range of sizes, at two thirds of the largest one.
+    println!("cargo:rerun-if-env-changed=SLINT_FONT_SIZES");
+    slint_build::compile_with_config(main, config).unwrap();
+}
+
+/// Makes the `.ttf` and `.otf` fonts of `dir` usable by their family name in `font-family`,
+/// without importing them from a `.slint` file: the compiler embeds them like the fonts that are
+/// imported, pre-rendered, so the firmware doesn't parse them at run time. Generates a
+/// `main.slint` in `OUT_DIR` that imports them and exports what `main` exports, and returns its
+/// path, or the path of `main` if there are no fonts.
+fn import_fonts(main: &std::path::Path, dir: &std::path::Path) -> std::path::PathBuf {
+    println!("cargo:rerun-if-changed={}", dir.display());
+    let mut fonts: Vec<_> = std::fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == "ttf" || extension == "otf")
+        })
+        .collect();
+    if fonts.is_empty() {
+        return main.to_path_buf();
+    }
+    // The order of the directory entries doesn't change the generated code
+    fonts.sort();
+
+    let mut code = String::new();
+    for font in &fonts {
+        code += &format!("import {:?};\n", font.display().to_string());
+    }
+    code += &format!("export * from {:?};\n", main.display().to_string());
+    // Named like `main`, for `check_rust_callbacks`
+    let wrapper = std::path::Path::new(&std::env::var("OUT_DIR").unwrap()).join("main.slint");
+    std::fs::write(&wrapper, code).unwrap();
+    wrapper
+}
+
+/// Converts the frames of the animated GIFs to raw RGB565 images in `OUT_DIR`, and generates
+/// `animations.rs` with a constant of each name that embeds them, see `src/animated_icon.rs`.
+/// RGB565 has no alpha channel, so the transparent pixels are blended with `background`.
+fn embed_animations(animations: &[(&str, &str)], background: [u8; 3]) {
+    use image::AnimationDecoder;
+
+    let out_dir = std::path::PathBuf::from(std::env::var("OUT_DIR").unwrap());
+    let mut code = String::new();
+    for (name, path) in animations {
+        println!("cargo:rerun-if-changed={path}");
+        let file = std::io::BufReader::new(std::fs::File::open(path).unwrap());
+        let frames = image::codecs::gif::GifDecoder::new(file)
+            .unwrap()
+            .into_frames()
+            .collect_frames()
+            .unwrap();
+        let (width, height) = frames[0].buffer().dimensions();
+
+        let mut includes = String::new();
+        for (index, frame) in frames.iter().enumerate() {
+            let mut data = Vec::with_capacity((width * height * 2) as usize);
+            for pixel in frame.buffer().pixels() {
+                let [r, g, b] = std::array::from_fn(|channel| {
+                    let alpha = pixel[3] as u32;
+                    (pixel[channel] as u32 * alpha + background[channel] as u32 * (255 - alpha))
+                        / 255
+                });
+                let rgb565 = (r >> 3) << 11 | (g >> 2) << 5 | b >> 3;
+                data.extend_from_slice(&(rgb565 as u16).to_le_bytes());
+            }
+            let frame_path = out_dir.join(format!("{}_{index}.rgb", name.to_lowercase()));
+            std::fs::write(&frame_path, data).unwrap();
+            includes += &format!(
+                "        include_bytes!({:?}),\n",
+                frame_path.display().to_string()
+            );
+        }
+        code += &format!(
+            "/// The frames of `{path}`\npub const {name}: Animation = Animation {{\n    width: {width},\n    height: {height},\n    frames: &[\n{includes}    ],\n}};\n"
+        );
+    }
+    std::fs::write(out_dir.join("animations.rs"), code).unwrap();
+}
+
+/// Concatenates the files of `dir` into `assets.bin`, the image of the `assets` partition, which
+/// is written next to the firmware, and generates `assets.rs` in `OUT_DIR` with an `Asset`
+/// constant of each file, named after its path, see `src/raw_flash.rs`. Fails the build if the
+/// image doesn't fit in the partition of `partition_table`.
+fn generate_assets_image(dir: &str, partition_table: &str) {
+    println!("cargo:rerun-if-changed={dir}");
+    println!("cargo:rerun-if-changed={partition_table}");
+    let partition_size = std::fs::read_to_string(partition_table)
+        .unwrap()
+        .lines()
+        .map(|line| line.split(',').map(str::trim).collect::<Vec<_>>())
+        .find(|fields| fields[0] == "assets")
+        .map(|fields| u32::from_str_radix(fields[4].trim_start_matches("0x"), 16).unwrap())
+        .unwrap();
+
+    fn collect_files(dir: &std::path::Path, files: &mut Vec<std::path::PathBuf>) {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return;
+        };
+        for entry in entries {
+            let path = entry.unwrap().path();
+            if path.file_name().unwrap().to_string_lossy().starts_with('.') {
+                continue;
+            }
+            if path.is_dir() {
+                collect_files(&path, files);
+            } else {
+                files.push(path);
+            }
+        }
+    }
+    let mut files = Vec::new();
+    collect_files(std::path::Path::new(dir), &mut files);
+    // The offsets don't change with the order of the directory entries
+    files.sort();
+
+    let mut image = Vec::new();
+    let mut code = String::new();
+    let mut names = Vec::new();
+    for path in &files {
+        let data = std::fs::read(path).unwrap();
+        let relative_path = path.strip_prefix(dir).unwrap().display().to_string();
+        let name: String = relative_path
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() {
+                    c.to_ascii_uppercase()
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        code += &format!(
+            "/// `{dir}/{relative_path}`\npub const {name}: Asset = Asset {{\n    offset: {},\n    len: {},\n}};\n",
+            image.len(),
+            data.len()
+        );
+        image.extend_from_slice(&data);
+        names.push(name);
+    }
+    code += &format!(
+        "/// All of them, in the order of the image\npub const ASSETS: &[Asset] = &[{}];\n",
+        names.join(", ")
+    );
+    if image.len() > partition_size as usize {
+        println!(
+            "cargo::error=The files of {dir} take {} bytes, more than the {partition_size} bytes of the assets partition",
+            image.len()
+        );
+    }
+
+    let out_dir = std::path::PathBuf::from(std::env::var("OUT_DIR").unwrap());
+    std::fs::write(out_dir.join("assets.rs"), code).unwrap();
+    let image_path = out_dir.join("assets.bin");
+    // Only once there is something to flash
+    if !image.is_empty() {
+        println!(
+            "cargo::warning=The image of the assets partition is {}",
+            image_path.display()
+        );
+    }
+    std::fs::write(image_path, image).unwrap();
+}
+
+/// Fails the build if the generated code has a callback that isn't listed in the manifest
+fn check_rust_callbacks(manifest_path: &str) {
+    println!("cargo:rerun-if-changed={manifest_path}");
+    let manifest: toml::Table = std::fs::read_to_string(manifest_path)
+        .unwrap()
+        .parse()
+        .unwrap();
+    let expected: Vec<&str> = manifest["callbacks"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|callback| callback.as_str().unwrap())
+        .collect();
+
+    let generated = std::path::Path::new(&std::env::var("OUT_DIR").unwrap()).join("main.rs");
+    let generated = std::fs::read_to_string(generated).unwrap();
+    let mut declared: Vec<String> = generated
+        .split("fn invoke_")
+        .skip(1)
+        .map(|rest| {
+            rest.split(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+                .next()
+                .unwrap()
+                .replace('_', "-")
+        })
+        .collect();
+    declared.sort();
+    declared.dedup();
+
+    for callback in &declared {
+        if !expected.contains(&callback.as_str()) {
+            println!(
+                "cargo::error=The Slint callback `{callback}` is missing from {manifest_path}"
+            );
+        }
+    }
+    for callback in expected {
+        if !declared.iter().any(|declared| declared == callback) {
+            println!("cargo::warning=The callback `{callback}` listed in {manifest_path} is not declared in the Slint code");
+        }
+    }
 }
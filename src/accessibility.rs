@@ -0,0 +1,58 @@
+//! The accessibility events of `ui/accessibility.slint`, written to the UART as JSON objects,
+//! one per line, e.g. `{"event":"show","label":"Map","role":"page"}`.
+//!
+//! The events share the UART with the logs, so the screen reader on the host has to skip the
+//! lines that aren't JSON objects.
+
+use std::sync::mpsc;
+
+use esp_idf_svc::hal::uart::UartTxDriver;
+use slint::ComponentHandle;
+
+use crate::{Accessibility, MainWindow};
+
+/// The UART of the USB to serial converter
+pub type EventUart = UartTxDriver<'static>;
+
+pub fn init(window: &MainWindow, mut uart: EventUart) {
+    let (sender, receiver) = mpsc::channel::<String>();
+    window
+        .global::<Accessibility>()
+        .on_emit(move |event, label, role| {
+            let line = format!(
+                "{{\"event\":{},\"label\":{},\"role\":{}}}\n",
+                json_string(&event),
+                json_string(&label),
+                json_string(&role)
+            );
+            // The writing thread only stops when the UART fails, then the events are dropped
+            let _ = sender.send(line);
+        });
+
+    // Writing to the UART waits for room in its buffer, which the event loop mustn't do
+    std::thread::spawn(move || {
+        for line in receiver {
+            if let Err(err) = uart.write(line.as_bytes()) {
+                log::error!("Cannot write the accessibility events: {err}");
+                break;
+            }
+        }
+    });
+}
+
+/// `text` quoted and escaped as a JSON string
+fn json_string(text: &str) -> String {
+    let mut json = String::with_capacity(text.len() + 2);
+    json.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            c if c.is_control() => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
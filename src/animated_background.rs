@@ -0,0 +1,30 @@
+//! Passes the pattern of the `AnimatedBackground` component, see
+//! `ui/animated_background.slint`, to the platform that draws it.
+
+use slint::platform::software_renderer::{Rgb565Pixel, TargetPixel};
+use slint::ComponentHandle;
+
+use crate::slint_platform::StripePattern;
+use crate::{BackgroundStripes, MainWindow};
+
+pub fn init(window: &MainWindow) {
+    let stripes = window.global::<BackgroundStripes>();
+    stripes.on_hidden(|| crate::slint_platform::set_stripe_pattern(None));
+    stripes.on_pattern_changed({
+        let window = window.as_weak();
+        move |pattern| {
+            let scale_factor = window.unwrap().window().scale_factor();
+            crate::slint_platform::set_stripe_pattern(Some(StripePattern {
+                pixels_per_frame: pattern.pixels_per_frame * scale_factor,
+                stripe_width: (pattern.stripe_width * scale_factor).round().max(0.) as usize,
+                stripe_color: rgb565(pattern.stripe_color),
+                gap_color: rgb565(pattern.gap_color),
+                key_color: rgb565(pattern.key_color),
+            }));
+        }
+    });
+}
+
+fn rgb565(color: slint::Color) -> Rgb565Pixel {
+    Rgb565Pixel::from_rgb(color.red(), color.green(), color.blue())
+}
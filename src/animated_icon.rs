@@ -0,0 +1,32 @@
+//! The frames of the animated icons, converted from GIFs by `build.rs` and embedded in the
+//! firmware, see `ui/animated_icon.slint`.
+
+use std::rc::Rc;
+
+use slint::{ComponentHandle, VecModel};
+
+use crate::{Animations, MainWindow};
+
+/// Frames of `width`×`height` pixels in the format of [`crate::storage::read_rgb565_image`]
+pub struct Animation {
+    pub width: u32,
+    pub height: u32,
+    pub frames: &'static [&'static [u8]],
+}
+
+impl Animation {
+    pub fn images(&self) -> Vec<slint::Image> {
+        self.frames
+            .iter()
+            .map(|frame| crate::storage::rgb565_image(frame, self.width, self.height))
+            .collect()
+    }
+}
+
+include!(concat!(env!("OUT_DIR"), "/animations.rs"));
+
+pub fn init(window: &MainWindow) {
+    window
+        .global::<Animations>()
+        .set_spinner(Rc::new(VecModel::from(SPINNER.images())).into());
+}
@@ -0,0 +1,128 @@
+//! The initials and the colors of the avatars of `ui/avatar.slint`, and their pictures, cropped
+//! from JPEG images by [`avatar_from_jpeg`].
+
+use slint::ComponentHandle;
+#[cfg(not(feature = "sim"))]
+use slint::{Rgba8Pixel, SharedPixelBuffer};
+
+use crate::{AvatarStyle, MainWindow};
+
+/// Backgrounds of the initials, all dark enough for white letters
+const COLORS: [(u8, u8, u8); 8] = [
+    (0x1e, 0x88, 0xe5),
+    (0x43, 0xa0, 0x47),
+    (0xe5, 0x39, 0x35),
+    (0x8e, 0x24, 0xaa),
+    (0xef, 0x6c, 0x00),
+    (0x00, 0x89, 0x7b),
+    (0x6d, 0x4c, 0x41),
+    (0x39, 0x49, 0xab),
+];
+/// The picture of the first contact of the avatars page, on the storage partition, see the
+/// README
+#[cfg(not(feature = "sim"))]
+const CONTACT_PICTURE: &str = "avatar.jpg";
+/// In physical pixels, the avatars of the page are 64 logical pixels wide
+#[cfg(not(feature = "sim"))]
+const CONTACT_PICTURE_SIZE: u32 = 128;
+
+/// Must be called once the storage is mounted
+pub fn init(window: &MainWindow) {
+    let style = window.global::<AvatarStyle>();
+    style.on_initials(|name| initials(&name).into());
+    style.on_color(|name| color(&name));
+
+    #[cfg(not(feature = "sim"))]
+    {
+        let path: std::path::PathBuf = [crate::storage::MOUNT_POINT, CONTACT_PICTURE]
+            .iter()
+            .collect();
+        if let Ok(bytes) = std::fs::read(&path) {
+            window.set_contact_picture(avatar_from_jpeg(&bytes, CONTACT_PICTURE_SIZE));
+        }
+    }
+}
+
+/// The first letters of the first two words of `name`, or the first two letters of its only
+/// word, in uppercase
+fn initials(name: &str) -> String {
+    let mut words = name.split_whitespace();
+    let letters: String = match (words.next(), words.next()) {
+        (Some(first), Some(second)) => first
+            .chars()
+            .take(1)
+            .chain(second.chars().take(1))
+            .collect(),
+        (Some(word), None) => word.chars().take(2).collect(),
+        (None, _) => String::new(),
+    };
+    letters.to_uppercase()
+}
+
+/// The same for the same name on every board and after every update, unlike the hasher of the
+/// standard library, so the hash is FNV-1a
+fn color(name: &str) -> slint::Color {
+    let hash = name.bytes().fold(0x811c_9dc5u32, |hash, byte| {
+        (hash ^ u32::from(byte)).wrapping_mul(0x0100_0193)
+    });
+    let (red, green, blue) = COLORS[hash as usize % COLORS.len()];
+    slint::Color::from_rgb_u8(red, green, blue)
+}
+
+/// Crops the square in the middle of the JPEG image `bytes` and scales it to `size`×`size`
+/// pixels, for the `image` of an `Avatar`. The image is never decoded at its full size: TJpgDec
+/// halves it while decoding as long as the square stays larger, and the nearest pixel is taken
+/// from there. The corners are left transparent, since the software renderer only clips the
+/// avatar to a square. If it can't be decoded, the error is logged and the image is empty, for
+/// which the avatar shows the initials.
+#[cfg(not(feature = "sim"))]
+pub fn avatar_from_jpeg(bytes: &[u8], size: u32) -> slint::Image {
+    let mut buffer = SharedPixelBuffer::<Rgba8Pixel>::new(size, size);
+    let pixels = buffer.make_mut_slice();
+    let size = size as usize;
+    let decoded = crate::jpeg::decode_jpeg(
+        bytes,
+        |width, height| {
+            (0..3u8)
+                .take_while(|scale| width.min(height) >> (scale + 1) >= size)
+                .count() as u8
+        },
+        |block| {
+            let side = block.image_width.min(block.image_height);
+            let left = (block.image_width - side) / 2;
+            let top = (block.image_height - side) / 2;
+            // The pixel of the image at the center of a pixel of the avatar
+            let source = |target: usize| (2 * target + 1) * side / (2 * size);
+            for y in 0..size {
+                let Some(row) = (top + source(y)).checked_sub(block.top) else {
+                    continue;
+                };
+                if row >= block.height {
+                    continue;
+                }
+                for x in 0..size {
+                    let Some(column) = (left + source(x)).checked_sub(block.left) else {
+                        continue;
+                    };
+                    if column >= block.width {
+                        continue;
+                    }
+                    let rgb = &block.pixels[(row * block.width + column) * 3..][..3];
+                    pixels[y * size + x] = Rgba8Pixel::new(rgb[0], rgb[1], rgb[2], 255);
+                }
+            }
+        },
+    );
+    if let Err(err) = decoded {
+        log::warn!("Cannot decode the picture of the avatar: {err}");
+        return slint::Image::default();
+    }
+    // Antialiased, from how much of each pixel is within the circle
+    let radius = size as f32 / 2.;
+    for (index, pixel) in pixels.iter_mut().enumerate() {
+        let (x, y) = ((index % size) as f32 + 0.5, (index / size) as f32 + 0.5);
+        let coverage = (radius + 0.5 - (x - radius).hypot(y - radius)).clamp(0., 1.);
+        pixel.a = (f32::from(pixel.a) * coverage) as u8;
+    }
+    slint::Image::from_rgba8(buffer)
+}
@@ -0,0 +1,66 @@
+//! The scale of the `BarChart` component, and the energy shown by the energy page, see
+//! `ui/bar_chart.slint`.
+//!
+//! The board has no energy meter, so the use of each day changes a little every few seconds.
+
+use std::rc::Rc;
+use std::time::Duration;
+
+use slint::{ComponentHandle, Model, ModelRc, SharedString, VecModel};
+
+use crate::{BarScale, EnergyMeter, MainWindow};
+
+const DAYS: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+/// In kWh, around which the use of each day varies
+const TYPICAL_USE: [f32; 7] = [8.2, 7.5, 9.1, 8.8, 11.3, 13.6, 10.4];
+const UPDATE_INTERVAL: Duration = Duration::from_secs(3);
+
+thread_local! {
+    // Only accessed from the event loop thread
+    static TIMER: slint::Timer = Default::default();
+}
+
+/// Must be called once before a bar chart is shown
+pub fn init(window: &MainWindow) {
+    window
+        .global::<BarScale>()
+        .on_nice_maximum(|values, steps| {
+            nice_maximum(values.iter().fold(0., f32::max), steps.max(1) as u32)
+        });
+
+    let meter = window.global::<EnergyMeter>();
+    meter.set_days(ModelRc::new(VecModel::from_iter(
+        DAYS.into_iter().map(SharedString::from),
+    )));
+    let daily_use = Rc::new(VecModel::from(TYPICAL_USE.to_vec()));
+    meter.set_daily_use(daily_use.clone().into());
+
+    let mut time = 0f32;
+    TIMER.with(|timer| {
+        timer.start(slint::TimerMode::Repeated, UPDATE_INTERVAL, move || {
+            time += 1.;
+            for (day, typical) in TYPICAL_USE.iter().enumerate() {
+                // Setting the rows rather than the model, for the bars to be animated
+                let variation = 3. * (time * 0.7 + day as f32 * 1.9).sin();
+                daily_use.set_row_data(day, ((typical + variation) * 10.).round() / 10.);
+            }
+        })
+    });
+}
+
+/// The smallest multiple of `steps` round steps, which are 1, 2 or 5 times a power of ten, that
+/// is at least `largest`, e.g. 20 for 13.6 with 4 steps of 5. Steps of 1 when all the values are
+/// 0 or less.
+pub fn nice_maximum(largest: f32, steps: u32) -> f32 {
+    if largest <= 0. || !largest.is_finite() {
+        return steps as f32;
+    }
+    let smallest_step = largest / steps as f32;
+    let power_of_ten = 10f32.powf(smallest_step.log10().floor());
+    let step = [1., 2., 5., 10.]
+        .into_iter()
+        .map(|factor| factor * power_of_ten)
+        .find(|step| *step >= smallest_step)
+        .unwrap_or(10. * power_of_ten);
+    step * steps as f32
+}
@@ -0,0 +1,172 @@
+//! Measures how long a closure queued from another thread with `slint::invoke_from_event_loop`
+//! waits until the event loop of the platform runs it, and the thread is woken up again. The
+//! closure sets a property that is drawn, like the closures of the application do, e.g. with the
+//! readings of a sensor. The latency is mostly the time left in the iteration of the event loop
+//! that was running, which reads the touch controller and renders the frame, and the time that
+//! the queue stays locked for.
+//!
+//! ```bash
+//! cargo run --release --bin platform_benchmark
+//! ```
+//!
+//! The minimum, mean, 99th percentile and maximum of the latencies are logged once done. With the
+//! `iram-vtable` feature, the size of the vtables of Slint moved to the internal RAM is logged too.
+
+#[cfg(not(feature = "sim"))]
+use std::sync::{Arc, Condvar, Mutex};
+
+#[cfg(not(feature = "sim"))]
+use esp_idf_svc::hal::prelude::*;
+#[cfg(not(feature = "sim"))]
+use slint::ComponentHandle;
+
+// `src/slint_platform.rs`, in a module of `src` for its own modules to be found next to it
+#[cfg(not(feature = "sim"))]
+#[path = ".."]
+mod src {
+    // Only the display and the event loop are used
+    #[allow(dead_code, unused_imports)]
+    pub mod slint_platform;
+}
+#[cfg(not(feature = "sim"))]
+use src::slint_platform;
+
+/// Measured, after one to wait until the event loop runs
+#[cfg(not(feature = "sim"))]
+const ITERATIONS: usize = 1000;
+
+#[cfg(not(feature = "sim"))]
+slint::slint! {
+    export component Benchmark inherits Window {
+        in property <int> counter;
+
+        background: black;
+
+        // Drawn again at each update
+        Rectangle {
+            x: 0;
+            y: 0;
+            width: mod(root.counter, 100) * 4px;
+            height: 8px;
+            background: white;
+        }
+    }
+}
+
+#[cfg(not(feature = "sim"))]
+fn main() {
+    esp_idf_svc::sys::link_patches();
+    esp_idf_svc::log::EspLogger::initialize_default();
+
+    let p = Peripherals::take().unwrap();
+    // The latency of the event loop, rather than of the refresh of the panel, which the frames
+    // of the benchmark don't need to wait for
+    let display = slint_platform::init_display(slint_platform::EspPlatformConfig {
+        sync_mode: slint_platform::SyncMode::ColorTransDone,
+        ..Default::default()
+    })
+    .unwrap();
+    let i2c: slint_platform::SharedI2c = std::rc::Rc::new(
+        esp_idf_svc::hal::i2c::I2cDriver::new(
+            p.i2c0,
+            p.pins.gpio8,
+            p.pins.gpio9,
+            &esp_idf_svc::hal::i2c::config::Config::new().baudrate(400_000.Hz()),
+        )
+        .unwrap()
+        .into(),
+    );
+    slint_platform::init_touch(display, i2c)
+        .map_err(|(err, _display)| err)
+        .unwrap();
+
+    let benchmark = Benchmark::new().unwrap();
+    let window = benchmark.as_weak();
+    std::thread::Builder::new()
+        // For the formatting of the logs
+        .stack_size(8 * 1024)
+        .spawn(move || {
+            let latencies = measure(window);
+            report(latencies);
+            slint::quit_event_loop().unwrap();
+        })
+        .unwrap();
+    benchmark.run().unwrap();
+}
+
+/// The latencies of [`ITERATIONS`] closures, in µs
+#[cfg(not(feature = "sim"))]
+fn measure(window: slint::Weak<Benchmark>) -> Vec<u32> {
+    let ran = Arc::new((Mutex::new(false), Condvar::new()));
+    let mut latencies = Vec::with_capacity(ITERATIONS);
+    for counter in 0..=ITERATIONS {
+        *ran.0.lock().unwrap() = false;
+        let start = now_us();
+        let window = window.clone();
+        let ran_in_event_loop = ran.clone();
+        slint::invoke_from_event_loop(move || {
+            window.unwrap().set_counter(counter as i32);
+            let (done, condvar) = &*ran_in_event_loop;
+            *done.lock().unwrap() = true;
+            condvar.notify_one();
+        })
+        .unwrap();
+        let (done, condvar) = &*ran;
+        drop(
+            condvar
+                .wait_while(done.lock().unwrap(), |done| !*done)
+                .unwrap(),
+        );
+        // The first closure waits for the event loop to start
+        if counter > 0 {
+            latencies.push((now_us() - start) as u32);
+        }
+    }
+    latencies
+}
+
+#[cfg(not(feature = "sim"))]
+fn report(mut latencies: Vec<u32>) {
+    latencies.sort_unstable();
+    let mean = latencies
+        .iter()
+        .map(|&latency| u64::from(latency))
+        .sum::<u64>()
+        / latencies.len() as u64;
+    let percentile_99 = latencies[(latencies.len() * 99).div_ceil(100) - 1];
+    log::info!(
+        "invoke_from_event_loop latency over {} closures: min {} µs, mean {mean} µs, p99 \
+         {percentile_99} µs, max {} µs",
+        latencies.len(),
+        latencies[0],
+        latencies[latencies.len() - 1],
+    );
+    #[cfg(feature = "iram-vtable")]
+    {
+        // Also checked by the linker script, but the numbers would be those of the flash
+        assert!(vtables_size() > 0, "No Slint vtable in internal RAM");
+        log::info!("Slint vtables in internal RAM: {} bytes", vtables_size());
+    }
+}
+
+/// The size of the section of the linker script of `build.rs`
+#[cfg(all(feature = "iram-vtable", not(feature = "sim")))]
+fn vtables_size() -> usize {
+    extern "C" {
+        static _slint_vtables_start: u8;
+        static _slint_vtables_end: u8;
+    }
+    // Only their addresses are taken, which is safe
+    core::ptr::addr_of!(_slint_vtables_end) as usize
+        - core::ptr::addr_of!(_slint_vtables_start) as usize
+}
+
+#[cfg(not(feature = "sim"))]
+fn now_us() -> i64 {
+    unsafe { esp_idf_svc::sys::esp_timer_get_time() }
+}
+
+#[cfg(feature = "sim")]
+fn main() {
+    eprintln!("The benchmark measures the event loop of the board, it can't run in the simulator");
+}
@@ -0,0 +1,132 @@
+//! Captures the frames shown by the `CameraViewfinder` of `ui/camera_viewfinder.slint` into
+//! `CameraGlobal.frame`, while its page is shown.
+//!
+//! No camera is connected to the board, so a camera stands for it, which films a test pattern. A
+//! real one, e.g. an OV2640, would give JPEG frames, to decode in the same task with
+//! `jpeg::decode_jpeg`.
+
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::Duration;
+
+use slint::{ComponentHandle, Rgb8Pixel, SharedPixelBuffer};
+
+use crate::slint_platform::CancellationToken;
+use crate::{CameraGlobal, MainWindow};
+
+const WIDTH: u32 = 320;
+const HEIGHT: u32 = 240;
+/// 15 frames per second
+const FRAME_INTERVAL: Duration = Duration::from_millis(66);
+/// The colors of the test pattern, from left to right
+const BARS: [(u8, u8, u8); 8] = [
+    (0xc0, 0xc0, 0xc0),
+    (0xc0, 0xc0, 0x00),
+    (0x00, 0xc0, 0xc0),
+    (0x00, 0xc0, 0x00),
+    (0xc0, 0x00, 0xc0),
+    (0xc0, 0x00, 0x00),
+    (0x00, 0x00, 0xc0),
+    (0x10, 0x10, 0x10),
+];
+
+type Frame = SharedPixelBuffer<Rgb8Pixel>;
+
+/// Stands for the camera: color bars that scroll to the left, and a white line that goes down
+#[derive(Default)]
+pub struct Camera {
+    frame: u32,
+}
+
+impl Camera {
+    fn capture(&mut self) -> Result<Frame, std::convert::Infallible> {
+        // As long as the exposure of a frame
+        std::thread::sleep(FRAME_INTERVAL);
+        self.frame = self.frame.wrapping_add(1);
+        let bar_width = WIDTH / BARS.len() as u32;
+        let line = self.frame * 4 % HEIGHT;
+        let mut frame = Frame::new(WIDTH, HEIGHT);
+        for (index, pixel) in frame.make_mut_slice().iter_mut().enumerate() {
+            let (x, y) = (index as u32 % WIDTH, index as u32 / WIDTH);
+            let (red, green, blue) = if y.abs_diff(line) < 2 {
+                (0xff, 0xff, 0xff)
+            } else {
+                BARS[((x + self.frame * 2) / bar_width) as usize % BARS.len()]
+            };
+            *pixel = Rgb8Pixel::new(red, green, blue);
+        }
+        Ok(frame)
+    }
+}
+
+/// Captures in a task of its own, from each call of `CameraGlobal.start` until the page that
+/// called it isn't shown anymore
+pub fn init(window: &MainWindow, mut camera: Camera) {
+    let (starts, started) = mpsc::channel::<CancellationToken>();
+    let weak = window.as_weak();
+    // Of the page that streams, so that one with several viewfinders only streams once
+    let mut streaming: Option<CancellationToken> = None;
+    window.global::<CameraGlobal>().on_start(move || {
+        if streaming
+            .as_ref()
+            .is_some_and(|token| !token.is_cancelled())
+        {
+            return;
+        }
+        let token = crate::navigation::page_token(&weak.unwrap());
+        // The task only ends with the event loop
+        let _ = starts.send(token.clone());
+        streaming = Some(token);
+    });
+
+    let window = window.as_weak();
+    // The frame captured last, until the event loop shows it. A frame captured meanwhile
+    // replaces it, so that they don't pile up when the event loop renders slower than the camera.
+    let pending = Arc::new(Mutex::new(None::<Frame>));
+    let spawned = crate::slint_platform::spawn_low_priority(async move {
+        // The task only runs this future, which can block it
+        while let Ok(token) = started.recv() {
+            while !token.is_cancelled() {
+                let frame = match camera.capture() {
+                    Ok(frame) => frame,
+                    Err(err) => {
+                        log::warn!("Cannot capture a frame of the camera: {err}");
+                        break;
+                    }
+                };
+                if pending.lock().unwrap().replace(frame).is_some() {
+                    continue;
+                }
+                let window = window.clone();
+                let pending = pending.clone();
+                let captured = slint::invoke_from_event_loop(move || {
+                    let frame = pending.lock().unwrap().take();
+                    if let (Some(window), Some(frame)) = (window.upgrade(), frame) {
+                        window
+                            .global::<CameraGlobal>()
+                            .set_frame(slint::Image::from_rgb8(frame));
+                    }
+                });
+                // The event loop was quit
+                if captured.is_err() {
+                    return;
+                }
+            }
+            // Not to show the last frame when the page is shown again
+            pending.lock().unwrap().take();
+            let window = window.clone();
+            let cleared = slint::invoke_from_event_loop(move || {
+                if let Some(window) = window.upgrade() {
+                    window
+                        .global::<CameraGlobal>()
+                        .set_frame(slint::Image::default());
+                }
+            });
+            if cleared.is_err() {
+                return;
+            }
+        }
+    });
+    if let Err(err) = spawned {
+        log::error!("Cannot start the camera: {err}");
+    }
+}
@@ -0,0 +1,162 @@
+//! Draws the pixels of the `CanvasWidget` component, see `ui/canvas.slint`, and the animation of
+//! the canvas page.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use slint::platform::software_renderer::{Rgb565Pixel, TargetPixel};
+use slint::{ComponentHandle, Rgb8Pixel, SharedPixelBuffer};
+
+use crate::{CanvasState, MainWindow};
+
+/// The size of the canvas of the canvas page, in physical pixels: twice its size in
+/// `ui/main.slint`
+const WIDTH: usize = 400;
+const HEIGHT: usize = 240;
+
+/// Pixels drawn with the primitives below, shown with [`Canvas::to_image`]. The coordinates are
+/// in pixels from the top left corner, and what is drawn outside of the canvas is clipped.
+pub struct Canvas {
+    width: usize,
+    height: usize,
+    pixels: Vec<Rgb565Pixel>,
+}
+
+impl Canvas {
+    /// A black canvas. The pixels are big enough to be taken from the PSRAM by the allocator.
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![Rgb565Pixel(0); width * height],
+        }
+    }
+
+    pub fn fill(&mut self, color: Rgb565Pixel) {
+        self.pixels.fill(color);
+    }
+
+    pub fn set_pixel(&mut self, x: i32, y: i32, color: Rgb565Pixel) {
+        if (0..self.width as i32).contains(&x) && (0..self.height as i32).contains(&y) {
+            self.pixels[y as usize * self.width + x as usize] = color;
+        }
+    }
+
+    /// With Bresenham's algorithm, both ends included
+    pub fn draw_line(&mut self, x0: i32, y0: i32, x1: i32, y1: i32, color: Rgb565Pixel) {
+        let (dx, dy) = ((x1 - x0).abs(), -(y1 - y0).abs());
+        let (step_x, step_y) = (if x0 < x1 { 1 } else { -1 }, if y0 < y1 { 1 } else { -1 });
+        let (mut x, mut y) = (x0, y0);
+        let mut error = dx + dy;
+        loop {
+            self.set_pixel(x, y, color);
+            if x == x1 && y == y1 {
+                break;
+            }
+            let doubled_error = 2 * error;
+            if doubled_error >= dy {
+                error += dy;
+                x += step_x;
+            }
+            if doubled_error <= dx {
+                error += dx;
+                y += step_y;
+            }
+        }
+    }
+
+    /// The outline of the `width`×`height` rectangle, inside of it
+    pub fn draw_rect(&mut self, x: i32, y: i32, width: i32, height: i32, color: Rgb565Pixel) {
+        if width <= 0 || height <= 0 {
+            return;
+        }
+        let (right, bottom) = (x + width - 1, y + height - 1);
+        self.draw_line(x, y, right, y, color);
+        self.draw_line(x, bottom, right, bottom, color);
+        self.draw_line(x, y, x, bottom, color);
+        self.draw_line(right, y, right, bottom, color);
+    }
+
+    pub fn fill_rect(&mut self, x: i32, y: i32, width: i32, height: i32, color: Rgb565Pixel) {
+        if width <= 0 || height <= 0 {
+            return;
+        }
+        let left = x.clamp(0, self.width as i32) as usize;
+        let right = (x + width).clamp(0, self.width as i32) as usize;
+        let top = y.clamp(0, self.height as i32) as usize;
+        let bottom = (y + height).clamp(0, self.height as i32) as usize;
+        for line in self.pixels[top * self.width..bottom * self.width].chunks_exact_mut(self.width)
+        {
+            line[left..right].fill(color);
+        }
+    }
+
+    /// The outline of the circle, with the midpoint algorithm
+    pub fn draw_circle(&mut self, center_x: i32, center_y: i32, radius: i32, color: Rgb565Pixel) {
+        let (mut x, mut y) = (radius, 0);
+        let mut error = 1 - radius;
+        // One eighth of the circle, mirrored to the others
+        while x >= y {
+            for (dx, dy) in [(x, y), (y, x)] {
+                for (sign_x, sign_y) in [(1, 1), (-1, 1), (1, -1), (-1, -1)] {
+                    self.set_pixel(center_x + sign_x * dx, center_y + sign_y * dy, color);
+                }
+            }
+            y += 1;
+            if error < 0 {
+                error += 2 * y + 1;
+            } else {
+                x -= 1;
+                error += 2 * (y - x) + 1;
+            }
+        }
+    }
+
+    /// Copies the pixels to an image, which Slint can't show from RGB565 pixels
+    pub fn to_image(&self) -> slint::Image {
+        let mut buffer = SharedPixelBuffer::<Rgb8Pixel>::new(self.width as u32, self.height as u32);
+        for (pixel, rgb565) in buffer.make_mut_slice().iter_mut().zip(&self.pixels) {
+            *pixel = crate::storage::rgb565_to_rgb8(rgb565.0);
+        }
+        slint::Image::from_rgb8(buffer)
+    }
+}
+
+/// A ball going around the canvas, with a hand pointing at it and a bar showing its height
+fn draw_frame(canvas: &mut Canvas, timestamp_ms: i32) {
+    let background = Rgb565Pixel::from_rgb(0xfa, 0xfa, 0xfa);
+    let blue = Rgb565Pixel::from_rgb(0x1e, 0x88, 0xe5);
+    let red = Rgb565Pixel::from_rgb(0xe5, 0x39, 0x35);
+    let gray = Rgb565Pixel::from_rgb(0x75, 0x75, 0x75);
+    let (width, height) = (canvas.width as i32, canvas.height as i32);
+
+    // One turn every 4 seconds
+    let angle = timestamp_ms as f32 / 4000. * core::f32::consts::TAU;
+    let (center_x, center_y) = (width / 2, height / 2);
+    let ball_x = center_x + (width as f32 * 0.3 * angle.cos()) as i32;
+    let ball_y = center_y + (height as f32 * 0.3 * angle.sin()) as i32;
+
+    canvas.fill(background);
+    canvas.draw_rect(0, 0, width, height, gray);
+    let bar_height = height - 16 - (ball_y - 8).max(0);
+    canvas.fill_rect(width - 24, height - 8 - bar_height, 16, bar_height, blue);
+    canvas.draw_line(center_x, center_y, ball_x, ball_y, gray);
+    for radius in [18, 19, 20] {
+        canvas.draw_circle(ball_x, ball_y, radius, red);
+    }
+}
+
+pub fn init(window: &MainWindow) {
+    let canvas = Rc::new(RefCell::new(Canvas::new(WIDTH, HEIGHT)));
+    window.global::<CanvasState>().on_painted({
+        let window = window.as_weak();
+        move |timestamp_ms| {
+            let mut canvas = canvas.borrow_mut();
+            draw_frame(&mut canvas, timestamp_ms);
+            window
+                .unwrap()
+                .global::<CanvasState>()
+                .set_image(canvas.to_image());
+        }
+    });
+}
@@ -0,0 +1,47 @@
+//! Model for the pages of the `Carousel` component, see `ui/carousel.slint`.
+
+use slint::{Model, ModelTracker, VecModel};
+
+/// The pages shown by a `Carousel`. Pages can be pushed and removed while the carousel is shown;
+/// the number of pages and their content is tracked by the carousel through the [`Model`] trait.
+pub struct CarouselModel<T> {
+    pages: VecModel<T>,
+}
+
+impl<T: Clone + 'static> CarouselModel<T> {
+    pub fn new(pages: Vec<T>) -> Self {
+        Self {
+            pages: VecModel::from(pages),
+        }
+    }
+
+    pub fn push_page(&self, page: T) {
+        self.pages.push(page);
+    }
+
+    /// Removes and returns the page at `index`.
+    /// Panics if `index` is out of bounds, like [`Vec::remove`].
+    pub fn remove_page(&self, index: usize) -> T {
+        self.pages.remove(index)
+    }
+}
+
+impl<T: Clone + 'static> Model for CarouselModel<T> {
+    type Data = T;
+
+    fn row_count(&self) -> usize {
+        self.pages.row_count()
+    }
+
+    fn row_data(&self, row: usize) -> Option<T> {
+        self.pages.row_data(row)
+    }
+
+    fn model_tracker(&self) -> &dyn ModelTracker {
+        self.pages.model_tracker()
+    }
+
+    fn as_any(&self) -> &dyn core::any::Any {
+        self
+    }
+}
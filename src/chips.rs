@@ -0,0 +1,91 @@
+//! Wraps the chips of the chip group into rows, see `ui/chip.slint`.
+
+use std::cell::RefCell;
+
+use slint::ComponentHandle;
+
+use crate::{ChipFlow, ChipSlot, MainWindow};
+
+/// Horizontal space between two chips of a row
+const SPACING: f32 = 6.;
+
+#[derive(Default)]
+struct ChipLayout {
+    window: slint::Weak<MainWindow>,
+    available_width: f32,
+    /// Of the chips of the group, in order
+    widths: Vec<f32>,
+}
+
+impl ChipLayout {
+    fn update_slots(&self) {
+        let Some(window) = self.window.upgrade() else {
+            return;
+        };
+        let slots: Vec<ChipSlot> = flow(&self.widths, self.available_width, SPACING)
+            .into_iter()
+            .map(|(x, row)| ChipSlot { x, row })
+            .collect();
+        window
+            .global::<ChipFlow>()
+            .set_slots(std::rc::Rc::new(slint::VecModel::from(slots)).into());
+    }
+}
+
+thread_local! {
+    // Only accessed from the event loop thread
+    static LAYOUT: RefCell<ChipLayout> = Default::default();
+}
+
+/// Must be called once before the chip group is shown
+pub fn init(window: &MainWindow) {
+    let flow = window.global::<ChipFlow>();
+    flow.on_group_resized(|width| {
+        LAYOUT.with(|layout| {
+            let mut layout = layout.borrow_mut();
+            layout.available_width = width;
+            layout.update_slots();
+        })
+    });
+    flow.on_chip_measured(|index, width| {
+        LAYOUT.with(|layout| {
+            let mut layout = layout.borrow_mut();
+            let index = index as usize;
+            if index >= layout.widths.len() {
+                layout.widths.resize(index + 1, 0.);
+            }
+            layout.widths[index] = width;
+            layout.update_slots();
+        })
+    });
+    flow.on_chip_removed(|index| {
+        LAYOUT.with(|layout| {
+            let mut layout = layout.borrow_mut();
+            if (index as usize) < layout.widths.len() {
+                layout.widths.remove(index as usize);
+                layout.update_slots();
+            }
+        })
+    });
+    LAYOUT.with(|layout| layout.borrow_mut().window = window.as_weak());
+}
+
+/// The x position and the row of items of `widths` placed one after the other, `spacing` apart,
+/// starting a new row when the next item would end after `available_width`. An item wider
+/// than `available_width` gets a row of its own.
+fn flow(widths: &[f32], available_width: f32, spacing: f32) -> Vec<(f32, i32)> {
+    let mut x = 0.;
+    let mut row = 0;
+    widths
+        .iter()
+        .map(|&width| {
+            if x > 0. && x + width > available_width {
+                x = 0.;
+                row += 1;
+            }
+            let slot = (x, row);
+            x += width + spacing;
+            slot
+        })
+        .collect()
+}
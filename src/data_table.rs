@@ -0,0 +1,162 @@
+//! The readings of the table page, see `ui/data_table.slint`. The whole dataset stays in Rust,
+//! where a task sorts it, and only the rows of the page that is shown are set to
+//! `ReadingsTable.rows`.
+//!
+//! The readings are made up: one per minute over the last day.
+
+use core::cmp::Ordering;
+use std::rc::Rc;
+use std::sync::{mpsc, Arc, Mutex};
+
+use slint::{ComponentHandle, ModelRc, SharedString, StandardListViewItem, VecModel};
+
+use crate::{MainWindow, ReadingsTable};
+
+/// The cells of a row, in the order of the columns of the table
+pub type TableRow = ModelRc<StandardListViewItem>;
+
+const READING_COUNT: u32 = 24 * 60;
+
+struct Reading {
+    /// Since midnight
+    minute: u32,
+    /// In °C
+    temperature: f32,
+    /// In %
+    humidity: f32,
+}
+
+impl Reading {
+    fn cells(&self) -> [SharedString; 3] {
+        [
+            slint::format!("{:02}:{:02}", self.minute / 60, self.minute % 60),
+            slint::format!("{:.1}", self.temperature),
+            slint::format!("{:.0}", self.humidity),
+        ]
+    }
+}
+
+/// The comparator of the readings by `column`, in the order of the columns of the table
+fn comparator(column: i32) -> Option<fn(&Reading, &Reading) -> Ordering> {
+    let compare: fn(&Reading, &Reading) -> Ordering = match column {
+        0 => |a, b| a.minute.cmp(&b.minute),
+        1 => |a, b| a.temperature.total_cmp(&b.temperature),
+        2 => |a, b| a.humidity.total_cmp(&b.humidity),
+        _ => return None,
+    };
+    Some(compare)
+}
+
+/// All the readings, in the order of the column that was sorted last
+#[derive(Default)]
+pub struct FullModel {
+    readings: Vec<Reading>,
+}
+
+impl FullModel {
+    fn simulated() -> Self {
+        let readings = (0..READING_COUNT)
+            .map(|minute| {
+                let day = minute as f32 / READING_COUNT as f32 * core::f32::consts::TAU;
+                // The same noise for the same minute, a few tenths of a degree
+                let noise = (minute.wrapping_mul(2_654_435_761) >> 28) as f32 / 16. - 0.5;
+                Reading {
+                    minute,
+                    temperature: 21. - 3. * day.cos() + noise,
+                    humidity: 45. + 10. * day.cos() - 4. * noise,
+                }
+            })
+            .collect();
+        Self { readings }
+    }
+
+    /// Stable, the readings that `compare` finds equal keep the order of the previous sort
+    fn sort_by(&mut self, compare: impl FnMut(&Reading, &Reading) -> Ordering) {
+        self.readings.sort_by(compare);
+    }
+}
+
+/// The rows of `page`, `page_size` of them but on the last page
+pub fn get_page(model: &FullModel, page: usize, page_size: usize) -> VecModel<TableRow> {
+    let rows: Vec<TableRow> = model
+        .readings
+        .iter()
+        .skip(page.saturating_mul(page_size))
+        .take(page_size)
+        .map(|reading| {
+            let cells = reading.cells().map(StandardListViewItem::from);
+            ModelRc::new(VecModel::from(Vec::from(cells)))
+        })
+        .collect();
+    VecModel::from(rows)
+}
+
+enum Request {
+    Page { page: usize, page_size: usize },
+    Sort { column: i32, ascending: bool },
+}
+
+/// Sorts the readings in a task of its own, which sets the rows of the pages in the order of the
+/// requests.
+pub fn init(window: &MainWindow) {
+    let (requests, requested) = mpsc::channel();
+    let table = window.global::<ReadingsTable>();
+    table.on_page_requested({
+        let requests = requests.clone();
+        move |page, page_size| {
+            // The task only ends with the event loop
+            let _ = requests.send(Request::Page {
+                page: page.max(0) as usize,
+                page_size: page_size.max(1) as usize,
+            });
+        }
+    });
+    table.on_sort_requested(move |column, ascending| {
+        let _ = requests.send(Request::Sort { column, ascending });
+    });
+
+    let model = Arc::new(Mutex::new(FullModel::default()));
+    let window = window.as_weak();
+    let spawned = crate::slint_platform::spawn_low_priority(async move {
+        // The task only runs this future, which can block it
+        *model.lock().unwrap() = FullModel::simulated();
+        while let Ok(request) = requested.recv() {
+            let (page, page_size) = match request {
+                Request::Page { page, page_size } => (page, page_size),
+                Request::Sort { column, ascending } => {
+                    let Some(compare) = comparator(column) else {
+                        continue;
+                    };
+                    let mut model = model.lock().unwrap();
+                    if ascending {
+                        model.sort_by(compare);
+                    } else {
+                        model.sort_by(|a, b| compare(b, a));
+                    }
+                    continue;
+                }
+            };
+            let model = model.clone();
+            let window = window.clone();
+            let shown = slint::invoke_from_event_loop(move || {
+                if let Some(window) = window.upgrade() {
+                    // Only waits if the task is sorting meanwhile, which takes a few ms
+                    let model = model.lock().unwrap();
+                    let row_count = model.readings.len();
+                    let page = page.min(row_count.saturating_sub(1) / page_size);
+                    let table = window.global::<ReadingsTable>();
+                    table.set_rows(Rc::new(get_page(&model, page, page_size)).into());
+                    table.set_row_count(row_count as i32);
+                    table.set_page(page as i32);
+                }
+            });
+            // The event loop was quit
+            if shown.is_err() {
+                break;
+            }
+        }
+    });
+    if let Err(err) = spawned {
+        log::error!("Cannot start sorting the readings: {err}");
+    }
+}
@@ -0,0 +1,26 @@
+//! Calendar computations used by the date picker, see `ui/date_picker.slint`.
+
+use slint::ComponentHandle;
+
+use crate::{Calendar, MainWindow};
+
+/// Must be called once before the date picker is shown
+pub fn init(window: &MainWindow) {
+    let calendar = window.global::<Calendar>();
+    calendar.on_is_leap_year(is_leap_year);
+    calendar.on_days_in_month(days_in_month);
+}
+
+pub fn is_leap_year(year: i32) -> bool {
+    (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
+
+/// Number of days in `month`, which is between 1 and 12
+pub fn days_in_month(year: i32, month: i32) -> i32 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
@@ -0,0 +1,60 @@
+//! A UUID that identifies the board, e.g. in MQTT topics or to target an OTA update, generated on
+//! the first start and kept in NVS, so the same firmware is flashed on every board.
+
+use std::sync::OnceLock;
+
+use esp_idf_svc::nvs::{EspDefaultNvsPartition, EspNvs};
+use esp_idf_svc::sys::{esp, esp_efuse_mac_get_default, esp_random, EspError};
+use slint::ComponentHandle;
+use uuid::Uuid;
+
+use crate::{DeviceInfo, MainWindow};
+
+const NVS_NAMESPACE: &str = "device";
+const NVS_ID_KEY: &str = "id";
+
+// Read from any task once `init` stored it
+static DEVICE_ID: OnceLock<Uuid> = OnceLock::new();
+
+/// Reads the device ID from NVS, generating and storing it if there is none yet, and sets
+/// `DeviceInfo.device-id`
+pub fn init(window: &MainWindow, nvs_partition: EspDefaultNvsPartition) -> Result<(), EspError> {
+    let mut nvs = EspNvs::new(nvs_partition, NVS_NAMESPACE, true)?;
+    let mut bytes = [0; 16];
+    let stored = nvs.get_blob(NVS_ID_KEY, &mut bytes)?;
+    let id = match stored.and_then(|stored| Uuid::from_slice(stored).ok()) {
+        Some(id) => id,
+        None => {
+            let id = generate()?;
+            nvs.set_blob(NVS_ID_KEY, id.as_bytes())?;
+            log::info!("Generated the device ID {id}");
+            id
+        }
+    };
+    let id = *DEVICE_ID.get_or_init(|| id);
+    window
+        .global::<DeviceInfo>()
+        .set_device_id(id.hyphenated().to_string().into());
+    Ok(())
+}
+
+/// The ID read by `init`, which must be called first
+#[allow(dead_code)] // For the MQTT topics and the OTA requests
+pub fn get() -> Uuid {
+    *DEVICE_ID.get().expect("device_id::init wasn't called")
+}
+
+/// A version 4 UUID. `esp_random` is only truly random once the radio is on, so it is XOR-ed
+/// with the MAC address of the chip, for two boards not to get the same one at boot.
+fn generate() -> Result<Uuid, EspError> {
+    let mut mac = [0u8; 6];
+    esp!(unsafe { esp_efuse_mac_get_default(mac.as_mut_ptr()) })?;
+    let mut bytes = [0u8; 16];
+    for chunk in bytes.chunks_mut(4) {
+        chunk.copy_from_slice(&unsafe { esp_random() }.to_le_bytes());
+    }
+    for (byte, mac) in bytes.iter_mut().zip(mac) {
+        *byte ^= mac;
+    }
+    Ok(uuid::Builder::from_random_bytes(bytes).into_uuid())
+}
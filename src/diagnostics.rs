@@ -0,0 +1,127 @@
+//! Lists the FreeRTOS tasks on the `TaskListView` of `ui/debug_overlay.slint`, to find the tasks
+//! whose stack is almost exhausted or that stay blocked. `vTaskList` needs the
+//! `CONFIG_FREERTOS_USE_TRACE_FACILITY` and `CONFIG_FREERTOS_USE_STATS_FORMATTING_FUNCTIONS`
+//! options of `sdkconfig.defaults`.
+//!
+//! Also logs the PSRAM that a page freed once another one replaced it, e.g. the photos of the
+//! gallery. ESP-IDF has no `heap_caps_malloc_trim`: its allocator merges the free blocks as they
+//! are freed, so there is nothing to give back, but the largest free block shows how fragmented
+//! the heap is.
+
+use std::ffi::CStr;
+use std::rc::Rc;
+use std::time::Duration;
+
+use esp_idf_svc::sys::{
+    heap_caps_get_free_size, heap_caps_get_largest_free_block, uxTaskGetNumberOfTasks, vTaskList,
+    MALLOC_CAP_SPIRAM,
+};
+use slint::{ComponentHandle, SharedString, VecModel};
+
+use crate::{MainWindow, TaskList, TaskRow};
+
+/// The length of a line of `vTaskList`, with a name of `CONFIG_FREERTOS_MAX_TASK_NAME_LEN`
+const LINE_LEN: usize = 40;
+/// Slint only drops the previous page when it renders the next frame
+const PAGE_DROP_DELAY: Duration = Duration::from_millis(500);
+
+thread_local! {
+    // Only accessed from the event loop thread
+    static PAGE_DROPPED: slint::Timer = Default::default();
+}
+
+/// A line of `vTaskList`
+#[derive(Clone, Debug)]
+pub struct TaskInfo {
+    pub name: String,
+    /// Running, Ready, Blocked, Suspended or Deleted
+    pub state: &'static str,
+    pub priority: u32,
+    /// The smallest free stack space since the task started, in bytes
+    pub stack_watermark: u32,
+}
+
+/// The tasks, in the order of `vTaskList`. It suspends the scheduler while it lists them.
+pub fn task_list() -> Vec<TaskInfo> {
+    // With room for the tasks created meanwhile
+    let mut buffer = vec![0u8; (unsafe { uxTaskGetNumberOfTasks() } as usize + 4) * LINE_LEN];
+    unsafe { vTaskList(buffer.as_mut_ptr().cast()) };
+    let Ok(text) = CStr::from_bytes_until_nul(&buffer) else {
+        return Vec::new();
+    };
+    text.to_string_lossy()
+        .lines()
+        .filter_map(parse_line)
+        .collect()
+}
+
+/// E.g. "main\tX\t1\t3216\t2" for the name, the state, the priority, the stack watermark and the
+/// number of the task, and the core it is pinned to with `CONFIG_FREERTOS_VTASKLIST_INCLUDE_COREID`
+fn parse_line(line: &str) -> Option<TaskInfo> {
+    let mut columns = line.split('\t').map(str::trim);
+    let name = columns.next()?.to_owned();
+    let state = match columns.next()? {
+        "X" => "Running",
+        "R" => "Ready",
+        "B" => "Blocked",
+        "S" => "Suspended",
+        "D" => "Deleted",
+        _ => return None,
+    };
+    Some(TaskInfo {
+        name,
+        state,
+        priority: columns.next()?.parse().ok()?,
+        stack_watermark: columns.next()?.parse().ok()?,
+    })
+}
+
+fn show_tasks(window: &MainWindow) {
+    let rows: Vec<TaskRow> = task_list()
+        .into_iter()
+        .map(|task| TaskRow {
+            name: SharedString::from(task.name),
+            state: task.state.into(),
+            priority: task.priority as i32,
+            stack_watermark: task.stack_watermark as i32,
+        })
+        .collect();
+    window
+        .global::<TaskList>()
+        .set_tasks(Rc::new(VecModel::from(rows)).into());
+}
+
+/// Must be called once to list the tasks, and to list them again on `TaskList.refresh`
+pub fn init(window: &MainWindow) {
+    show_tasks(window);
+    let window_weak = window.as_weak();
+    window.global::<TaskList>().on_refresh(move || {
+        // Once the click was handled, the scheduler is suspended while the tasks are listed
+        let _ = window_weak.upgrade_in_event_loop(|window| show_tasks(&window));
+    });
+}
+
+/// Logs the PSRAM heap once the page that replaced the previous one settled, from the handler
+/// of `MainWindow.page-changed`
+pub fn page_changed(page: slint::SharedString) {
+    let free_before = unsafe { heap_caps_get_free_size(MALLOC_CAP_SPIRAM) };
+    // Starting the timer again restarts it, a page that was only shown briefly is skipped
+    PAGE_DROPPED.with(|timer| {
+        timer.start(slint::TimerMode::SingleShot, PAGE_DROP_DELAY, move || {
+            log_psram_heap(&page, free_before);
+        })
+    });
+}
+
+fn log_psram_heap(page: &str, free_before: usize) {
+    let (free, largest_block) = unsafe {
+        (
+            heap_caps_get_free_size(MALLOC_CAP_SPIRAM),
+            heap_caps_get_largest_free_block(MALLOC_CAP_SPIRAM),
+        )
+    };
+    log::info!(
+        "PSRAM after opening {page}: {free} bytes free ({:+}), largest block {largest_block}",
+        free as isize - free_before as isize
+    );
+}
@@ -0,0 +1,129 @@
+//! Modal dialogs, see `ui/dialog.slint`.
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::task::{Poll, Waker};
+
+use slint::ComponentHandle;
+
+use crate::{DialogState, MainWindow};
+
+/// Maximum number of buttons that fit in the dialog
+const MAX_BUTTONS: usize = 3;
+
+/// Shared between the event loop and the future returned by [`show_dialog`]
+#[derive(Default)]
+struct DialogResult {
+    button: Option<usize>,
+    waker: Option<Waker>,
+}
+
+impl DialogResult {
+    fn resolve(result: &Mutex<Self>, button: usize) {
+        let mut result = result.lock().unwrap();
+        result.button = Some(button);
+        if let Some(waker) = result.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+struct PendingDialog {
+    title: slint::SharedString,
+    body: slint::SharedString,
+    buttons: Vec<slint::SharedString>,
+    result: Arc<Mutex<DialogResult>>,
+}
+
+#[derive(Default)]
+struct DialogQueue {
+    window: slint::Weak<MainWindow>,
+    /// The dialog currently shown is the first one
+    pending: VecDeque<PendingDialog>,
+}
+
+thread_local! {
+    // Only accessed from the event loop thread
+    static QUEUE: RefCell<DialogQueue> = Default::default();
+}
+
+/// Must be called once before any dialog can be shown
+pub fn init(window: &MainWindow) {
+    window
+        .global::<DialogState>()
+        .on_button_clicked(button_clicked);
+    QUEUE.with(|queue| queue.borrow_mut().window = window.as_weak());
+}
+
+/// Shows a modal dialog with up to three buttons. The returned future resolves with the index
+/// of the button that was clicked. If another dialog is already shown, this one is shown after
+/// it is closed. This can be called from any thread.
+///
+/// If the dialog can't be shown because there is no event loop, the future resolves with
+/// `buttons.len()`.
+pub fn show_dialog(title: &str, body: &str, buttons: &[&str]) -> impl Future<Output = usize> {
+    let result = Arc::new(Mutex::new(DialogResult::default()));
+    let dialog = PendingDialog {
+        title: title.into(),
+        body: body.into(),
+        buttons: buttons
+            .iter()
+            .take(MAX_BUTTONS)
+            .map(|label| (*label).into())
+            .collect(),
+        result: result.clone(),
+    };
+    if let Err(err) = slint::invoke_from_event_loop(move || {
+        QUEUE.with(|queue| queue.borrow_mut().pending.push_back(dialog));
+        show_next();
+    }) {
+        log::error!("Cannot show dialog {title:?}: {err}");
+        DialogResult::resolve(&result, buttons.len());
+    }
+
+    std::future::poll_fn(move |cx| {
+        let mut result = result.lock().unwrap();
+        match result.button {
+            Some(button) => Poll::Ready(button),
+            None => {
+                result.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    })
+}
+
+fn show_next() {
+    QUEUE.with(|queue| {
+        let queue = queue.borrow();
+        let Some(window) = queue.window.upgrade() else {
+            return;
+        };
+        let state = window.global::<DialogState>();
+        if state.get_visible() {
+            return;
+        }
+        let Some(dialog) = queue.pending.front() else {
+            return;
+        };
+        state.set_title(dialog.title.clone());
+        state.set_body(dialog.body.clone());
+        state.set_buttons(std::rc::Rc::new(slint::VecModel::from(dialog.buttons.clone())).into());
+        state.set_visible(true);
+    });
+}
+
+fn button_clicked(button: i32) {
+    QUEUE.with(|queue| {
+        let mut queue = queue.borrow_mut();
+        if let Some(window) = queue.window.upgrade() {
+            window.global::<DialogState>().set_visible(false);
+        }
+        if let Some(dialog) = queue.pending.pop_front() {
+            DialogResult::resolve(&dialog.result, button as usize);
+        }
+    });
+    show_next();
+}
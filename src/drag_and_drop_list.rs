@@ -0,0 +1,95 @@
+//! Keeps the order of the favorites page across restarts, see `ui/drag_and_drop_list.slint`. The
+//! order is stored as the indices of [`FAVORITES`] in the `favorites` NVS namespace.
+//!
+//! The simulator has no NVS: the favorites start in their default order, which isn't stored.
+
+#[cfg(not(feature = "sim"))]
+use std::cell::RefCell;
+use std::rc::Rc;
+
+#[cfg(not(feature = "sim"))]
+use esp_idf_svc::nvs::EspDefaultNvsPartition;
+#[cfg(not(feature = "sim"))]
+use esp_idf_svc::sys::EspError;
+use slint::{ComponentHandle, Model, SharedString, VecModel};
+
+#[cfg(not(feature = "sim"))]
+use crate::persistent_property::PersistentProperty;
+use crate::{Favorites, MainWindow};
+
+#[cfg(not(feature = "sim"))]
+const NVS_NAMESPACE: &str = "favorites";
+#[cfg(not(feature = "sim"))]
+const NVS_ORDER_KEY: &str = "order";
+
+/// In their default order
+const FAVORITES: [&str; 5] = ["Map", "Camera", "Sensors", "Calendar", "Keyboard"];
+
+/// The indices of [`FAVORITES`], in the order of the page
+type Order = [u8; FAVORITES.len()];
+
+/// Reads the order from NVS, and stores it again each time an item is moved
+#[cfg(not(feature = "sim"))]
+pub fn init(window: &MainWindow, nvs_partition: EspDefaultNvsPartition) -> Result<(), EspError> {
+    let stored = PersistentProperty::<Order>::new(nvs_partition, NVS_NAMESPACE, NVS_ORDER_KEY)?;
+    // Not the indices of the favorites if they changed since it was stored
+    let order = stored.get()?.filter(|order| {
+        let mut sorted = *order;
+        sorted.sort_unstable();
+        sorted == default_order()
+    });
+    let items = set_items(window, order.unwrap_or_else(default_order));
+
+    let stored = RefCell::new(stored);
+    window.global::<Favorites>().on_item_moved(move |from, to| {
+        move_item(&items, from as usize, to as usize);
+        if let Err(err) = stored.borrow_mut().set(order_of(&items)) {
+            log::warn!("Cannot store the order of the favorites: {err}");
+        }
+    });
+    Ok(())
+}
+
+/// Sets the favorites in their default order, and moves them until the end
+#[cfg(feature = "sim")]
+pub fn init(window: &MainWindow) {
+    let items = set_items(window, default_order());
+    window
+        .global::<Favorites>()
+        .on_item_moved(move |from, to| move_item(&items, from as usize, to as usize));
+}
+
+fn default_order() -> Order {
+    core::array::from_fn(|index| index as u8)
+}
+
+fn set_items(window: &MainWindow, order: Order) -> Rc<VecModel<SharedString>> {
+    let items: Rc<VecModel<SharedString>> = Rc::new(VecModel::from_iter(
+        order
+            .iter()
+            .map(|index| SharedString::from(FAVORITES[*index as usize])),
+    ));
+    window.global::<Favorites>().set_items(items.clone().into());
+    items
+}
+
+/// Keeps the model in sync with the list, whose item was moved from `from` to `to`
+fn move_item(items: &VecModel<SharedString>, from: usize, to: usize) {
+    if from == to || from >= items.row_count() || to >= items.row_count() {
+        return;
+    }
+    let item = items.remove(from);
+    items.insert(to, item);
+}
+
+#[cfg(not(feature = "sim"))]
+fn order_of(items: &VecModel<SharedString>) -> Order {
+    let mut order = default_order();
+    for (index, item) in order.iter_mut().zip(items.iter()) {
+        *index = FAVORITES
+            .iter()
+            .position(|favorite| *favorite == item.as_str())
+            .unwrap_or_default() as u8;
+    }
+    order
+}
@@ -0,0 +1,77 @@
+//! Runs a task with `slint::spawn_local`, and shows its error in the `ErrorBoundary` of
+//! `ui/error_boundary.slint` until it is retried.
+//!
+//! The firmware is built with `panic_abort`, so `std::panic::catch_unwind` can't catch the panics
+//! of a task: they still restart the board, and `panic_record.rs` shows them afterwards. The tasks
+//! return the errors they can recover from instead.
+
+use core::fmt::Display;
+use core::future::Future;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use slint::ComponentHandle;
+
+use crate::{MainWindow, TaskError};
+
+thread_local! {
+    // Only accessed from the event loop thread
+    static RETRY: RefCell<Option<Box<dyn Fn()>>> = const { RefCell::new(None) };
+}
+
+/// Spawns the task of the last [`spawn`] again when the retry button is clicked
+pub fn init(window: &MainWindow) {
+    window.global::<TaskError>().on_retry(|| {
+        RETRY.with(|retry| {
+            if let Some(retry) = retry.borrow().as_ref() {
+                retry();
+            }
+        })
+    });
+}
+
+/// Spawns the future of `make_task`, and once it fails, shows its error instead of the content of
+/// the `ErrorBoundary`. Retrying calls `make_task` again, so it is the only task that can be
+/// retried.
+pub fn spawn<F, Fut, E>(window: &MainWindow, make_task: F)
+where
+    F: Fn() -> Fut + 'static,
+    Fut: Future<Output = Result<(), E>> + 'static,
+    E: Display,
+{
+    let window = window.as_weak();
+    let make_task = Rc::new(make_task);
+    RETRY.with(|retry| {
+        *retry.borrow_mut() = Some(Box::new({
+            let window = window.clone();
+            let make_task = make_task.clone();
+            move || run(window.clone(), make_task.clone())
+        }))
+    });
+    run(window, make_task);
+}
+
+fn run<F, Fut, E>(window: slint::Weak<MainWindow>, make_task: Rc<F>)
+where
+    F: Fn() -> Fut + 'static,
+    Fut: Future<Output = Result<(), E>> + 'static,
+    E: Display,
+{
+    if let Some(window) = window.upgrade() {
+        window.global::<TaskError>().set_has_error(false);
+    }
+    let spawned = slint::spawn_local(async move {
+        if let Err(err) = make_task().await {
+            let message = format!("{err}");
+            log::warn!("The task failed: {message}");
+            if let Some(window) = window.upgrade() {
+                let task_error = window.global::<TaskError>();
+                task_error.set_error_message(message.into());
+                task_error.set_has_error(true);
+            }
+        }
+    });
+    if let Err(err) = spawned {
+        log::warn!("Cannot spawn the task: {err}");
+    }
+}
@@ -0,0 +1,114 @@
+//! Model for the values of the `HeatMap` component, see `ui/heat_map.slint`, and the
+//! temperatures shown by the heat map page.
+//!
+//! The board has no thermal camera yet, so a thread pretends to measure a warm spot that
+//! moves around the grid.
+
+use std::time::Duration;
+
+use slint::{ComponentHandle, Model, ModelRc, ModelTracker, VecModel};
+
+use crate::{MainWindow, ThermalCamera};
+
+const ROWS: usize = 6;
+const COLS: usize = 10;
+const MEASUREMENT_INTERVAL: Duration = Duration::from_millis(200);
+/// In °C
+const AMBIENT_TEMPERATURE: f32 = 22.;
+const SPOT_TEMPERATURE: f32 = 38.;
+
+/// The values of a grid, row after row. The grid keeps its size, only the values change.
+pub struct HeatMapModel {
+    cols: usize,
+    values: VecModel<f32>,
+}
+
+impl HeatMapModel {
+    /// Panics if the cell is outside of the grid
+    pub fn set_value(&self, row: usize, col: usize, value: f32) {
+        assert!(col < self.cols, "column {col} is outside of the grid");
+        self.values.set_row_data(row * self.cols + col, value);
+    }
+}
+
+impl Model for HeatMapModel {
+    type Data = f32;
+
+    fn row_count(&self) -> usize {
+        self.values.row_count()
+    }
+
+    fn row_data(&self, row: usize) -> Option<f32> {
+        self.values.row_data(row)
+    }
+
+    fn model_tracker(&self) -> &dyn ModelTracker {
+        self.values.model_tracker()
+    }
+
+    fn as_any(&self) -> &dyn core::any::Any {
+        self
+    }
+}
+
+/// A grid of zeros for the heat map. The values are set with [`HeatMapModel::set_value`]
+/// after downcasting the model.
+pub fn make_heat_map_model(rows: usize, cols: usize) -> ModelRc<f32> {
+    ModelRc::new(HeatMapModel {
+        cols,
+        values: VecModel::from(vec![0.; rows * cols]),
+    })
+}
+
+pub fn init(window: &MainWindow) {
+    let camera = window.global::<ThermalCamera>();
+    camera.set_rows(ROWS as i32);
+    camera.set_cols(COLS as i32);
+    camera.set_temperatures(make_heat_map_model(ROWS, COLS));
+
+    let window = window.as_weak();
+    // Like a real camera, which must be read before its next frame overwrites the current one
+    let spawned = crate::slint_platform::spawn_high_priority(async move {
+        let mut angle = 0f32;
+        loop {
+            // The task only runs this future, which can block it
+            std::thread::sleep(MEASUREMENT_INTERVAL);
+            angle += 0.1;
+            // Going around an ellipse in the middle of the grid
+            let spot_row = (ROWS as f32 - 1.) / 2. * (1. + 0.6 * angle.sin());
+            let spot_col = (COLS as f32 - 1.) / 2. * (1. + 0.6 * angle.cos());
+            let mut temperatures = [[0.; COLS]; ROWS];
+            for (row, temperatures) in temperatures.iter_mut().enumerate() {
+                for (col, temperature) in temperatures.iter_mut().enumerate() {
+                    let distance = (row as f32 - spot_row).hypot(col as f32 - spot_col);
+                    *temperature = AMBIENT_TEMPERATURE
+                        + (SPOT_TEMPERATURE - AMBIENT_TEMPERATURE)
+                            / (1. + distance * distance / 4.);
+                }
+            }
+
+            let window = window.clone();
+            let measured = slint::invoke_from_event_loop(move || {
+                let Some(window) = window.upgrade() else {
+                    return;
+                };
+                let model = window.global::<ThermalCamera>().get_temperatures();
+                let Some(model) = model.as_any().downcast_ref::<HeatMapModel>() else {
+                    return;
+                };
+                for (row, temperatures) in temperatures.iter().enumerate() {
+                    for (col, &temperature) in temperatures.iter().enumerate() {
+                        model.set_value(row, col, temperature);
+                    }
+                }
+            });
+            // The event loop was quit
+            if measured.is_err() {
+                break;
+            }
+        }
+    });
+    if let Err(err) = spawned {
+        log::error!("Cannot start the thermal camera: {err}");
+    }
+}
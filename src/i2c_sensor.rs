@@ -0,0 +1,33 @@
+//! Reads the registers of the devices on the I2C bus of the touch controller for the UI, see
+//! `read-sensor` in `ui/main.slint`.
+
+use esp_idf_svc::hal::delay::TickType;
+
+use crate::slint_platform::SharedI2c;
+use crate::MainWindow;
+
+/// How long a device has to answer
+const I2C_TIMEOUT_MS: u64 = 10;
+
+pub fn init(window: &MainWindow, i2c: SharedI2c) {
+    window.on_read_sensor(move |address, register| {
+        let (Ok(address), Ok(register)) = (u8::try_from(address), u8::try_from(register)) else {
+            return -1;
+        };
+        let mut value = [0];
+        // Never borrowed by the event loop while it runs the callbacks
+        let read = i2c.borrow_mut().write_read(
+            address,
+            &[register],
+            &mut value,
+            TickType::new_millis(I2C_TIMEOUT_MS).ticks(),
+        );
+        match read {
+            Ok(()) => value[0] as i32,
+            Err(err) => {
+                log::warn!("Cannot read the register {register:#04x} of {address:#04x}: {err}");
+                -1
+            }
+        }
+    });
+}
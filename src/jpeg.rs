@@ -0,0 +1,234 @@
+//! Decodes JPEG images straight into an RGB565 buffer, e.g. a frame buffer, with the TJpgDec
+//! decoder of the ROM. The ESP32-S3 has no hardware JPEG decoder. TJpgDec outputs one MCU, a
+//! block of 8×8 or 16×16 pixels, at a time, so the decoded image is never held in memory besides
+//! in the buffer: the decoder only needs its work area and the current MCU. [`decode_jpeg`] passes
+//! the MCUs to a closure instead, e.g. to crop the image.
+
+use core::ffi::c_void;
+
+use slint::platform::software_renderer::{Rgb565Pixel, TargetPixel};
+
+mod sys {
+    //! From `esp_rom/include/esp32s3/rom/tjpgd.h`, the decoder of the ROM only outputs RGB888
+
+    #![allow(non_camel_case_types, clippy::upper_case_acronyms)]
+
+    use core::ffi::c_void;
+
+    pub type JRESULT = u32;
+
+    #[repr(C)]
+    pub struct JRECT {
+        pub left: u16,
+        pub right: u16,
+        pub top: u16,
+        pub bottom: u16,
+    }
+
+    #[repr(C)]
+    pub struct JDEC {
+        pub dctr: u32,
+        pub dptr: *mut u8,
+        pub inbuf: *mut u8,
+        pub dmsk: u8,
+        pub scale: u8,
+        pub msx: u8,
+        pub msy: u8,
+        pub qtid: [u8; 3],
+        pub dcv: [i16; 3],
+        pub nrst: u16,
+        pub width: u16,
+        pub height: u16,
+        pub huffbits: [[*mut u8; 2]; 2],
+        pub huffcode: [[*mut u16; 2]; 2],
+        pub huffdata: [[*mut u8; 2]; 2],
+        pub qttbl: [*mut i32; 4],
+        pub workbuf: *mut c_void,
+        pub mcubuf: *mut u8,
+        pub pool: *mut c_void,
+        pub sz_pool: u32,
+        pub infunc: Option<extern "C" fn(*mut JDEC, *mut u8, u32) -> u32>,
+        /// The [`super::Decoding`]
+        pub device: *mut c_void,
+    }
+
+    extern "C" {
+        pub fn jd_prepare(
+            jd: *mut JDEC,
+            infunc: extern "C" fn(*mut JDEC, *mut u8, u32) -> u32,
+            work: *mut c_void,
+            sz_work: u32,
+            dev: *mut c_void,
+        ) -> JRESULT;
+
+        pub fn jd_decomp(
+            jd: *mut JDEC,
+            outfunc: extern "C" fn(*mut JDEC, *mut c_void, *mut JRECT) -> u32,
+            scale: u8,
+        ) -> JRESULT;
+    }
+}
+
+/// Size of the work area of TJpgDec, the minimum recommended by its documentation
+const WORK_SIZE: usize = 3100;
+
+/// Why [`decode_jpeg`] failed, from the result codes of TJpgDec
+#[derive(Debug)]
+pub enum JpegError {
+    /// The data ends before the image does
+    Truncated,
+    /// The work area is too small, or the image has too large tables
+    OutOfMemory,
+    /// Not a JPEG image
+    InvalidFormat,
+    /// A JPEG image that TJpgDec can't decode, e.g. a progressive one
+    Unsupported,
+    Other(u32),
+}
+
+impl core::fmt::Display for JpegError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            JpegError::Truncated => f.write_str("The JPEG data is truncated"),
+            JpegError::OutOfMemory => f.write_str("The JPEG decoder is out of memory"),
+            JpegError::InvalidFormat => f.write_str("The data isn't a JPEG image"),
+            JpegError::Unsupported => f.write_str("The JPEG format isn't supported"),
+            JpegError::Other(result) => write!(f, "The JPEG decoder failed with {result}"),
+        }
+    }
+}
+
+fn check(result: sys::JRESULT) -> Result<(), JpegError> {
+    match result {
+        0 => Ok(()),
+        2 => Err(JpegError::Truncated),
+        3 | 4 => Err(JpegError::OutOfMemory),
+        6 => Err(JpegError::InvalidFormat),
+        7 | 8 => Err(JpegError::Unsupported),
+        result => Err(JpegError::Other(result)),
+    }
+}
+
+/// The state shared with the callbacks of TJpgDec
+struct Decoding<'a> {
+    input: &'a [u8],
+    output: &'a mut dyn FnMut(&Block),
+    /// Of the image, once scaled
+    image_width: usize,
+    image_height: usize,
+}
+
+/// Pixels decoded by [`decode_jpeg`], a block of the image at a time
+pub struct Block<'a> {
+    /// In RGB888, line by line
+    pub pixels: &'a [u8],
+    pub left: usize,
+    pub top: usize,
+    pub width: usize,
+    pub height: usize,
+    /// The size of the whole image, once scaled
+    pub image_width: usize,
+    pub image_height: usize,
+}
+
+/// Decodes the JPEG image `bytes` into `buffer`, whose lines are `width` pixels long, from its
+/// top left corner. The parts of the image that don't fit in `buffer` are left out.
+pub fn draw_jpeg_streaming(
+    bytes: &[u8],
+    buffer: &mut [Rgb565Pixel],
+    width: usize,
+) -> Result<(), JpegError> {
+    decode_jpeg(
+        bytes,
+        |_, _| 0,
+        |block| {
+            if block.left >= width {
+                return;
+            }
+            let visible_width = block.width.min(width - block.left);
+            for (row, line) in block.pixels.chunks_exact(block.width * 3).enumerate() {
+                let start = (block.top + row) * width + block.left;
+                let Some(target) = buffer.get_mut(start..start + visible_width) else {
+                    break;
+                };
+                for (pixel, rgb) in target.iter_mut().zip(line.chunks_exact(3)) {
+                    *pixel = Rgb565Pixel::from_rgb(rgb[0], rgb[1], rgb[2]);
+                }
+            }
+        },
+    )
+}
+
+/// Decodes the JPEG image `bytes`, and passes its pixels to `output` as they are decoded.
+/// `scale` gets the width and height of the image, and returns how many times it is halved,
+/// from 0 to 3, which TJpgDec does while decoding.
+pub fn decode_jpeg(
+    bytes: &[u8],
+    scale: impl FnOnce(usize, usize) -> u8,
+    mut output: impl FnMut(&Block),
+) -> Result<(), JpegError> {
+    let mut decoding = Decoding {
+        input: bytes,
+        output: &mut output,
+        image_width: 0,
+        image_height: 0,
+    };
+    // `u32` for the alignment that the decoder needs
+    let mut work = vec![0u32; WORK_SIZE / 4];
+    let mut decoder = core::mem::MaybeUninit::<sys::JDEC>::zeroed();
+    // Only accessed through the pointer, which the callbacks also get from the decoder
+    let decoding = &mut decoding as *mut Decoding;
+    unsafe {
+        check(sys::jd_prepare(
+            decoder.as_mut_ptr(),
+            read_input,
+            work.as_mut_ptr().cast(),
+            WORK_SIZE as u32,
+            decoding.cast(),
+        ))?;
+        let (width, height) = {
+            let decoder = decoder.assume_init_ref();
+            (decoder.width as usize, decoder.height as usize)
+        };
+        let scale = scale(width, height).min(3);
+        (*decoding).image_width = width >> scale;
+        (*decoding).image_height = height >> scale;
+        check(sys::jd_decomp(decoder.as_mut_ptr(), write_output, scale))
+    }
+}
+
+/// Copies the next `len` bytes of the input to `buffer`, or skips them if `buffer` is null, and
+/// returns how many there were
+extern "C" fn read_input(decoder: *mut sys::JDEC, buffer: *mut u8, len: u32) -> u32 {
+    let decoding = unsafe { &mut *((*decoder).device as *mut Decoding) };
+    let len = (len as usize).min(decoding.input.len());
+    let (read, rest) = decoding.input.split_at(len);
+    if !buffer.is_null() {
+        unsafe { core::ptr::copy_nonoverlapping(read.as_ptr(), buffer, len) };
+    }
+    decoding.input = rest;
+    len as u32
+}
+
+/// Passes the RGB888 pixels of `rect` to the output. Returns 1 to continue decoding.
+extern "C" fn write_output(
+    decoder: *mut sys::JDEC,
+    pixels: *mut c_void,
+    rect: *mut sys::JRECT,
+) -> u32 {
+    let decoding = unsafe { &mut *((*decoder).device as *mut Decoding) };
+    let rect = unsafe { &*rect };
+    let width = (rect.right - rect.left + 1) as usize;
+    let height = (rect.bottom - rect.top + 1) as usize;
+    let pixels = unsafe { core::slice::from_raw_parts(pixels as *const u8, width * height * 3) };
+    (decoding.output)(&Block {
+        pixels,
+        left: rect.left as usize,
+        top: rect.top as usize,
+        width,
+        height,
+        image_width: decoding.image_width,
+        image_height: decoding.image_height,
+    });
+    1
+}
@@ -0,0 +1,58 @@
+//! Swipe typing for the on-screen keyboard, see `ui/virtual_keyboard.slint`.
+
+use std::cell::RefCell;
+
+use slint::ComponentHandle;
+
+use crate::{MainWindow, SwipeTyping};
+
+mod predictor;
+
+pub use predictor::SwipePredictor;
+
+/// The word list on the storage partition, compressed with zlib. See the README.
+const WORD_LIST: &str = "words.z";
+
+#[derive(Default)]
+struct Keyboard {
+    predictor: SwipePredictor,
+    /// Points of the current swipe, in key units
+    trace: Vec<(f32, f32)>,
+}
+
+thread_local! {
+    // Only accessed from the event loop thread
+    static KEYBOARD: RefCell<Keyboard> = Default::default();
+}
+
+/// Must be called once before the keyboard is used, after the storage is mounted. Falls back
+/// to a small built-in word list if the word list can't be loaded.
+pub fn init(window: &MainWindow) {
+    let path = std::path::Path::new(crate::storage::MOUNT_POINT).join(WORD_LIST);
+    match SwipePredictor::load(&path) {
+        Ok(predictor) => KEYBOARD.with(|keyboard| keyboard.borrow_mut().predictor = predictor),
+        Err(err) => log::warn!("Cannot load the word list {}: {err}", path.display()),
+    }
+
+    let swipe_typing = window.global::<SwipeTyping>();
+    swipe_typing.on_trace_started(|x, y| {
+        KEYBOARD.with(|keyboard| {
+            let trace = &mut keyboard.borrow_mut().trace;
+            trace.clear();
+            trace.push((x, y));
+        })
+    });
+    swipe_typing
+        .on_trace_moved(|x, y| KEYBOARD.with(|keyboard| keyboard.borrow_mut().trace.push((x, y))));
+    swipe_typing.on_trace_finished(|| {
+        KEYBOARD.with(|keyboard| {
+            let keyboard = &mut *keyboard.borrow_mut();
+            let word = keyboard
+                .predictor
+                .predict(&keyboard.trace)
+                .unwrap_or_default();
+            keyboard.trace.clear();
+            word.into()
+        })
+    });
+}
@@ -0,0 +1,144 @@
+//! Predicts the word typed by swiping over its letters from the shape of the trace.
+
+use std::io;
+use std::path::Path;
+
+/// The letters of each row of the keyboard, and the offset of the row in key widths. Must
+/// match the layout in `ui/virtual_keyboard.slint`.
+const ROWS: [(&str, f32); 3] = [("qwertyuiop", 0.), ("asdfghjkl", 0.5), ("zxcvbnm", 1.5)];
+/// Number of points the trace and the trajectories of the words are resampled to
+const SAMPLES: usize = 32;
+/// Maximum distance, in keys, between the start and the end of the trace and the first and
+/// last letters of a candidate word
+const END_TOLERANCE: f32 = 1.;
+
+/// Used when the word list can't be loaded from the storage, the most frequent first
+const BUILTIN_WORDS: &str = "the\nof\nand\nto\nin\nis\nyou\nthat\nit\nhe\nwas\nfor\non\nare\nas\n\
+    with\nhis\nthey\nat\nbe\nthis\nhave\nfrom\nor\none\nhad\nby\nword\nbut\nnot\nwhat\nall\n\
+    were\nwe\nwhen\nyour\ncan\nsaid\nthere\nuse\nan\neach\nwhich\nshe\ndo\nhow\ntheir\nif\n\
+    will\nup\nother\nabout\nout\nmany\nthen\nthem\nthese\nso\nsome\nher\nwould\nmake\nlike\n\
+    him\ninto\ntime\nhas\nlook\ntwo\nmore\nwrite\ngo\nsee\nnumber\nno\nway\ncould\npeople\nmy\n\
+    than\nfirst\nwater\nbeen\ncall\nwho\noil\nits\nnow\nfind\nlong\ndown\nday\ndid\nget\ncome\n\
+    made\nmay\npart\nhello\nworld\nyes\nok\nthanks\nplease\ngood\nnew\nhome\nwork\nback\n";
+
+/// Scores the words of a list by comparing the trace of a swipe with the trajectory that goes
+/// through the center of the keys of each word, one straight segment per bigram.
+pub struct SwipePredictor {
+    /// One lowercase word per line, the most frequent first
+    words: String,
+}
+
+impl Default for SwipePredictor {
+    fn default() -> Self {
+        Self::new(BUILTIN_WORDS.into())
+    }
+}
+
+impl SwipePredictor {
+    /// `words` has one lowercase word per line, the most frequent first. Words with letters
+    /// that aren't on the keyboard are never predicted.
+    pub fn new(words: String) -> Self {
+        Self { words }
+    }
+
+    /// Decompresses a word list compressed with zlib
+    pub fn from_zlib(data: &[u8]) -> Result<Self, miniz_oxide::inflate::DecompressError> {
+        let words = miniz_oxide::inflate::decompress_to_vec_zlib(data)?;
+        Ok(Self::new(String::from_utf8_lossy(&words).into_owned()))
+    }
+
+    /// Reads a word list compressed with zlib, usually from the storage partition
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let data = std::fs::read(path)?;
+        Self::from_zlib(&data)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err.to_string()))
+    }
+
+    /// Returns the word whose trajectory is the closest to `trace`, in key units. Among words
+    /// with the same score, the most frequent one wins.
+    pub fn predict(&self, trace: &[(f32, f32)]) -> Option<&str> {
+        let (&start, &end) = (trace.first()?, trace.last()?);
+        let trace = resample(trace);
+        let mut trajectory = Vec::new();
+        let mut best = None;
+        let mut best_score = f32::MAX;
+        'words: for word in self.words.lines() {
+            // Most words are discarded by their first and last letters, before computing the
+            // whole trajectory
+            let (Some(first), Some(last)) = (word.chars().next(), word.chars().last()) else {
+                continue;
+            };
+            if !key_center(first).is_some_and(|center| distance(center, start) <= END_TOLERANCE)
+                || !key_center(last).is_some_and(|center| distance(center, end) <= END_TOLERANCE)
+            {
+                continue;
+            }
+
+            trajectory.clear();
+            for letter in word.chars() {
+                let Some(center) = key_center(letter) else {
+                    continue 'words;
+                };
+                // A double letter doesn't change the shape of the trajectory
+                if trajectory.last() != Some(&center) {
+                    trajectory.push(center);
+                }
+            }
+            let score = resample(&trajectory)
+                .iter()
+                .zip(&trace)
+                .map(|(&a, &b)| distance(a, b))
+                .sum::<f32>();
+            if score < best_score {
+                best = Some(word);
+                best_score = score;
+            }
+        }
+        best
+    }
+}
+
+fn key_center(letter: char) -> Option<(f32, f32)> {
+    ROWS.iter()
+        .enumerate()
+        .find_map(|(row, (letters, offset))| {
+            letters
+                .find(letter)
+                .map(|column| (offset + column as f32 + 0.5, row as f32 + 0.5))
+        })
+}
+
+fn distance(a: (f32, f32), b: (f32, f32)) -> f32 {
+    (a.0 - b.0).hypot(a.1 - b.1)
+}
+
+/// Returns `SAMPLES` points evenly spaced along the polyline through `points`, which must not
+/// be empty
+fn resample(points: &[(f32, f32)]) -> [(f32, f32); SAMPLES] {
+    let mut samples = [points[0]; SAMPLES];
+    let length = points.windows(2).map(|w| distance(w[0], w[1])).sum::<f32>();
+    if length == 0. {
+        return samples;
+    }
+    // The sample is on the segment from `points[segment]`, at `segment_start` along the polyline
+    let mut segment = 0;
+    let mut segment_start = 0.;
+    for (i, sample) in samples.iter_mut().enumerate() {
+        let target = length * i as f32 / (SAMPLES - 1) as f32;
+        while segment + 2 < points.len()
+            && segment_start + distance(points[segment], points[segment + 1]) < target
+        {
+            segment_start += distance(points[segment], points[segment + 1]);
+            segment += 1;
+        }
+        let (a, b) = (points[segment], points[segment + 1]);
+        let segment_length = distance(a, b);
+        let t = if segment_length > 0. {
+            ((target - segment_start) / segment_length).clamp(0., 1.)
+        } else {
+            0.
+        };
+        *sample = (a.0 + (b.0 - a.0) * t, a.1 + (b.1 - a.1) * t);
+    }
+    samples
+}
@@ -0,0 +1,159 @@
+//! Sets the levels of the `AudioLevels` global, see `ui/level_meter.slint`, from the frames of
+//! an I2S microphone with the `microphone` feature, e.g. two INMP441 whose L/R pins select the
+//! left and the right channel.
+//!
+//! Without it, the board has no microphone, so a thread pretends to capture music whose loudness
+//! jumps on each beat and fades until the next one. The sound is only captured while the page of
+//! the level meter is shown.
+
+#[cfg(all(feature = "microphone", not(feature = "sim")))]
+use esp_idf_svc::hal::{
+    delay::BLOCK,
+    gpio::{AnyIOPin, InputPin, OutputPin},
+    i2s::{
+        config::{DataBitWidth, StdConfig},
+        I2s, I2sDriver, I2sRx,
+    },
+    peripheral::Peripheral,
+};
+#[cfg(all(feature = "microphone", not(feature = "sim")))]
+use esp_idf_svc::sys::EspError;
+use std::sync::mpsc;
+
+use slint::ComponentHandle;
+
+use crate::slint_platform::CancellationToken;
+use crate::{AudioLevels, MainWindow};
+
+const SAMPLE_RATE_HZ: u32 = 16_000;
+/// Measured at once, 20 times per second
+const FRAME_COUNT: usize = SAMPLE_RATE_HZ as usize / 20;
+/// The bottom of the meter
+const FLOOR_DBFS: f32 = -60.;
+
+/// A stereo microphone, whose samples are 32-bit, the left channel first
+#[cfg(all(feature = "microphone", not(feature = "sim")))]
+pub struct Microphone {
+    driver: I2sDriver<'static, I2sRx>,
+    bytes: Vec<u8>,
+}
+
+#[cfg(all(feature = "microphone", not(feature = "sim")))]
+impl Microphone {
+    pub fn new<I: I2s>(
+        i2s: impl Peripheral<P = I> + 'static,
+        bclk: impl Peripheral<P = impl InputPin + OutputPin> + 'static,
+        din: impl Peripheral<P = impl InputPin> + 'static,
+        ws: impl Peripheral<P = impl InputPin + OutputPin> + 'static,
+    ) -> Result<Self, EspError> {
+        let config = StdConfig::philips(SAMPLE_RATE_HZ, DataBitWidth::Bits32);
+        let mut driver =
+            I2sDriver::new_std_rx(i2s, &config, bclk, din, Option::<AnyIOPin>::None, ws)?;
+        driver.rx_enable()?;
+        Ok(Self {
+            driver,
+            bytes: vec![0; 2 * FRAME_COUNT * 4],
+        })
+    }
+
+    /// Waits until the frames are captured
+    fn capture(&mut self, samples: &mut [i32]) -> Result<(), EspError> {
+        let mut read = 0;
+        while read < self.bytes.len() {
+            read += self.driver.read(&mut self.bytes[read..], BLOCK)?;
+        }
+        for (sample, bytes) in samples.iter_mut().zip(self.bytes.chunks_exact(4)) {
+            *sample = i32::from_le_bytes(bytes.try_into().unwrap());
+        }
+        Ok(())
+    }
+}
+
+/// Stands for the microphone: 120 beats per minute, a tone of 220 Hz on the left and of 330 Hz
+/// on the right, a bit quieter
+#[cfg(any(not(feature = "microphone"), feature = "sim"))]
+#[derive(Default)]
+pub struct Microphone {
+    frame: u64,
+}
+
+#[cfg(any(not(feature = "microphone"), feature = "sim"))]
+impl Microphone {
+    fn capture(&mut self, samples: &mut [i32]) -> Result<(), std::convert::Infallible> {
+        // As long as the frames would take to be captured
+        std::thread::sleep(std::time::Duration::from_secs_f32(
+            FRAME_COUNT as f32 / SAMPLE_RATE_HZ as f32,
+        ));
+        for frame in samples.chunks_exact_mut(2) {
+            let time = self.frame as f32 / SAMPLE_RATE_HZ as f32;
+            let since_beat = time % 0.5;
+            let loudness = 0.02 + 0.7 * (-6. * since_beat).exp();
+            let tone = |frequency: f32| (core::f32::consts::TAU * frequency * time).sin();
+            frame[0] = (loudness * tone(220.) * i32::MAX as f32) as i32;
+            frame[1] = (0.6 * loudness * tone(330.) * i32::MAX as f32) as i32;
+            self.frame += 1;
+        }
+        Ok(())
+    }
+}
+
+pub fn init(window: &MainWindow, mut microphone: Microphone) {
+    let (starts, started) = mpsc::channel::<CancellationToken>();
+    let weak = window.as_weak();
+    window.global::<AudioLevels>().on_start(move || {
+        let token = crate::navigation::page_token(&weak.unwrap());
+        // The task only ends with the event loop
+        let _ = starts.send(token);
+    });
+
+    let window = window.as_weak();
+    // Like a real microphone, whose DMA buffers must be read before they are overwritten
+    let spawned = crate::slint_platform::spawn_high_priority(async move {
+        let mut samples = vec![0; 2 * FRAME_COUNT];
+        // The task only runs this future, which can block it
+        while let Ok(token) = started.recv() {
+            while !token.is_cancelled() {
+                if let Err(err) = microphone.capture(&mut samples) {
+                    log::error!("Cannot capture the sound of the microphone: {err}");
+                    return;
+                }
+                let (left, right) = rms(&samples);
+                let window = window.clone();
+                let measured = slint::invoke_from_event_loop(move || {
+                    if let Some(window) = window.upgrade() {
+                        let levels = window.global::<AudioLevels>();
+                        levels.set_rms_left(level(left));
+                        levels.set_rms_right(level(right));
+                    }
+                });
+                // The event loop was quit
+                if measured.is_err() {
+                    return;
+                }
+            }
+        }
+    });
+    if let Err(err) = spawned {
+        log::error!("Cannot start the level meter: {err}");
+    }
+}
+
+/// Of the left and the right channels of stereo `samples`, relative to the full scale
+fn rms(samples: &[i32]) -> (f32, f32) {
+    let mut sums = (0., 0.);
+    for frame in samples.chunks_exact(2) {
+        let [left, right] = [frame[0], frame[1]].map(|sample| sample as f64 / i32::MAX as f64);
+        sums.0 += left * left;
+        sums.1 += right * right;
+    }
+    let frame_count = (samples.len() / 2).max(1) as f64;
+    (
+        (sums.0 / frame_count).sqrt() as f32,
+        (sums.1 / frame_count).sqrt() as f32,
+    )
+}
+
+/// On the meter, from 0 at [`FLOOR_DBFS`] to 1 at the full scale
+fn level(rms: f32) -> f32 {
+    (1. - 20. * rms.log10() / FLOOR_DBFS).clamp(0., 1.)
+}
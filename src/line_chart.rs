@@ -0,0 +1,77 @@
+//! Model for the values plotted by the `LineChart` component, and the curves of the smooth
+//! charts, see `ui/line_chart.slint`.
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+
+use slint::{ComponentHandle, Model, ModelNotify, ModelTracker};
+
+use crate::{ControlPoints, MainWindow, Spline};
+
+/// The last `capacity` values pushed, from the oldest to the newest. Pushing a value when the
+/// buffer is full drops the oldest one, so the chart scrolls to the left.
+pub struct RingBuffer {
+    values: RefCell<VecDeque<f32>>,
+    capacity: usize,
+    notify: ModelNotify,
+}
+
+impl RingBuffer {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            values: RefCell::new(VecDeque::with_capacity(capacity)),
+            capacity,
+            notify: Default::default(),
+        }
+    }
+
+    pub fn push(&self, value: f32) {
+        // Not borrowing the values while notifying, the chart reads them back
+        let full = self.values.borrow().len() == self.capacity;
+        if full {
+            self.values.borrow_mut().pop_front();
+            self.notify.row_removed(0, 1);
+        }
+        let row = {
+            let mut values = self.values.borrow_mut();
+            values.push_back(value);
+            values.len() - 1
+        };
+        self.notify.row_added(row, 1);
+    }
+}
+
+impl Model for RingBuffer {
+    type Data = f32;
+
+    fn row_count(&self) -> usize {
+        self.values.borrow().len()
+    }
+
+    fn row_data(&self, row: usize) -> Option<f32> {
+        self.values.borrow().get(row).copied()
+    }
+
+    fn model_tracker(&self) -> &dyn ModelTracker {
+        &self.notify
+    }
+}
+
+/// Must be called once before a smooth chart is shown
+pub fn init(window: &MainWindow) {
+    window
+        .global::<Spline>()
+        .on_control_points(catmull_rom_control_points);
+}
+
+/// The control points of the cubic Bézier curve from `from` to `to` that follows the
+/// Catmull-Rom spline through evenly spaced values, `previous` and `next` being the values
+/// before and after them. The curves of consecutive segments join without a kink.
+pub fn catmull_rom_control_points(previous: f32, from: f32, to: f32, next: f32) -> ControlPoints {
+    ControlPoints {
+        x1: 1. / 3.,
+        y1: from + (to - previous) / 6.,
+        x2: 2. / 3.,
+        y2: to - (next - from) / 6.,
+    }
+}
@@ -0,0 +1,103 @@
+//! Keeps the last log messages for the `LogView` of `ui/debug_overlay.slint`, to read them on
+//! the screen without a serial connection. They are still written to the UART, or to the
+//! terminal in the simulator.
+
+use std::collections::VecDeque;
+use std::rc::Rc;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use slint::{ComponentHandle, Model, SharedString, VecModel};
+
+use crate::{DebugLog, MainWindow};
+
+/// The number of messages kept, the older ones are dropped
+const MAX_LINES: usize = 100;
+/// How often the messages logged since then are added to the view
+const UPDATE_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Logged from any thread, until the event loop moves them to the view. Not through
+/// `slint::invoke_from_event_loop`, which logs when its queue is full.
+static PENDING: Mutex<VecDeque<SharedString>> = Mutex::new(VecDeque::new());
+
+thread_local! {
+    // Only accessed from the event loop thread
+    static TIMER: slint::Timer = Default::default();
+}
+
+struct UiLogger {
+    #[cfg(not(feature = "sim"))]
+    uart: esp_idf_svc::log::EspLogger,
+}
+
+impl log::Log for UiLogger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        #[cfg(not(feature = "sim"))]
+        let enabled = self.uart.enabled(metadata);
+        #[cfg(feature = "sim")]
+        let enabled = metadata.level() <= log::max_level();
+        enabled
+    }
+
+    fn log(&self, record: &log::Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        #[cfg(not(feature = "sim"))]
+        self.uart.log(record);
+        #[cfg(feature = "sim")]
+        eprintln!("{} {}: {}", record.level(), record.target(), record.args());
+
+        // Like the ESP-IDF logs, e.g. "W storage: Cannot mount the storage partition"
+        let level = &record.level().as_str()[..1];
+        let line = format!("{level} {}: {}", record.target(), record.args());
+        if let Ok(mut pending) = PENDING.lock() {
+            if pending.len() == MAX_LINES {
+                pending.pop_front();
+            }
+            pending.push_back(line.into());
+        }
+    }
+
+    fn flush(&self) {
+        #[cfg(not(feature = "sim"))]
+        self.uart.flush();
+    }
+}
+
+/// Sets the logger of the `log` crate, instead of `EspLogger::initialize_default`. The messages
+/// logged before [`init`] are kept for the view.
+pub fn init_logger() {
+    let logger = Box::leak(Box::new(UiLogger {
+        #[cfg(not(feature = "sim"))]
+        uart: esp_idf_svc::log::EspLogger::new(),
+    }));
+    if log::set_logger(logger).is_err() {
+        return;
+    }
+    // To the level of the ESP-IDF configuration
+    #[cfg(not(feature = "sim"))]
+    logger.uart.initialize();
+    #[cfg(feature = "sim")]
+    log::set_max_level(log::LevelFilter::Info);
+}
+
+/// Must be called once to show the messages in the view
+pub fn init(window: &MainWindow) {
+    let lines = Rc::new(VecModel::<SharedString>::default());
+    window.global::<DebugLog>().set_lines(lines.clone().into());
+    TIMER.with(|timer| {
+        timer.start(slint::TimerMode::Repeated, UPDATE_INTERVAL, move || {
+            let pending = match PENDING.lock() {
+                Ok(mut pending) => std::mem::take(&mut *pending),
+                Err(_) => return,
+            };
+            for line in pending {
+                if lines.row_count() == MAX_LINES {
+                    lines.remove(0);
+                }
+                lines.push(line);
+            }
+        })
+    });
+}
@@ -1,37 +1,560 @@
+#[cfg(not(feature = "sim"))]
 use esp_idf_svc::hal::prelude::*;
+use slint::Model;
 
+#[cfg(feature = "accessibility")]
+mod accessibility;
+mod animated_background;
+mod animated_icon;
+mod avatar;
+mod bar_chart;
+mod camera;
+mod canvas;
+mod carousel;
+mod chips;
+mod data_table;
+mod date_picker;
+#[cfg(not(feature = "sim"))]
+mod device_id;
+#[cfg(not(feature = "sim"))]
+mod diagnostics;
+mod dialog;
+mod drag_and_drop_list;
+// The simulator has no timer to spawn a task with
+#[cfg_attr(feature = "sim", allow(dead_code))]
+mod error_boundary;
+mod heat_map;
+#[cfg(not(feature = "sim"))]
+mod i2c_sensor;
+#[cfg(not(feature = "sim"))]
+mod jpeg;
+mod keyboard;
+mod level_meter;
+mod line_chart;
+mod log_view;
+mod map;
+mod navigation;
+mod network_list;
+mod notification;
+mod number_format;
+#[cfg(not(feature = "sim"))]
+mod panic_record;
+#[cfg(not(feature = "sim"))]
+mod persistent_property;
+mod pid;
+#[cfg(not(feature = "sim"))]
+mod pin;
+#[cfg(not(feature = "sim"))]
+mod raw_flash;
+mod search_bar;
+mod sensor_dashboard;
+mod signature;
+mod slider_list;
 mod slint_platform;
+#[cfg(not(feature = "sim"))]
+mod split_view;
+mod status_bar;
+#[cfg(feature = "status-led")]
+mod status_led;
+mod storage;
+mod switch_list;
+#[cfg(not(feature = "sim"))]
+mod system_events;
+mod system_info;
+mod thumbnails;
+mod timeline;
+mod tree;
+mod video;
+#[cfg(not(feature = "sim"))]
+mod wifi_signal;
 
 slint::include_modules!();
 
 fn main() {
     // It is necessary to call this function once. Otherwise some patches to the runtime
     // implemented by esp-idf-sys might not link properly. See https://github.com/esp-rs/esp-idf-template/issues/71
+    #[cfg(not(feature = "sim"))]
     esp_idf_svc::sys::link_patches();
 
-    // Bind the log crate to the ESP Logging facilities
-    esp_idf_svc::log::EspLogger::initialize_default();
+    // Bind the log crate to the ESP Logging facilities, and keep the last messages for the log
+    // page
+    log_view::init_logger();
 
+    #[cfg(not(feature = "sim"))]
+    let last_panic = panic_record::take();
+    #[cfg(not(feature = "sim"))]
+    {
+        if let Some(message) = &last_panic {
+            log::error!("Restarted after a panic: {message}");
+        }
+        panic_record::install();
+    }
+
+    #[cfg(not(feature = "sim"))]
     let p = Peripherals::take().unwrap();
 
-    let touch_i2c = esp_idf_svc::hal::i2c::I2cDriver::new(
-        p.i2c0,
-        p.pins.gpio8,
-        p.pins.gpio9,
-        &esp_idf_svc::hal::i2c::config::Config::new().baudrate(400_000.Hz()),
+    let display = match slint_platform::init_display(slint_platform::EspPlatformConfig {
+        #[cfg(feature = "gamma")]
+        gamma_correction: Some(2.2),
+        ..Default::default()
+    }) {
+        Ok(display) => display,
+        Err(err) => {
+            // Nothing can be shown
+            log::error!("{err}");
+            return;
+        }
+    };
+
+    // The background of the splash screen
+    if let Err(err) = display.fill(slint::platform::software_renderer::TargetPixel::from_rgb(
+        0xfa, 0xfa, 0xfa,
+    )) {
+        log::warn!("Cannot fill the screen: {err}");
+    }
+
+    #[cfg(not(feature = "sim"))]
+    {
+        if let Err(err) = storage::mount() {
+            log::warn!("Cannot mount the storage partition: {err}");
+        }
+        if let Err(err) = raw_flash::check_assets() {
+            log::warn!("Cannot read the assets partition: {err}");
+        }
+
+        // Shown until the splash screen is drawn, decoded straight into the frame buffer since
+        // there isn't enough PSRAM left for a decoded copy of a full screen image
+        let boot_image_path: std::path::PathBuf =
+            [storage::MOUNT_POINT, "boot.jpg"].iter().collect();
+        if let Ok(boot_image) = std::fs::read(&boot_image_path) {
+            let drawn = display.draw(|frame_buffer, width| {
+                if let Err(err) = jpeg::draw_jpeg_streaming(&boot_image, frame_buffer, width) {
+                    log::warn!("Cannot draw {}: {err}", boot_image_path.display());
+                }
+            });
+            if let Err(err) = drawn {
+                log::warn!("Cannot draw {}: {err}", boot_image_path.display());
+            }
+        }
+    }
+
+    // Shared by the touch controller and `read-sensor`
+    #[cfg(not(feature = "sim"))]
+    let i2c: slint_platform::SharedI2c = std::rc::Rc::new(
+        esp_idf_svc::hal::i2c::I2cDriver::new(
+            p.i2c0,
+            p.pins.gpio8,
+            p.pins.gpio9,
+            &esp_idf_svc::hal::i2c::config::Config::new().baudrate(400_000.Hz()),
+        )
+        .unwrap()
+        .into(),
+    );
+    #[cfg(not(feature = "sim"))]
+    let touch_initialized = slint_platform::init_touch(display, i2c.clone());
+    // The mouse stands for the touch screen
+    #[cfg(feature = "sim")]
+    let touch_initialized = slint_platform::init_touch(display);
+
+    if let Err((err, display)) = touch_initialized {
+        log::error!("{err}");
+        // Slint can't draw without the platform, the red screen shows that the firmware stopped
+        // and why. A failure to draw it is ignored, the error was logged above.
+        let _ = display.draw(|buffer, width| {
+            slint_platform::draw_message(
+                buffer,
+                width,
+                slint::platform::software_renderer::TargetPixel::from_rgb(0xb0, 0x00, 0x20),
+                format_args!("{err}"),
+            );
+        });
+        return;
+    }
+
+    #[cfg(not(feature = "sim"))]
+    if let Some(message) = last_panic {
+        let error_screen = ErrorScreen::new().unwrap();
+        error_screen.set_message(message.into());
+        error_screen.on_continue_clicked(|| slint::quit_event_loop().unwrap());
+        error_screen.run().unwrap();
+    }
+
+    // There is a single window, which shows the last component created, so the main window is
+    // only created once the splash screen is done
+    let splash = SplashScreen::new().unwrap();
+    splash.on_done(|| slint::quit_event_loop().unwrap());
+    splash.run().unwrap();
+    drop(splash);
+
+    let main_window = MainWindow::new().unwrap();
+    error_boundary::init(&main_window);
+
+    #[cfg(not(feature = "sim"))]
+    {
+        let timer =
+            esp_idf_svc::hal::timer::TimerDriver::new(p.timer00, &Default::default()).unwrap();
+        // Taken by the task while it runs, and put back for the next retry
+        let timer = std::rc::Rc::new(core::cell::Cell::new(Some(timer)));
+
+        error_boundary::spawn(&main_window, move || {
+            let timer = timer.clone();
+            async move {
+                let Some(mut driver) = timer.take() else {
+                    return Ok(());
+                };
+                let mut result = Ok(());
+                for _ in 0..5 {
+                    result = driver.delay(5 * driver.tick_hz()).await;
+                    if result.is_err() {
+                        break;
+                    }
+                    log::debug!("The timer task waited 5 seconds");
+                }
+                timer.set(Some(driver));
+                result
+            }
+        });
+    }
+
+    number_format::init(&main_window, number_format::Locale::from_language("en"));
+    notification::init(&main_window);
+    status_bar::init(&main_window);
+    dialog::init(&main_window);
+    signature::init(&main_window);
+    date_picker::init(&main_window);
+    keyboard::init(&main_window);
+    map::init(&main_window);
+    chips::init(&main_window);
+    tree::init(&main_window);
+    thumbnails::init(&main_window);
+    navigation::init(
+        &main_window,
+        #[cfg(not(feature = "sim"))]
+        esp_idf_svc::hal::gpio::PinDriver::input(p.pins.gpio15).unwrap(),
+    );
+    search_bar::init(&main_window);
+    let record_page = timeline::init(&main_window);
+    main_window.on_page_changed(move |page| {
+        #[cfg(not(feature = "sim"))]
+        diagnostics::page_changed(page.clone());
+        record_page(page);
+    });
+    avatar::init(&main_window);
+    camera::init(&main_window, Default::default());
+    data_table::init(&main_window);
+    system_info::init(&main_window);
+    video::init(&main_window);
+    heat_map::init(&main_window);
+    #[cfg(not(feature = "sim"))]
+    if let Err(err) = system_events::init(&main_window) {
+        log::warn!("Cannot watch the system events: {err}");
+    }
+    #[cfg(not(feature = "sim"))]
+    wifi_signal::init(&main_window);
+    animated_icon::init(&main_window);
+    animated_background::init(&main_window);
+    canvas::init(&main_window);
+    log_view::init(&main_window);
+    #[cfg(not(feature = "sim"))]
+    diagnostics::init(&main_window);
+    #[cfg(not(feature = "sim"))]
+    i2c_sensor::init(&main_window, i2c);
+    #[cfg(feature = "accessibility")]
+    accessibility::init(
+        &main_window,
+        esp_idf_svc::hal::uart::UartTxDriver::new(
+            p.uart0,
+            p.pins.gpio43,
+            Option::<esp_idf_svc::hal::gpio::AnyIOPin>::None,
+            Option::<esp_idf_svc::hal::gpio::AnyIOPin>::None,
+            &Default::default(),
+        )
+        .unwrap(),
+    );
+
+    #[cfg(not(feature = "sim"))]
+    let timer_service = esp_idf_svc::timer::EspTaskTimerService::new().unwrap();
+
+    // The simulator has no NVS to store the PIN in, the pad never unlocks, nor the split ratio,
+    // the preferences, the equalizer, the favorites and the device ID
+    #[cfg(not(feature = "sim"))]
+    {
+        let nvs_partition = esp_idf_svc::nvs::EspDefaultNvsPartition::take().unwrap();
+        if let Err(err) = device_id::init(&main_window, nvs_partition.clone()) {
+            log::warn!("Cannot read the device ID: {err}");
+        }
+        if let Err(err) = pin::init(&main_window, nvs_partition.clone(), &timer_service) {
+            log::warn!("Cannot read the PIN: {err}");
+        }
+        if let Err(err) = split_view::init(&main_window, nvs_partition.clone()) {
+            log::warn!("Cannot read the split ratio: {err}");
+        }
+        if let Err(err) = switch_list::init(&main_window, nvs_partition.clone()) {
+            log::warn!("Cannot read the preferences: {err}");
+        }
+        if let Err(err) = slider_list::init(&main_window, nvs_partition.clone()) {
+            log::warn!("Cannot read the equalizer: {err}");
+        }
+        if let Err(err) = drag_and_drop_list::init(&main_window, nvs_partition.clone()) {
+            log::warn!("Cannot read the order of the favorites: {err}");
+        }
+        // The calibration data of the radio is kept in NVS
+        let scanner = esp_idf_svc::eventloop::EspSystemEventLoop::take()
+            .and_then(|sysloop| network_list::Scanner::new(p.modem, sysloop, nvs_partition));
+        match scanner {
+            Ok(scanner) => network_list::init(&main_window, scanner),
+            Err(err) => log::warn!("Cannot start the Wi-Fi: {err}"),
+        }
+    }
+    #[cfg(feature = "sim")]
+    {
+        switch_list::init(&main_window);
+        slider_list::init(&main_window);
+        drag_and_drop_list::init(&main_window);
+        network_list::init(&main_window, Default::default());
+    }
+
+    #[cfg(feature = "status-led")]
+    {
+        #[cfg(all(feature = "status-led-rgb", not(status_led_rgb)))]
+        log::warn!("The microphone takes the pins of the RGB status LED, driving a WS2812 instead");
+        #[cfg(not(status_led_rgb))]
+        let led = status_led::Led::ws2812(p.rmt.channel0, p.pins.gpio16);
+        #[cfg(status_led_rgb)]
+        let led = status_led::Led::rgb(
+            p.ledc.timer0,
+            (p.ledc.channel0, p.pins.gpio11),
+            (p.ledc.channel1, p.pins.gpio12),
+            (p.ledc.channel2, p.pins.gpio13),
+        );
+        if let Err(err) = led.and_then(|led| status_led::init(&main_window, led, &timer_service)) {
+            log::warn!("Cannot drive the status LED: {err}");
+        }
+    }
+
+    #[cfg(all(feature = "microphone", not(feature = "sim")))]
+    match level_meter::Microphone::new(p.i2s0, p.pins.gpio11, p.pins.gpio13, p.pins.gpio12) {
+        Ok(microphone) => level_meter::init(&main_window, microphone),
+        Err(err) => log::warn!("Cannot capture the sound of the microphone: {err}"),
+    }
+    #[cfg(any(not(feature = "microphone"), feature = "sim"))]
+    level_meter::init(&main_window, Default::default());
+
+    #[cfg(not(feature = "sim"))]
+    let process_value_sensor = esp_idf_svc::hal::adc::oneshot::AdcChannelDriver::new(
+        esp_idf_svc::hal::adc::oneshot::AdcDriver::new(p.adc1).unwrap(),
+        p.pins.gpio6,
+        &esp_idf_svc::hal::adc::oneshot::config::AdcChannelConfig {
+            attenuation: esp_idf_svc::hal::adc::attenuation::DB_11,
+            ..Default::default()
+        },
     )
     .unwrap();
+    #[cfg(feature = "sim")]
+    let process_value_sensor = pid::ProcessValueSensor::default();
+    line_chart::init(&main_window);
+    bar_chart::init(&main_window);
+    pid::init(&main_window, process_value_sensor);
+    sensor_dashboard::init(&main_window);
 
-    slint_platform::init(touch_i2c);
+    let gesture = main_window.global::<TouchGesture>();
+    gesture.on_velocity_x(|| slint_platform::touch_velocity().0);
+    gesture.on_velocity_y(|| slint_platform::touch_velocity().1);
+    gesture.on_pinch_distance(|| slint_platform::pinch().map_or(0., |pinch| pinch.distance));
+    gesture.on_pinch_center_x(|| slint_platform::pinch().map_or(0., |pinch| pinch.center.x));
+    gesture.on_pinch_center_y(|| slint_platform::pinch().map_or(0., |pinch| pinch.center.y));
+    slint_platform::set_dwell_handler({
+        let main_window = main_window.as_weak();
+        move |position| {
+            let Some(main_window) = main_window.upgrade() else {
+                return;
+            };
+            let gesture = main_window.global::<TouchGesture>();
+            gesture.set_dwelling(position.is_some());
+            if let Some(position) = position {
+                gesture.set_dwell_x(position.x);
+                gesture.set_dwell_y(position.y);
+            }
+        }
+    });
 
-    let mut timer = esp_idf_svc::hal::timer::TimerDriver::new(p.timer00, &Default::default()).unwrap();
+    main_window.set_items(
+        std::rc::Rc::new(slint::VecModel::from(vec![slint::SharedString::from(
+            "Pull down to refresh",
+        )]))
+        .into(),
+    );
+    main_window.on_refresh_items({
+        let main_window = main_window.as_weak();
+        move || {
+            let fetched_window = main_window.clone();
+            // Pretend that the new items are fetched from the network
+            let spawned = slint_platform::spawn_low_priority(async move {
+                // The task only runs this future, which can block it
+                std::thread::sleep(core::time::Duration::from_secs(1));
+                slint::invoke_from_event_loop(move || {
+                    let Some(main_window) = fetched_window.upgrade() else {
+                        return;
+                    };
+                    let items = main_window.get_items();
+                    if let Some(items) = items
+                        .as_any()
+                        .downcast_ref::<slint::VecModel<slint::SharedString>>()
+                    {
+                        items.push(format!("Item {}", items.row_count()).into());
+                    }
+                    main_window.set_refreshing(false);
+                })
+                .unwrap();
+                notification::show_notification("The list was refreshed", 2000);
+            });
+            if let Err(err) = spawned {
+                log::error!("Cannot refresh the items: {err}");
+                main_window.unwrap().set_refreshing(false);
+            }
+        }
+    });
 
-    slint::spawn_local(async move {
-        for _ in 0..5 {
-            timer.delay(5 * timer.tick_hz()).await.unwrap();
-            eprintln!("hello from future");
+    let carousel_colors = [
+        slint::Color::from_rgb_u8(0x1e, 0x88, 0xe5),
+        slint::Color::from_rgb_u8(0x43, 0xa0, 0x47),
+        slint::Color::from_rgb_u8(0xfb, 0x8c, 0x00),
+    ];
+    let carousel_pages = std::rc::Rc::new(carousel::CarouselModel::new(
+        ["Swipe left", "Or tap the dots", "Last page"]
+            .iter()
+            .zip(carousel_colors)
+            .map(|(title, background)| CarouselPage {
+                title: (*title).into(),
+                background,
+                ..Default::default()
+            })
+            .collect(),
+    ));
+    main_window.set_carousel_pages(carousel_pages.clone().into());
+    main_window.on_add_carousel_page({
+        let carousel_pages = carousel_pages.clone();
+        move || {
+            let count = carousel_pages.row_count();
+            carousel_pages.push_page(CarouselPage {
+                title: format!("Page {}", count + 1).into(),
+                background: carousel_colors[count % carousel_colors.len()],
+                ..Default::default()
+            })
+        }
+    });
+    main_window.on_remove_carousel_page({
+        let main_window = main_window.as_weak();
+        move |index| {
+            let main_window = main_window.clone();
+            let carousel_pages = carousel_pages.clone();
+            slint::spawn_local(async move {
+                let choice = dialog::show_dialog(
+                    "Remove page",
+                    "Do you want to remove the current page?",
+                    &["Cancel", "Remove"],
+                )
+                .await;
+                if choice == 1 {
+                    carousel_pages.remove_page(index as usize);
+                    main_window
+                        .unwrap()
+                        .set_carousel_page(index.min(carousel_pages.row_count() as i32 - 1));
+                }
+            })
+            .unwrap();
         }
-    }).unwrap();
+    });
 
-    MainWindow::new().unwrap().run().unwrap();
+    main_window.set_accordion_sections(
+        std::rc::Rc::new(slint::VecModel::from(vec![
+            AccordionSection {
+                title: "Display".into(),
+                text: "Brightness, sleep timeout and orientation of the screen.".into(),
+            },
+            AccordionSection {
+                title: "Network".into(),
+                text: "Wi-Fi network to join, and whether to use DHCP or a static address.".into(),
+            },
+            AccordionSection {
+                title: "Sound".into(),
+                text: "Volume of the buzzer, and whether taps on the screen make a click.".into(),
+            },
+            AccordionSection {
+                title: "About".into(),
+                text: "Waveshare ESP32-S3-Touch-LCD-5 running a Slint UI.".into(),
+            },
+        ]))
+        .into(),
+    );
+
+    let photo_path: std::path::PathBuf = [storage::MOUNT_POINT, "photo.rgb"].iter().collect();
+    match storage::read_rgb565_image(&photo_path, 800, 480) {
+        Ok(photo) => main_window.set_photo(photo),
+        Err(err) => log::warn!("Cannot read {}: {err}", photo_path.display()),
+    }
+
+    let tags = std::rc::Rc::new(slint::VecModel::from(
+        [
+            "Sensor",
+            "Alarm",
+            "Kitchen",
+            "Outdoor",
+            "Battery low",
+            "Wi-Fi",
+        ]
+        .map(slint::SharedString::from)
+        .to_vec(),
+    ));
+    main_window.set_tags(tags.clone().into());
+    main_window.on_add_tag({
+        let tags = tags.clone();
+        move || tags.push(format!("Tag {}", tags.row_count() + 1).into())
+    });
+    main_window.on_remove_tag(move |index| {
+        if (index as usize) < tags.row_count() {
+            tags.remove(index as usize);
+        }
+    });
+
+    main_window.on_signature_signed({
+        let main_window = main_window.as_weak();
+        move |svg| {
+            log::info!("Signature: {svg}");
+            let icon = main_window
+                .upgrade()
+                .map(|window| window.global::<NotificationState>().get_check_icon());
+            notification::show_notification_with_icon("The signature was saved", icon, 2000);
+        }
+    });
+
+    main_window.global::<ContextMenu>().on_item_selected({
+        let main_window = main_window.as_weak();
+        move |index| {
+            let items = main_window.unwrap().global::<ContextMenu>().get_items();
+            if let Some(item) = items.row_data(index as usize) {
+                notification::show_notification(&format!("{item} was selected"), 2000);
+            }
+        }
+    });
+
+    main_window.on_remove_last_character(|text| {
+        let mut text = text.to_string();
+        text.pop();
+        text.into()
+    });
+
+    if let Err(err) = main_window.run() {
+        // The panel may draw again once it is reset, the other errors would happen again
+        #[cfg(not(feature = "sim"))]
+        if let Some(
+            slint_platform::EspPlatformError::PanelInit(_)
+            | slint_platform::EspPlatformError::VsyncTimeout,
+        ) = slint_platform::last_error()
+        {
+            log::error!("{err}, restarting");
+            esp_idf_svc::hal::reset::restart();
+        }
+        panic!("The event loop failed: {err}");
+    }
 }
@@ -0,0 +1,180 @@
+//! Loads the tiles of the offline map from the storage partition, see `ui/map_view.slint`.
+
+use std::cell::RefCell;
+use std::num::NonZeroUsize;
+use std::path::PathBuf;
+use std::task::Poll;
+
+use lru::LruCache;
+use slint::ComponentHandle;
+
+use crate::{MainWindow, MapTile, MapTiles};
+
+/// Width and height of the tiles in physical pixels
+const TILE_SIZE: u32 = 256;
+/// Enough for all the tiles that intersect the screen
+const CACHE_SIZE: usize = 16;
+/// Directory of the tiles on the storage partition, see the README
+const TILE_DIRECTORY: &str = "map";
+
+/// The zoom level, column and row of a tile
+type TileKey = (i32, i32, i32);
+
+/// Keeps the most recently shown tiles decoded in memory, and loads the other visible tiles one
+/// at a time, letting the event loop render between them.
+struct TileLoader {
+    window: slint::Weak<MainWindow>,
+    /// Tiles that couldn't be loaded are cached as empty images, so they aren't read again
+    cache: LruCache<TileKey, slint::Image>,
+    /// Tiles that intersect the viewport, from the center outwards
+    visible: Vec<TileKey>,
+    /// Tiles in the model of the view
+    shown: Vec<TileKey>,
+    /// The task of [`load_missing_tiles`], cancelled once the map isn't shown anymore
+    loading: Option<slint::JoinHandle<Option<()>>>,
+}
+
+impl Default for TileLoader {
+    fn default() -> Self {
+        Self {
+            window: Default::default(),
+            cache: LruCache::new(NonZeroUsize::new(CACHE_SIZE).unwrap()),
+            visible: Vec::new(),
+            shown: Vec::new(),
+            loading: None,
+        }
+    }
+}
+
+impl TileLoader {
+    /// The viewport is in tiles at `zoom`
+    fn set_viewport(&mut self, zoom: i32, left: f32, top: f32, width: f32, height: f32) {
+        let count = 1 << zoom.clamp(0, 30);
+        let columns = (left.floor() as i32).max(0)..((left + width).ceil() as i32).min(count);
+        let rows = (top.floor() as i32).max(0)..((top + height).ceil() as i32).min(count);
+        let (center_x, center_y) = (left + width / 2., top + height / 2.);
+        let distance =
+            |&(_, x, y): &TileKey| (x as f32 + 0.5 - center_x).hypot(y as f32 + 0.5 - center_y);
+        self.visible = columns
+            .flat_map(|x| rows.clone().map(move |y| (zoom, x, y)))
+            .collect();
+        self.visible
+            .sort_by(|a, b| distance(a).total_cmp(&distance(b)));
+        self.update_tiles();
+
+        let loading = self
+            .loading
+            .as_ref()
+            .is_some_and(|task| !task.is_finished());
+        if !loading && self.next_missing().is_some() {
+            let Some(window) = self.window.upgrade() else {
+                return;
+            };
+            let token = crate::navigation::page_token(&window);
+            match crate::slint_platform::spawn_cancellable(load_missing_tiles(), token) {
+                Ok(task) => self.loading = Some(task),
+                Err(err) => log::error!("Cannot load the map tiles: {err}"),
+            }
+        }
+    }
+
+    fn next_missing(&self) -> Option<TileKey> {
+        self.visible
+            .iter()
+            .copied()
+            .find(|key| !self.cache.contains(key))
+    }
+
+    /// Shows the visible tiles that are loaded
+    fn update_tiles(&mut self) {
+        let Some(window) = self.window.upgrade() else {
+            return;
+        };
+        let shown: Vec<TileKey> = self
+            .visible
+            .iter()
+            .copied()
+            .filter(|key| self.cache.contains(key))
+            .collect();
+        if shown == self.shown {
+            return;
+        }
+        let tiles: Vec<MapTile> = shown
+            .iter()
+            .map(|key| MapTile {
+                column: key.1,
+                row: key.2,
+                // Also marks the tile as the most recently used
+                image: self.cache.get(key).unwrap().clone(),
+            })
+            .collect();
+        window
+            .global::<MapTiles>()
+            .set_tiles(std::rc::Rc::new(slint::VecModel::from(tiles)).into());
+        self.shown = shown;
+    }
+}
+
+thread_local! {
+    // Only accessed from the event loop thread
+    static LOADER: RefCell<TileLoader> = Default::default();
+}
+
+/// Must be called once before the map is shown, after the storage is mounted
+pub fn init(window: &MainWindow) {
+    window
+        .global::<MapTiles>()
+        .on_viewport_changed(|zoom, left, top, width, height| {
+            LOADER.with(|loader| {
+                loader
+                    .borrow_mut()
+                    .set_viewport(zoom, left, top, width, height)
+            })
+        });
+    LOADER.with(|loader| loader.borrow_mut().window = window.as_weak());
+}
+
+/// Loads the visible tiles that aren't in the cache until there are none left
+async fn load_missing_tiles() {
+    while let Some(key) = LOADER.with(|loader| loader.borrow().next_missing()) {
+        // Not borrowing the loader while reading, which takes a while
+        let image = read_tile(key).unwrap_or_else(|err| {
+            log::warn!("Cannot load the map tile {key:?}: {err}");
+            slint::Image::default()
+        });
+        LOADER.with(|loader| {
+            let mut loader = loader.borrow_mut();
+            loader.cache.put(key, image);
+            loader.update_tiles();
+        });
+        yield_now().await;
+    }
+}
+
+/// Reads the raw RGB565 pixels of a tile, in little endian, from `{z}/{x}/{y}.rgb`
+fn read_tile((z, x, y): TileKey) -> std::io::Result<slint::Image> {
+    let path: PathBuf = [
+        crate::storage::MOUNT_POINT,
+        TILE_DIRECTORY,
+        &z.to_string(),
+        &x.to_string(),
+        &format!("{y}.rgb"),
+    ]
+    .iter()
+    .collect();
+    crate::storage::read_rgb565_image(&path, TILE_SIZE, TILE_SIZE)
+}
+
+/// Lets the event loop process the other events and render before the task continues
+pub fn yield_now() -> impl std::future::Future<Output = ()> {
+    let mut yielded = false;
+    std::future::poll_fn(move |cx| {
+        if yielded {
+            Poll::Ready(())
+        } else {
+            yielded = true;
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        }
+    })
+}
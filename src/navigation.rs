@@ -0,0 +1,159 @@
+//! The navigation stack shown by the breadcrumb in the header, see `ui/breadcrumb.slint`. The
+//! back button and swiping the breadcrumb to the right go back to the previous page.
+
+use std::cell::RefCell;
+use std::sync::{Arc, Mutex};
+#[cfg(not(feature = "sim"))]
+use std::time::Duration;
+
+#[cfg(not(feature = "sim"))]
+use esp_idf_svc::hal::gpio::{Gpio15, Input, PinDriver, Pull};
+use slint::{ComponentHandle, Model, SharedString};
+
+use crate::slint_platform::CancellationToken;
+use crate::{MainWindow, Navigation};
+
+/// A push button between the pin and the ground, the pin isn't used by anything else
+#[cfg(not(feature = "sim"))]
+pub type BackButton = PinDriver<'static, Gpio15, Input>;
+
+/// The oldest pages are forgotten so that the breadcrumb fits in the header
+const MAX_DEPTH: usize = 4;
+/// Also debounces the button
+#[cfg(not(feature = "sim"))]
+const POLL_PERIOD: Duration = Duration::from_millis(20);
+
+/// Labels of the pages that were opened, the current one last. It is never empty.
+type Stack = Arc<Mutex<Vec<String>>>;
+
+thread_local! {
+    // Only accessed from the event loop thread
+    static PAGE_TOKEN: RefCell<(i32, CancellationToken)> = Default::default();
+}
+
+/// Must be called once, with the page that is shown first. The simulator has no back button.
+pub fn init(window: &MainWindow, #[cfg(not(feature = "sim"))] back_button: BackButton) {
+    let first_page = window
+        .get_pages()
+        .row_data(window.get_current_page() as usize)
+        .unwrap_or_default();
+    let stack: Stack = Arc::new(Mutex::new(vec![first_page.into()]));
+    show(window, &stack);
+
+    let navigation = window.global::<Navigation>();
+    navigation.on_page_opened({
+        let window = window.as_weak();
+        let stack = stack.clone();
+        move |page| {
+            {
+                let mut stack = stack.lock().unwrap();
+                // Going back when the page was already opened, so that the pages don't repeat
+                match stack.iter().position(|opened| *opened == *page) {
+                    Some(depth) => stack.truncate(depth + 1),
+                    None => stack.push(page.into()),
+                }
+                if stack.len() > MAX_DEPTH {
+                    let excess = stack.len() - MAX_DEPTH;
+                    stack.drain(..excess);
+                }
+            }
+            show(&window.unwrap(), &stack);
+        }
+    });
+    navigation.on_navigate_to({
+        let window = window.as_weak();
+        let stack = stack.clone();
+        move |depth| {
+            stack.lock().unwrap().truncate(depth.max(0) as usize + 1);
+            show(&window.unwrap(), &stack);
+        }
+    });
+    navigation.on_current_page_changed({
+        let window = window.as_weak();
+        move || {
+            page_token(&window.unwrap());
+        }
+    });
+    navigation.on_back({
+        let window = window.as_weak();
+        let stack = stack.clone();
+        move || {
+            if pop(&stack) {
+                show(&window.unwrap(), &stack);
+            }
+        }
+    });
+
+    #[cfg(not(feature = "sim"))]
+    watch_back_button(window, back_button, stack);
+}
+
+/// Cancelled once the current page isn't shown anymore, for the tasks that it started to stop
+/// with it, see [`spawn_cancellable`](crate::slint_platform::spawn_cancellable). The token of the
+/// last page is cancelled here too, if the current page changed and its handler didn't run yet.
+pub fn page_token(window: &MainWindow) -> CancellationToken {
+    let page = window.get_current_page();
+    PAGE_TOKEN.with(|token| {
+        let mut token = token.borrow_mut();
+        if token.0 != page {
+            token.1.cancel();
+            *token = (page, CancellationToken::default());
+        }
+        token.1.clone()
+    })
+}
+
+/// Goes back when the button is pressed
+#[cfg(not(feature = "sim"))]
+fn watch_back_button(window: &MainWindow, mut back_button: BackButton, stack: Stack) {
+    if let Err(err) = back_button.set_pull(Pull::Up) {
+        log::warn!("Cannot enable the pull-up of the back button: {err}");
+    }
+    let window = window.as_weak();
+    std::thread::spawn(move || {
+        let mut was_pressed = false;
+        loop {
+            std::thread::sleep(POLL_PERIOD);
+            let pressed = back_button.is_low();
+            if pressed && !was_pressed && pop(&stack) {
+                let window = window.clone();
+                let stack = stack.clone();
+                let result = slint::invoke_from_event_loop(move || {
+                    if let Some(window) = window.upgrade() {
+                        show(&window, &stack);
+                    }
+                });
+                // The event loop was quit
+                if result.is_err() {
+                    break;
+                }
+            }
+            was_pressed = pressed;
+        }
+    });
+}
+
+/// Removes the current page unless it is the only one, and returns whether it did
+fn pop(stack: &Stack) -> bool {
+    let mut stack = stack.lock().unwrap();
+    if stack.len() > 1 {
+        stack.pop();
+        true
+    } else {
+        false
+    }
+}
+
+/// Shows the path of the stack in the breadcrumb, and the last page
+fn show(window: &MainWindow, stack: &Stack) {
+    let path: Vec<SharedString> = stack.lock().unwrap().iter().map(Into::into).collect();
+    if let Some(index) = path
+        .last()
+        .and_then(|page| window.get_pages().iter().position(|label| label == *page))
+    {
+        window.set_current_page(index as i32);
+    }
+    window
+        .global::<Navigation>()
+        .set_path(std::rc::Rc::new(slint::VecModel::from(path)).into());
+}
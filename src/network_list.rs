@@ -0,0 +1,141 @@
+//! Scans the Wi-Fi networks around the board when the Scan button of the networks page is
+//! pressed, see `ui/network_list.slint`, and sets `NetworkScan.networks`, strongest first.
+//!
+//! The simulator has no Wi-Fi: a scanner stands for it, which finds a few made-up networks.
+
+use std::sync::mpsc;
+
+#[cfg(not(feature = "sim"))]
+use esp_idf_svc::eventloop::EspSystemEventLoop;
+#[cfg(not(feature = "sim"))]
+use esp_idf_svc::hal::{modem::WifiModemPeripheral, peripheral::Peripheral};
+#[cfg(not(feature = "sim"))]
+use esp_idf_svc::nvs::EspDefaultNvsPartition;
+#[cfg(not(feature = "sim"))]
+use esp_idf_svc::sys::EspError;
+#[cfg(not(feature = "sim"))]
+use esp_idf_svc::wifi::{AuthMethod, ClientConfiguration, Configuration, EspWifi};
+use slint::ComponentHandle;
+
+use crate::{MainWindow, NetworkEntry, NetworkScan};
+
+/// A network found by a scan
+struct Network {
+    ssid: String,
+    /// In dBm
+    rssi: i8,
+    secured: bool,
+}
+
+/// The Wi-Fi driver, started as a station that doesn't join any network
+#[cfg(not(feature = "sim"))]
+pub struct Scanner {
+    wifi: EspWifi<'static>,
+}
+
+#[cfg(not(feature = "sim"))]
+impl Scanner {
+    pub fn new<M: WifiModemPeripheral>(
+        modem: impl Peripheral<P = M> + 'static,
+        sysloop: EspSystemEventLoop,
+        nvs_partition: EspDefaultNvsPartition,
+    ) -> Result<Self, EspError> {
+        let mut wifi = EspWifi::new(modem, sysloop, Some(nvs_partition))?;
+        wifi.set_configuration(&Configuration::Client(ClientConfiguration::default()))?;
+        wifi.start()?;
+        Ok(Self { wifi })
+    }
+
+    /// Blocks for the 2 or 3 seconds that it takes to go through all the channels
+    fn scan(&mut self) -> Result<Vec<Network>, EspError> {
+        Ok(self
+            .wifi
+            .scan()?
+            .into_iter()
+            .map(|access_point| Network {
+                ssid: access_point.ssid.as_str().into(),
+                rssi: access_point.signal_strength,
+                secured: access_point
+                    .auth_method
+                    .is_some_and(|auth_method| auth_method != AuthMethod::None),
+            })
+            .collect())
+    }
+}
+
+/// Stands for the Wi-Fi driver: a few networks, whose signal gets weaker at each scan
+#[cfg(feature = "sim")]
+#[derive(Default)]
+pub struct Scanner {
+    scans: i8,
+}
+
+#[cfg(feature = "sim")]
+impl Scanner {
+    fn scan(&mut self) -> Result<Vec<Network>, std::convert::Infallible> {
+        // As long as a scan of all the channels
+        std::thread::sleep(std::time::Duration::from_secs(2));
+        self.scans = (self.scans + 1) % 10;
+        let network = |ssid: &str, rssi: i8, secured| Network {
+            ssid: ssid.into(),
+            rssi: rssi - self.scans,
+            secured,
+        };
+        Ok(vec![
+            network("Workshop", -48, true),
+            network("Guests", -63, false),
+            network("Sensors-2.4G", -71, true),
+            network("Neighbours", -84, true),
+        ])
+    }
+}
+
+/// Scans in a task of its own, once per press of the Scan button. A press while scanning is
+/// ignored, since the button is disabled meanwhile.
+pub fn init(window: &MainWindow, mut scanner: Scanner) {
+    let (requests, requested) = mpsc::sync_channel(1);
+    let weak = window.as_weak();
+    window.global::<NetworkScan>().on_scan_requested(move || {
+        if requests.try_send(()).is_ok() {
+            weak.unwrap().global::<NetworkScan>().set_scanning(true);
+        }
+    });
+
+    let window = window.as_weak();
+    let spawned = crate::slint_platform::spawn_low_priority(async move {
+        // The task only runs this future, which can block it
+        while requested.recv().is_ok() {
+            let mut networks = match scanner.scan() {
+                Ok(networks) => networks,
+                Err(err) => {
+                    log::warn!("Cannot scan the Wi-Fi networks: {err}");
+                    Vec::new()
+                }
+            };
+            networks.sort_by_key(|network| core::cmp::Reverse(network.rssi));
+            let window = window.clone();
+            let scanned = slint::invoke_from_event_loop(move || {
+                if let Some(window) = window.upgrade() {
+                    let networks: Vec<NetworkEntry> = networks
+                        .into_iter()
+                        .map(|network| NetworkEntry {
+                            ssid: network.ssid.into(),
+                            rssi: network.rssi.into(),
+                            secured: network.secured,
+                        })
+                        .collect();
+                    let scan = window.global::<NetworkScan>();
+                    scan.set_networks(std::rc::Rc::new(slint::VecModel::from(networks)).into());
+                    scan.set_scanning(false);
+                }
+            });
+            // The event loop was quit
+            if scanned.is_err() {
+                break;
+            }
+        }
+    });
+    if let Err(err) = spawned {
+        log::error!("Cannot start scanning the Wi-Fi networks: {err}");
+    }
+}
@@ -0,0 +1,83 @@
+//! Toast messages shown on top of the UI. See `ui/notification.slint`.
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+
+use slint::ComponentHandle;
+
+use crate::{MainWindow, NotificationState};
+
+struct PendingNotification {
+    message: slint::SharedString,
+    icon: Option<slint::Image>,
+    duration_ms: u32,
+}
+
+#[derive(Default)]
+struct NotificationQueue {
+    window: slint::Weak<MainWindow>,
+    pending: VecDeque<PendingNotification>,
+}
+
+thread_local! {
+    // Only accessed from the event loop thread
+    static QUEUE: RefCell<NotificationQueue> = Default::default();
+}
+
+/// Must be called once before any notification can be shown
+pub fn init(window: &MainWindow) {
+    window.global::<NotificationState>().on_dismissed(show_next);
+    QUEUE.with(|queue| queue.borrow_mut().window = window.as_weak());
+}
+
+/// Shows `msg` for `duration_ms` milliseconds. If a notification is already visible, this one
+/// is shown after it. This can be called from any thread.
+pub fn show_notification(msg: &str, duration_ms: u32) {
+    let message = slint::SharedString::from(msg);
+    let result = slint::invoke_from_event_loop(move || {
+        push(PendingNotification {
+            message,
+            icon: None,
+            duration_ms,
+        })
+    });
+    if let Err(err) = result {
+        log::error!("Cannot show notification {msg:?}: {err}");
+    }
+}
+
+/// Like [`show_notification`], with `icon` before the message. An image can't be sent to
+/// another thread, so this must be called from the event loop thread.
+pub fn show_notification_with_icon(msg: &str, icon: Option<slint::Image>, duration_ms: u32) {
+    push(PendingNotification {
+        message: msg.into(),
+        icon,
+        duration_ms,
+    });
+}
+
+fn push(notification: PendingNotification) {
+    QUEUE.with(|queue| queue.borrow_mut().pending.push_back(notification));
+    show_next();
+}
+
+fn show_next() {
+    QUEUE.with(|queue| {
+        let mut queue = queue.borrow_mut();
+        let Some(window) = queue.window.upgrade() else {
+            return;
+        };
+        let state = window.global::<NotificationState>();
+        // The message is only cleared once the previous notification has slid out
+        if !state.get_active() && state.get_message().is_empty() {
+            if let Some(next) = queue.pending.pop_front() {
+                state.set_message(next.message);
+                state.set_icon(next.icon.unwrap_or_default());
+                state.set_timeout(next.duration_ms.into());
+                state.set_active(true);
+            }
+        }
+        let shown = state.get_active() || !state.get_message().is_empty();
+        state.set_count((queue.pending.len() + shown as usize) as i32);
+    });
+}
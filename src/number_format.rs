@@ -0,0 +1,87 @@
+//! Locale-aware formatting of numbers, used by the UI through the `L10n` global, see
+//! `ui/l10n.slint`.
+
+use slint::ComponentHandle;
+
+use crate::{L10n, MainWindow};
+
+/// How a locale writes numbers
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Locale {
+    pub decimal_separator: char,
+    /// Inserted between each group of three digits of the integer part, if any
+    pub thousands_separator: Option<char>,
+}
+
+impl Locale {
+    /// 1,234.56
+    pub const ENGLISH: Self = Self {
+        decimal_separator: '.',
+        thousands_separator: Some(','),
+    };
+    /// 1.234,56
+    pub const GERMAN: Self = Self {
+        decimal_separator: ',',
+        thousands_separator: Some('.'),
+    };
+    /// 1 234,56, with a plain space: the fonts only have the glyphs of the characters of the
+    /// Slint code, which has no no-break space
+    pub const FRENCH: Self = Self {
+        decimal_separator: ',',
+        thousands_separator: Some(' '),
+    };
+
+    /// The locale of a language tag like `de` or `de-AT`, only looking at the language. The
+    /// unknown languages write numbers like English.
+    pub fn from_language(tag: &str) -> Self {
+        let language = tag.split(['-', '_']).next().unwrap_or_default();
+        match language.to_ascii_lowercase().as_str() {
+            "de" | "da" | "es" | "id" | "it" | "nl" | "pt" | "tr" => Self::GERMAN,
+            "cs" | "fi" | "fr" | "nb" | "pl" | "ru" | "sk" | "sv" | "uk" => Self::FRENCH,
+            _ => Self::ENGLISH,
+        }
+    }
+}
+
+/// Must be called once before the UI shows numbers. The bindings that format numbers aren't
+/// evaluated again, so the locale can't be changed afterwards.
+pub fn init(window: &MainWindow, locale: Locale) {
+    window
+        .global::<L10n>()
+        .on_format_number(move |value, decimal_places| {
+            let decimal_places = decimal_places.clamp(0, u8::MAX as i32) as u8;
+            format_number(value as f64, decimal_places, locale).into()
+        });
+}
+
+/// Rounds `val` to `decimal_places` and formats it with the separators of `locale`, e.g.
+/// `1.234,56` for 1234.56 in German
+pub fn format_number(val: f64, decimal_places: u8, locale: Locale) -> String {
+    if !val.is_finite() {
+        return val.to_string();
+    }
+    let digits = format!("{:.*}", decimal_places as usize, val.abs());
+    let (integer, fraction) = match digits.split_once('.') {
+        Some((integer, fraction)) => (integer, Some(fraction)),
+        None => (digits.as_str(), None),
+    };
+
+    let mut result = String::with_capacity(digits.len() + integer.len() / 3 + 1);
+    // Not showing -0 for the negative numbers that are rounded to zero
+    if val < 0. && digits.bytes().any(|digit| matches!(digit, b'1'..=b'9')) {
+        result.push('-');
+    }
+    for (i, digit) in integer.chars().enumerate() {
+        if i > 0 && (integer.len() - i) % 3 == 0 {
+            if let Some(separator) = locale.thousands_separator {
+                result.push(separator);
+            }
+        }
+        result.push(digit);
+    }
+    if let Some(fraction) = fraction {
+        result.push(locale.decimal_separator);
+        result.push_str(fraction);
+    }
+    result
+}
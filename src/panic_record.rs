@@ -0,0 +1,89 @@
+//! Keeps the message of a panic across the restart that follows it, in the RTC memory that isn't
+//! initialized at boot, so that the next boot shows it on the `ErrorScreen` of
+//! `ui/error_screen.slint` before the splash screen. The logs of the panic are only on the UART.
+//! Until the restart, the message is also written on the screen, without Slint.
+
+use esp_idf_svc::sys::{esp_restart, xPortInIsrContext};
+
+/// Tells a record from the random content of the RTC memory after a power on
+const MAGIC: u32 = 0x5041_4e43;
+/// The longer messages are truncated
+const MAX_MESSAGE_LEN: usize = 256;
+
+#[repr(C)]
+struct PanicRecord {
+    magic: u32,
+    len: u32,
+    message: [u8; MAX_MESSAGE_LEN],
+}
+
+// The initial value is never written, the section keeps the content of the last boot
+#[link_section = ".rtc_noinit"]
+static mut RECORD: PanicRecord = PanicRecord {
+    magic: 0,
+    len: 0,
+    message: [0; MAX_MESSAGE_LEN],
+};
+
+/// Formats into the record, without allocating
+struct RecordWriter<'a> {
+    record: &'a mut PanicRecord,
+}
+
+impl core::fmt::Write for RecordWriter<'_> {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        let len = self.record.len as usize;
+        let count = s.len().min(MAX_MESSAGE_LEN - len);
+        self.record.message[len..len + count].copy_from_slice(&s.as_bytes()[..count]);
+        self.record.len += count as u32;
+        Ok(())
+    }
+}
+
+/// # Safety
+///
+/// Nothing else may access the record meanwhile
+unsafe fn store(message: core::fmt::Arguments) {
+    let record = &mut *core::ptr::addr_of_mut!(RECORD);
+    record.magic = 0;
+    record.len = 0;
+    let _ = core::fmt::write(&mut RecordWriter { record }, message);
+    record.magic = MAGIC;
+}
+
+/// Stores `message` for [`take`] and restarts at once. It doesn't allocate, lock or log, so it
+/// can be called from an interrupt handler, where a panic can't be handled otherwise.
+///
+/// # Safety
+///
+/// Nothing else may access the record meanwhile, e.g. a panic of another task
+pub unsafe fn restart_with_message(message: core::fmt::Arguments) -> ! {
+    store(message);
+    esp_restart()
+}
+
+/// Sets the panic hook that stores the message of the panics. In an interrupt handler, it
+/// restarts with [`restart_with_message`] instead of logging the panic, which allocates.
+pub fn install() {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| unsafe {
+        if xPortInIsrContext() != 0 {
+            restart_with_message(format_args!("{info}"));
+        }
+        store(format_args!("{info}"));
+        crate::slint_platform::draw_panic_message(format_args!("{info}"));
+        default_hook(info);
+    }));
+}
+
+/// The message of the panic before the last restart, only once
+pub fn take() -> Option<String> {
+    // Called at boot, before any other task can panic
+    let record = unsafe { &mut *core::ptr::addr_of_mut!(RECORD) };
+    if record.magic != MAGIC {
+        return None;
+    }
+    record.magic = 0;
+    let len = (record.len as usize).min(MAX_MESSAGE_LEN);
+    Some(String::from_utf8_lossy(&record.message[..len]).into_owned())
+}
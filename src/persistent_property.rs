@@ -0,0 +1,77 @@
+//! A value of the UI that is kept in NVS across restarts, e.g. the ratio of the `SplitView` of
+//! `ui/split_view.slint`.
+
+use std::marker::PhantomData;
+
+use esp_idf_svc::nvs::{EspDefaultNvs, EspDefaultNvsPartition, EspNvs};
+use esp_idf_svc::sys::EspError;
+
+/// The types that [`PersistentProperty`] can store
+pub trait NvsValue: Sized {
+    fn read(nvs: &EspDefaultNvs, key: &str) -> Result<Option<Self>, EspError>;
+    fn write(nvs: &mut EspDefaultNvs, key: &str, value: Self) -> Result<(), EspError>;
+}
+
+impl NvsValue for f32 {
+    fn read(nvs: &EspDefaultNvs, key: &str) -> Result<Option<Self>, EspError> {
+        Ok(nvs.get_u32(key)?.map(f32::from_bits))
+    }
+
+    fn write(nvs: &mut EspDefaultNvs, key: &str, value: Self) -> Result<(), EspError> {
+        nvs.set_u32(key, value.to_bits())
+    }
+}
+
+impl NvsValue for bool {
+    fn read(nvs: &EspDefaultNvs, key: &str) -> Result<Option<Self>, EspError> {
+        Ok(nvs.get_u8(key)?.map(|value| value != 0))
+    }
+
+    fn write(nvs: &mut EspDefaultNvs, key: &str, value: Self) -> Result<(), EspError> {
+        nvs.set_u8(key, value.into())
+    }
+}
+
+/// A blob of exactly `N` bytes, a shorter one is ignored like a missing one
+impl<const N: usize> NvsValue for [u8; N] {
+    fn read(nvs: &EspDefaultNvs, key: &str) -> Result<Option<Self>, EspError> {
+        let mut bytes = [0; N];
+        let len = nvs.get_blob(key, &mut bytes)?.map(<[u8]>::len);
+        Ok(len.filter(|len| *len == N).map(|_| bytes))
+    }
+
+    fn write(nvs: &mut EspDefaultNvs, key: &str, value: Self) -> Result<(), EspError> {
+        nvs.set_blob(key, &value)
+    }
+}
+
+/// A key of an NVS namespace
+pub struct PersistentProperty<T> {
+    nvs: EspDefaultNvs,
+    key: &'static str,
+    value: PhantomData<T>,
+}
+
+impl<T: NvsValue> PersistentProperty<T> {
+    /// NVS keys and namespaces are at most 15 characters long
+    pub fn new(
+        nvs_partition: EspDefaultNvsPartition,
+        namespace: &str,
+        key: &'static str,
+    ) -> Result<Self, EspError> {
+        Ok(Self {
+            nvs: EspNvs::new(nvs_partition, namespace, true)?,
+            key,
+            value: PhantomData,
+        })
+    }
+
+    /// `None` until a value was stored
+    pub fn get(&self) -> Result<Option<T>, EspError> {
+        T::read(&self.nvs, self.key)
+    }
+
+    pub fn set(&mut self, value: T) -> Result<(), EspError> {
+        T::write(&mut self.nvs, self.key, value)
+    }
+}
@@ -0,0 +1,176 @@
+//! A PID controller whose process value is read from an ADC channel, shown and tuned on the
+//! dashboard, see `ui/pid_dashboard.slint`.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+#[cfg(not(feature = "sim"))]
+use esp_idf_svc::hal::adc::oneshot::{AdcChannelDriver, AdcDriver};
+#[cfg(not(feature = "sim"))]
+use esp_idf_svc::hal::adc::ADC1;
+#[cfg(not(feature = "sim"))]
+use esp_idf_svc::hal::gpio::Gpio6;
+use slint::ComponentHandle;
+
+use crate::line_chart::RingBuffer;
+use crate::{MainWindow, PidState};
+
+/// The AD pin of the sensor header, with an attenuation of 11 dB
+#[cfg(not(feature = "sim"))]
+pub type ProcessValueSensor = AdcChannelDriver<'static, Gpio6, AdcDriver<'static, ADC1>>;
+
+/// Stands for the ADC in the simulator: the voltage goes up and down over the whole range every
+/// 20 seconds
+#[cfg(feature = "sim")]
+#[derive(Default)]
+pub struct ProcessValueSensor {
+    samples: u32,
+}
+
+#[cfg(feature = "sim")]
+impl ProcessValueSensor {
+    /// In millivolts, like the ADC
+    fn read(&mut self) -> Result<u16, core::convert::Infallible> {
+        self.samples += 1;
+        let phase =
+            self.samples as f32 * SAMPLE_PERIOD.as_secs_f32() / 20. * core::f32::consts::TAU;
+        Ok(((1. - phase.cos()) / 2. * FULL_SCALE_MILLIVOLTS) as u16)
+    }
+}
+
+/// The process value is 100% at this voltage, the top of the range with 11 dB of attenuation
+const FULL_SCALE_MILLIVOLTS: f32 = 3100.;
+const SAMPLE_PERIOD: core::time::Duration = core::time::Duration::from_millis(100);
+/// 10 seconds of history
+const HISTORY_LENGTH: usize = 100;
+
+/// A discrete PID controller. The output is limited to `output_min..=output_max`, and the
+/// integral term stops growing while the output is saturated so that it doesn't overshoot
+/// once the process catches up.
+pub struct PidController {
+    pub kp: f32,
+    pub ki: f32,
+    pub kd: f32,
+    pub setpoint: f32,
+    pub output_min: f32,
+    pub output_max: f32,
+    integral: f32,
+    previous_error: Option<f32>,
+}
+
+impl PidController {
+    pub fn new(kp: f32, ki: f32, kd: f32) -> Self {
+        Self {
+            kp,
+            ki,
+            kd,
+            setpoint: 0.,
+            output_min: 0.,
+            output_max: 100.,
+            integral: 0.,
+            previous_error: None,
+        }
+    }
+
+    /// Returns the output for `process_value`, measured `dt` seconds after the previous one
+    pub fn update(&mut self, process_value: f32, dt: f32) -> f32 {
+        let error = self.setpoint - process_value;
+        let derivative = match self.previous_error {
+            Some(previous_error) if dt > 0. => (error - previous_error) / dt,
+            _ => 0.,
+        };
+        self.previous_error = Some(error);
+        let integral = self.integral + error * dt;
+        let output = self.kp * error + self.ki * integral + self.kd * derivative;
+        let clamped = output.clamp(self.output_min, self.output_max);
+        if clamped == output {
+            self.integral = integral;
+        }
+        clamped
+    }
+}
+
+struct Dashboard {
+    controller: PidController,
+    sensor: ProcessValueSensor,
+    setpoint_history: Rc<RingBuffer>,
+    process_value_history: Rc<RingBuffer>,
+    output_history: Rc<RingBuffer>,
+}
+
+impl Dashboard {
+    fn tick(&mut self) {
+        let process_value = match self.sensor.read() {
+            Ok(millivolts) => millivolts as f32 / FULL_SCALE_MILLIVOLTS * 100.,
+            Err(err) => {
+                log::error!("Cannot read the process value: {err}");
+                return;
+            }
+        };
+        let output = self
+            .controller
+            .update(process_value, SAMPLE_PERIOD.as_secs_f32());
+        self.setpoint_history.push(self.controller.setpoint);
+        self.process_value_history.push(process_value);
+        self.output_history.push(output);
+    }
+}
+
+thread_local! {
+    // Only accessed from the event loop thread
+    static DASHBOARD: RefCell<Option<Dashboard>> = const { RefCell::new(None) };
+    static TIMER: slint::Timer = Default::default();
+}
+
+/// Must be called once; the controller runs from then on, whether the dashboard is shown or not
+pub fn init(window: &MainWindow, sensor: ProcessValueSensor) {
+    let mut controller = PidController::new(2., 0.5, 0.);
+    controller.setpoint = 50.;
+
+    let state = window.global::<PidState>();
+    state.set_setpoint(controller.setpoint);
+    state.set_kp(controller.kp);
+    state.set_ki(controller.ki);
+    state.set_kd(controller.kd);
+    state.set_history_length(HISTORY_LENGTH as i32);
+    let dashboard = Dashboard {
+        controller,
+        sensor,
+        setpoint_history: Rc::new(RingBuffer::new(HISTORY_LENGTH)),
+        process_value_history: Rc::new(RingBuffer::new(HISTORY_LENGTH)),
+        output_history: Rc::new(RingBuffer::new(HISTORY_LENGTH)),
+    };
+    state.set_setpoint_history(dashboard.setpoint_history.clone().into());
+    state.set_process_value_history(dashboard.process_value_history.clone().into());
+    state.set_output_history(dashboard.output_history.clone().into());
+
+    state.on_tuning_changed({
+        let window = window.as_weak();
+        move || {
+            let Some(window) = window.upgrade() else {
+                return;
+            };
+            let state = window.global::<PidState>();
+            DASHBOARD.with(|dashboard| {
+                if let Some(dashboard) = dashboard.borrow_mut().as_mut() {
+                    let controller = &mut dashboard.controller;
+                    controller.setpoint = state.get_setpoint();
+                    controller.kp = state.get_kp();
+                    controller.ki = state.get_ki();
+                    controller.kd = state.get_kd();
+                }
+            });
+        }
+    });
+
+    DASHBOARD.with(|cell| *cell.borrow_mut() = Some(dashboard));
+    TIMER.with(|timer| {
+        timer.start(slint::TimerMode::Repeated, SAMPLE_PERIOD, || {
+            DASHBOARD.with(|dashboard| {
+                if let Some(dashboard) = dashboard.borrow_mut().as_mut() {
+                    dashboard.tick();
+                }
+            })
+        })
+    });
+}
@@ -0,0 +1,151 @@
+//! Checks the PIN entered on the PIN pad against its hash stored in NVS, see
+//! `ui/pin_entry.slint`.
+
+use std::cell::RefCell;
+use std::time::Duration;
+
+use esp_idf_svc::nvs::{EspDefaultNvsPartition, EspNvs};
+use esp_idf_svc::sys::EspError;
+use esp_idf_svc::timer::{EspTaskTimerService, EspTimer};
+use sha2::{Digest, Sha256};
+use slint::ComponentHandle;
+
+use crate::{MainWindow, PinState};
+
+const NVS_NAMESPACE: &str = "pin";
+/// SHA-256 of the PIN. There are at most a million PINs, so the hash only keeps the PIN from
+/// being read from the flash in plain text.
+const NVS_HASH_KEY: &str = "sha256";
+/// Stored when there is no PIN in NVS yet, on the first start
+const DEFAULT_PIN: &str = "1234";
+/// Wrong PINs in a row before the pad is locked for `LOCKOUT_DURATION`
+const MAX_ATTEMPTS: u32 = 3;
+const LOCKOUT_DURATION: Duration = Duration::from_secs(30);
+
+const ENTER_PIN: &str = "Enter the PIN";
+
+struct PinCheck {
+    window: slint::Weak<MainWindow>,
+    hash: [u8; 32],
+    failed_attempts: u32,
+    /// Ends the lockout
+    lockout_timer: EspTimer<'static>,
+}
+
+thread_local! {
+    // Only accessed from the event loop thread
+    static PIN_CHECK: RefCell<Option<PinCheck>> = const { RefCell::new(None) };
+}
+
+/// Reads the hash of the PIN from NVS, storing the one of `DEFAULT_PIN` if there is none yet.
+/// The PIN pad doesn't unlock when this fails.
+pub fn init(
+    window: &MainWindow,
+    nvs_partition: EspDefaultNvsPartition,
+    timer_service: &EspTaskTimerService,
+) -> Result<(), EspError> {
+    let mut nvs = EspNvs::new(nvs_partition, NVS_NAMESPACE, true)?;
+    let mut hash = [0; 32];
+    if nvs.get_blob(NVS_HASH_KEY, &mut hash)?.is_none() {
+        log::warn!("No PIN stored, using the default one");
+        hash = hash_pin(DEFAULT_PIN);
+        nvs.set_blob(NVS_HASH_KEY, &hash)?;
+    }
+
+    // Called from the timer task
+    let lockout_timer = timer_service.timer(|| {
+        if let Err(err) = slint::invoke_from_event_loop(end_lockout) {
+            log::error!("Cannot end the PIN lockout: {err}");
+        }
+    })?;
+
+    PIN_CHECK.with(|pin_check| {
+        *pin_check.borrow_mut() = Some(PinCheck {
+            window: window.as_weak(),
+            hash,
+            failed_attempts: 0,
+            lockout_timer,
+        })
+    });
+
+    let state = window.global::<PinState>();
+    state.on_check_pin(check_pin);
+    state.on_lock({
+        let window = window.as_weak();
+        move || {
+            let window = window.unwrap();
+            let state = window.global::<PinState>();
+            state.set_unlocked(false);
+            state.set_status(ENTER_PIN.into());
+        }
+    });
+    Ok(())
+}
+
+fn hash_pin(pin: &str) -> [u8; 32] {
+    Sha256::digest(pin.as_bytes()).into()
+}
+
+fn check_pin(pin: slint::SharedString) {
+    PIN_CHECK.with(|pin_check| {
+        let mut pin_check = pin_check.borrow_mut();
+        let Some(pin_check) = pin_check.as_mut() else {
+            return;
+        };
+        let Some(window) = pin_check.window.upgrade() else {
+            return;
+        };
+        let state = window.global::<PinState>();
+
+        if hash_pin(&pin) == pin_check.hash {
+            pin_check.failed_attempts = 0;
+            state.set_unlocked(true);
+            state.set_status("Unlocked".into());
+            return;
+        }
+
+        pin_check.failed_attempts += 1;
+        if pin_check.failed_attempts < MAX_ATTEMPTS {
+            let attempts_left = MAX_ATTEMPTS - pin_check.failed_attempts;
+            state.set_status(
+                format!(
+                    "Wrong PIN, {attempts_left} attempt{} left",
+                    if attempts_left > 1 { "s" } else { "" }
+                )
+                .into(),
+            );
+            return;
+        }
+
+        log::warn!("Too many wrong PINs, locking the PIN pad");
+        state.set_locked_out(true);
+        state.set_status(
+            format!(
+                "Too many wrong PINs, try again in {} s",
+                LOCKOUT_DURATION.as_secs()
+            )
+            .into(),
+        );
+        if let Err(err) = pin_check.lockout_timer.after(LOCKOUT_DURATION) {
+            log::error!("Cannot start the PIN lockout timer: {err}");
+            reset_attempts(pin_check, &state);
+        }
+    });
+}
+
+fn end_lockout() {
+    PIN_CHECK.with(|pin_check| {
+        if let Some(pin_check) = pin_check.borrow_mut().as_mut() {
+            if let Some(window) = pin_check.window.upgrade() {
+                reset_attempts(pin_check, &window.global::<PinState>());
+            }
+        }
+    });
+}
+
+/// Ends the lockout, if any
+fn reset_attempts(pin_check: &mut PinCheck, state: &PinState) {
+    pin_check.failed_attempts = 0;
+    state.set_locked_out(false);
+    state.set_status(ENTER_PIN.into());
+}
@@ -0,0 +1,95 @@
+//! Reads the large binary assets, e.g. sprite sheets or audio samples, straight from a raw data
+//! partition, without the overhead of the SPIFFS file system. `build.rs` concatenates the files of
+//! the `assets` directory into the image of the `assets` partition, see the README.
+
+use std::ffi::CString;
+
+use esp_idf_svc::sys::{
+    esp, esp_partition_find_first, esp_partition_read,
+    esp_partition_subtype_t_ESP_PARTITION_SUBTYPE_ANY,
+    esp_partition_type_t_ESP_PARTITION_TYPE_DATA, EspError,
+};
+
+/// The label of the partition of `partitions.csv` with the files of the `assets` directory
+pub const ASSETS_PARTITION: &str = "assets";
+
+/// Where a file of the `assets` directory is in the [`ASSETS_PARTITION`]
+#[derive(Clone, Copy, Debug)]
+pub struct Asset {
+    pub offset: u32,
+    pub len: u32,
+}
+
+// The constants generated by `build.rs`, one for each file, e.g. `SPRITES_RGB` for
+// `assets/sprites.rgb`
+include!(concat!(env!("OUT_DIR"), "/assets.rs"));
+
+impl Asset {
+    /// Fills `buf` with the bytes of the file from `offset` on, e.g. with a single sprite of a
+    /// sprite sheet, or with the whole file from 0
+    pub fn read(&self, offset: u32, buf: &mut [u8]) -> Result<(), FlashError> {
+        if offset as u64 + buf.len() as u64 > self.len as u64 {
+            return Err(FlashError::OutOfBounds);
+        }
+        read_asset(ASSETS_PARTITION, self.offset + offset, buf)
+    }
+}
+
+/// Fails if the [`ASSETS_PARTITION`] is missing from the partition table, or is too small for
+/// the files of the `assets` directory: their last bytes are read. It doesn't tell whether the
+/// partition was flashed, a blank one reads as `0xff` bytes.
+pub fn check_assets() -> Result<(), FlashError> {
+    for asset in ASSETS.iter().filter(|asset| asset.len > 0) {
+        asset.read(asset.len - 1, &mut [0])?;
+    }
+    Ok(())
+}
+
+/// Why [`read_asset`] failed
+#[derive(Debug)]
+pub enum FlashError {
+    /// There is no data partition with this label in the partition table
+    PartitionNotFound,
+    /// The bytes to read go past the end of the partition
+    OutOfBounds,
+    Read(EspError),
+}
+
+impl core::fmt::Display for FlashError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            FlashError::PartitionNotFound => f.write_str("The partition doesn't exist"),
+            FlashError::OutOfBounds => f.write_str("The data is past the end of the partition"),
+            FlashError::Read(err) => write!(f, "Cannot read the partition: {err}"),
+        }
+    }
+}
+
+/// Fills `buf` with the bytes at `offset` in the data partition `partition_label`. A partition
+/// that was never flashed reads as `0xff` bytes.
+pub fn read_asset(partition_label: &str, offset: u32, buf: &mut [u8]) -> Result<(), FlashError> {
+    let label = CString::new(partition_label).map_err(|_| FlashError::PartitionNotFound)?;
+    let partition = unsafe {
+        esp_partition_find_first(
+            esp_partition_type_t_ESP_PARTITION_TYPE_DATA,
+            esp_partition_subtype_t_ESP_PARTITION_SUBTYPE_ANY,
+            label.as_ptr(),
+        )
+    };
+    if partition.is_null() {
+        return Err(FlashError::PartitionNotFound);
+    }
+    let size = unsafe { (*partition).size };
+    if offset as u64 + buf.len() as u64 > size as u64 {
+        return Err(FlashError::OutOfBounds);
+    }
+    esp!(unsafe {
+        esp_partition_read(
+            partition,
+            offset as usize,
+            buf.as_mut_ptr().cast(),
+            buf.len(),
+        )
+    })
+    .map_err(FlashError::Read)
+}
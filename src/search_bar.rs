@@ -0,0 +1,92 @@
+//! Filters the pages of the search page as the query is typed, see `ui/search_bar.slint`.
+
+use std::rc::Rc;
+use std::time::Duration;
+
+use slint::{ComponentHandle, Model, ModelExt, VecModel};
+
+use crate::{HighlightedItem, MainWindow, Navigation, SearchState};
+
+/// The results are only filtered once the query wasn't changed for this long, not at every key
+const DEBOUNCE_DELAY: Duration = Duration::from_millis(150);
+
+thread_local! {
+    // Only accessed from the event loop thread
+    static DEBOUNCE: slint::Timer = Default::default();
+}
+
+/// Shows all the pages until a query is typed
+pub fn init(window: &MainWindow) {
+    let pages: Rc<Vec<String>> = Rc::new(window.get_pages().iter().map(Into::into).collect());
+    show_results(window, "", &pages);
+
+    let search = window.global::<SearchState>();
+    search.on_query_changed({
+        let window = window.as_weak();
+        let pages = pages.clone();
+        move |query| {
+            let window = window.clone();
+            let pages = pages.clone();
+            // Starting the timer again restarts it
+            DEBOUNCE.with(|timer| {
+                timer.start(slint::TimerMode::SingleShot, DEBOUNCE_DELAY, move || {
+                    if let Some(window) = window.upgrade() {
+                        show_results(&window, &query, &pages);
+                    }
+                })
+            });
+        }
+    });
+    search.on_opened({
+        let window = window.as_weak();
+        move |page| {
+            let window = window.unwrap();
+            let Some(index) = pages.iter().position(|name| *name == *page) else {
+                return;
+            };
+            window.set_current_page(index as i32);
+            window.global::<Navigation>().invoke_page_opened(page);
+        }
+    });
+}
+
+fn show_results(window: &MainWindow, query: &str, source: &[String]) {
+    let query = query.to_string();
+    let results = Rc::new(filter_model(&query, source)).map(move |item| highlight(&item, &query));
+    window
+        .global::<SearchState>()
+        .set_results(Rc::new(results).into());
+}
+
+/// The items of `source` that contain `query`, ignoring the case of the ASCII letters, in the
+/// same order. All of them match an empty query.
+pub fn filter_model(query: &str, source: &[String]) -> VecModel<String> {
+    source
+        .iter()
+        .filter(|item| find_ignoring_case(item, query).is_some())
+        .cloned()
+        .collect::<Vec<_>>()
+        .into()
+}
+
+/// `item` split around the first match of `query`, the whole item before it if there is none
+fn highlight(item: &str, query: &str) -> HighlightedItem {
+    let Some(start) = find_ignoring_case(item, query).filter(|_| !query.is_empty()) else {
+        return HighlightedItem {
+            before: item.into(),
+            ..Default::default()
+        };
+    };
+    let end = start + query.len();
+    HighlightedItem {
+        before: item[..start].into(),
+        matched: item[start..end].into(),
+        after: item[end..].into(),
+    }
+}
+
+/// The byte offset of `query` in `text`. Only the ASCII letters are lowercased, which keeps the
+/// offsets of the lowercased text valid in `text`.
+fn find_ignoring_case(text: &str, query: &str) -> Option<usize> {
+    text.to_ascii_lowercase().find(&query.to_ascii_lowercase())
+}
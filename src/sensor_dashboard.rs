@@ -0,0 +1,88 @@
+//! The readings of the sensors of the dashboard page, see `ui/sensor_card.slint`.
+//!
+//! The board has no such sensors, so each reading swings slowly around a typical value, and
+//! crosses the thresholds of the cards once in a while.
+
+use std::rc::Rc;
+use std::time::Duration;
+
+use slint::{ComponentHandle, Model, ModelRc, VecModel};
+
+use crate::line_chart::RingBuffer;
+use crate::{MainWindow, SensorReadings};
+
+/// A simulated sensor, in the unit of its card
+struct Sensor {
+    typical: f32,
+    /// Of the slow swing around `typical`
+    amplitude: f32,
+    period: Duration,
+    /// Of the noise over the swing
+    noise: f32,
+}
+
+/// In the order of the cards: temperature, humidity, CO₂, PM2.5, noise and VOC index
+const SENSORS: [Sensor; 6] = [
+    Sensor::new(23., 5.5, 90, 0.2),
+    Sensor::new(48., 15., 130, 1.),
+    Sensor::new(900., 550., 170, 20.),
+    Sensor::new(18., 14., 70, 2.),
+    Sensor::new(55., 20., 40, 4.),
+    Sensor::new(150., 150., 210, 10.),
+];
+const HISTORY_LENGTH: usize = 20;
+const UPDATE_INTERVAL: Duration = Duration::from_secs(1);
+
+impl Sensor {
+    const fn new(typical: f32, amplitude: f32, period_s: u64, noise: f32) -> Self {
+        Self {
+            typical,
+            amplitude,
+            period: Duration::from_secs(period_s),
+            noise,
+        }
+    }
+
+    /// `seed` picks the noise, the same for the same seed
+    fn read(&self, time: Duration, seed: u32) -> f32 {
+        let phase = time.as_secs_f32() / self.period.as_secs_f32() * core::f32::consts::TAU;
+        let noise = (seed.wrapping_mul(2_654_435_761) >> 24) as f32 / 128. - 1.;
+        (self.typical + self.amplitude * phase.sin() + self.noise * noise).max(0.)
+    }
+}
+
+thread_local! {
+    // Only accessed from the event loop thread
+    static TIMER: slint::Timer = Default::default();
+}
+
+/// Must be called once; the sensors are read from then on, whether the dashboard is shown or not
+pub fn init(window: &MainWindow) {
+    let histories: Vec<Rc<RingBuffer>> = SENSORS
+        .iter()
+        .map(|_| Rc::new(RingBuffer::new(HISTORY_LENGTH)))
+        .collect();
+    let values = Rc::new(VecModel::from(vec![0.; SENSORS.len()]));
+    let readings = window.global::<SensorReadings>();
+    readings.set_values(values.clone().into());
+    readings.set_histories(ModelRc::new(VecModel::from_iter(
+        histories
+            .iter()
+            .map(|history| ModelRc::from(history.clone())),
+    )));
+    readings.set_history_length(HISTORY_LENGTH as i32);
+
+    let mut time = Duration::ZERO;
+    let mut read = move || {
+        for (index, (sensor, history)) in SENSORS.iter().zip(&histories).enumerate() {
+            let seed = (time.as_secs() as u32).wrapping_mul(SENSORS.len() as u32) + index as u32;
+            let value = sensor.read(time, seed);
+            history.push(value);
+            values.set_row_data(index, value);
+        }
+        time += UPDATE_INTERVAL;
+    };
+    // A first reading right away, instead of zeros until the timer fires
+    read();
+    TIMER.with(|timer| timer.start(slint::TimerMode::Repeated, UPDATE_INTERVAL, read));
+}
@@ -0,0 +1,165 @@
+//! Records the strokes drawn on the signature canvas, see `ui/signature.slint`.
+
+use std::cell::RefCell;
+use std::fmt::Write;
+
+use slint::ComponentHandle;
+
+use crate::{MainWindow, SignatureState};
+
+mod ink;
+
+/// Width of the strokes in the exported SVG
+const SVG_STROKE_WIDTH: f32 = 2.;
+/// Stroke width in physical pixels when the finger is still, and when it moves at
+/// `FAST_VELOCITY` or faster
+const MAX_STROKE_WIDTH: f32 = 8.;
+const MIN_STROKE_WIDTH: f32 = 1.;
+/// In logical pixels per second
+const FAST_VELOCITY: f32 = 800.;
+
+#[derive(Default)]
+struct Signature {
+    window: slint::Weak<MainWindow>,
+    strokes: Vec<Vec<(f32, f32)>>,
+    /// Allocated once the size of the canvas is known
+    ink: Option<ink::Ink>,
+    /// Radius at the last point of the current stroke, in physical pixels
+    last_radius: f32,
+}
+
+impl Signature {
+    fn resize(&mut self, width: f32, height: f32) {
+        let Some(window) = self.window.upgrade() else {
+            return;
+        };
+        let scale_factor = window.window().scale_factor();
+        let size = (
+            (width * scale_factor) as u32,
+            (height * scale_factor) as u32,
+        );
+        if self.ink.as_ref().is_some_and(|ink| ink.size() == size) {
+            return;
+        }
+        // The strokes can't be scaled, so start over
+        self.strokes.clear();
+        self.ink = Some(ink::Ink::new(size.0, size.1));
+        self.update(&window);
+    }
+
+    fn add_point(&mut self, x: f32, y: f32, new_stroke: bool) {
+        let Some(window) = self.window.upgrade() else {
+            return;
+        };
+        if new_stroke || self.strokes.is_empty() {
+            self.strokes.push(Vec::new());
+        }
+        let stroke = self.strokes.last_mut().unwrap();
+        let from = stroke.last().copied();
+        if from == Some((x, y)) {
+            return;
+        }
+        stroke.push((x, y));
+
+        let (vx, vy) = crate::slint_platform::touch_velocity();
+        let speed = (vx.hypot(vy) / FAST_VELOCITY).min(1.);
+        let radius = (MAX_STROKE_WIDTH - (MAX_STROKE_WIDTH - MIN_STROKE_WIDTH) * speed) / 2.;
+        let (from, from_radius) = match from {
+            Some(from) => (from, self.last_radius),
+            None => ((x, y), radius),
+        };
+        self.last_radius = radius;
+
+        if let Some(ink) = &mut self.ink {
+            let scale_factor = window.window().scale_factor();
+            ink.add_line(
+                (from.0 * scale_factor, from.1 * scale_factor),
+                (x * scale_factor, y * scale_factor),
+                from_radius,
+                radius,
+            );
+        }
+        self.update(&window);
+    }
+
+    fn clear(&mut self) {
+        let Some(window) = self.window.upgrade() else {
+            return;
+        };
+        self.strokes.clear();
+        if let Some(ink) = &mut self.ink {
+            ink.clear();
+        }
+        self.update(&window);
+    }
+
+    fn update(&mut self, window: &MainWindow) {
+        let state = window.global::<SignatureState>();
+        state.set_blank(self.strokes.is_empty());
+        if let Some(ink) = &mut self.ink {
+            state.set_ink(ink.flush());
+        }
+    }
+}
+
+thread_local! {
+    // Only accessed from the event loop thread
+    static SIGNATURE: RefCell<Signature> = Default::default();
+}
+
+/// Must be called once before the signature canvas is used
+pub fn init(window: &MainWindow) {
+    let state = window.global::<SignatureState>();
+    state.on_canvas_resized(|width, height| {
+        SIGNATURE.with(|signature| signature.borrow_mut().resize(width, height))
+    });
+    state.on_stroke_started(|x, y| {
+        SIGNATURE.with(|signature| signature.borrow_mut().add_point(x, y, true))
+    });
+    state.on_stroke_moved(|x, y| {
+        SIGNATURE.with(|signature| signature.borrow_mut().add_point(x, y, false))
+    });
+    state.on_clear(|| SIGNATURE.with(|signature| signature.borrow_mut().clear()));
+    state.on_export_svg(|| {
+        SIGNATURE.with(|signature| export_svg(&signature.borrow().strokes).into())
+    });
+    SIGNATURE.with(|signature| signature.borrow_mut().window = window.as_weak());
+}
+
+/// Generates a minimal SVG document with one black polyline per stroke. The view box is the
+/// bounding box of the strokes.
+pub fn export_svg(strokes: &[Vec<(f32, f32)>]) -> String {
+    let margin = SVG_STROKE_WIDTH / 2.;
+    let (min, max) = strokes.iter().flatten().fold(
+        ((f32::MAX, f32::MAX), (f32::MIN, f32::MIN)),
+        |(min, max), &(x, y)| ((min.0.min(x), min.1.min(y)), (max.0.max(x), max.1.max(y))),
+    );
+    let (x, y, width, height) = if min.0 > max.0 {
+        (0., 0., 0., 0.)
+    } else {
+        (
+            min.0 - margin,
+            min.1 - margin,
+            max.0 - min.0 + 2. * margin,
+            max.1 - min.1 + 2. * margin,
+        )
+    };
+
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"{x} {y} {width} {height}\" \
+         fill=\"none\" stroke=\"black\" stroke-width=\"{SVG_STROKE_WIDTH}\" \
+         stroke-linecap=\"round\" stroke-linejoin=\"round\">"
+    );
+    for stroke in strokes {
+        svg.push_str("<polyline points=\"");
+        for (i, (x, y)) in stroke.iter().enumerate() {
+            if i > 0 {
+                svg.push(' ');
+            }
+            write!(svg, "{x},{y}").unwrap();
+        }
+        svg.push_str("\"/>");
+    }
+    svg.push_str("</svg>");
+    svg
+}
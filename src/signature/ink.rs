@@ -0,0 +1,121 @@
+//! Rasterizes the signature strokes into pixel buffers shown as an image by the canvas.
+
+use slint::{Rgb8Pixel, SharedPixelBuffer};
+
+const PAPER: Rgb8Pixel = Rgb8Pixel::new(0xff, 0xff, 0xff);
+const INK: Rgb8Pixel = Rgb8Pixel::new(0, 0, 0);
+
+/// A line between two points, in physical pixels, whose radius changes linearly along it
+#[derive(Clone, Copy)]
+struct Segment {
+    from: (i32, i32),
+    to: (i32, i32),
+    from_radius: f32,
+    to_radius: f32,
+}
+
+/// Two pixel buffers used in turn, like the frame buffers of the display: the segments are
+/// drawn in the buffer that isn't shown. The image of the shown buffer stays referenced until
+/// the canvas is rendered again, so drawing in that buffer would copy it.
+pub struct Ink {
+    buffers: [SharedPixelBuffer<Rgb8Pixel>; 2],
+    /// Segments not drawn yet in each buffer
+    pending: [Vec<Segment>; 2],
+    /// Index of the buffer that is shown
+    current: usize,
+}
+
+impl Ink {
+    /// Allocates buffers of the given size in physical pixels. They are big enough to be taken
+    /// from the PSRAM by the allocator.
+    pub fn new(width: u32, height: u32) -> Self {
+        let buffer = || {
+            let mut buffer = SharedPixelBuffer::new(width, height);
+            buffer.make_mut_slice().fill(PAPER);
+            buffer
+        };
+        Self {
+            buffers: [buffer(), buffer()],
+            pending: Default::default(),
+            current: 0,
+        }
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        (self.buffers[0].width(), self.buffers[0].height())
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::new(self.size().0, self.size().1);
+    }
+
+    /// Adds a line from `from` to `to`, in physical pixels. It is only drawn by [`Self::flush`].
+    pub fn add_line(&mut self, from: (f32, f32), to: (f32, f32), from_radius: f32, to_radius: f32) {
+        let segment = Segment {
+            from: (from.0 as i32, from.1 as i32),
+            to: (to.0 as i32, to.1 as i32),
+            from_radius,
+            to_radius,
+        };
+        for pending in &mut self.pending {
+            pending.push(segment);
+        }
+    }
+
+    /// Draws the pending segments in the buffer that isn't shown, and returns the image to show
+    /// instead of the current one.
+    pub fn flush(&mut self) -> slint::Image {
+        self.current = 1 - self.current;
+        let buffer = &mut self.buffers[self.current];
+        let width = buffer.width() as i32;
+        let height = buffer.height() as i32;
+        let pixels = buffer.make_mut_slice();
+        for segment in self.pending[self.current].drain(..) {
+            draw_segment(pixels, width, height, &segment);
+        }
+        slint::Image::from_rgb8(self.buffers[self.current].clone())
+    }
+}
+
+/// Bresenham's line algorithm, with a disc drawn at each point of the line
+fn draw_segment(pixels: &mut [Rgb8Pixel], width: i32, height: i32, segment: &Segment) {
+    let (mut x, mut y) = segment.from;
+    let (x1, y1) = segment.to;
+    let dx = (x1 - x).abs();
+    let dy = -(y1 - y).abs();
+    let sx = if x < x1 { 1 } else { -1 };
+    let sy = if y < y1 { 1 } else { -1 };
+    let steps = dx.max(-dy).max(1) as f32;
+    let mut error = dx + dy;
+    let mut step = 0;
+    loop {
+        let t = step as f32 / steps;
+        let radius = segment.from_radius + (segment.to_radius - segment.from_radius) * t;
+        draw_disc(pixels, width, height, (x, y), radius);
+        if (x, y) == (x1, y1) {
+            break;
+        }
+        let e2 = 2 * error;
+        if e2 >= dy {
+            error += dy;
+            x += sx;
+        }
+        if e2 <= dx {
+            error += dx;
+            y += sy;
+        }
+        step += 1;
+    }
+}
+
+fn draw_disc(pixels: &mut [Rgb8Pixel], width: i32, height: i32, center: (i32, i32), radius: f32) {
+    let r = radius.ceil() as i32;
+    for y in (center.1 - r).max(0)..=(center.1 + r).min(height - 1) {
+        for x in (center.0 - r).max(0)..=(center.0 + r).min(width - 1) {
+            let (dx, dy) = (x - center.0, y - center.1);
+            if ((dx * dx + dy * dy) as f32) <= radius * radius {
+                pixels[(y * width + x) as usize] = INK;
+            }
+        }
+    }
+}
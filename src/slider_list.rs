@@ -0,0 +1,150 @@
+//! Keeps the bands of the equalizer page across restarts, see `ui/slider_list.slint`. Each item
+//! of `Equalizer.items` is stored in its own key of the `equalizer` NVS namespace.
+//!
+//! The simulator has no NVS: the bands start from their defaults and aren't stored.
+
+#[cfg(not(feature = "sim"))]
+use std::cell::RefCell;
+use std::rc::Rc;
+
+#[cfg(not(feature = "sim"))]
+use esp_idf_svc::nvs::EspDefaultNvsPartition;
+#[cfg(not(feature = "sim"))]
+use esp_idf_svc::sys::EspError;
+use slint::{ComponentHandle, VecModel};
+
+#[cfg(not(feature = "sim"))]
+use crate::persistent_property::PersistentProperty;
+use crate::{Equalizer, MainWindow, SliderItem};
+
+#[cfg(not(feature = "sim"))]
+const NVS_NAMESPACE: &str = "equalizer";
+
+/// The gain of the bands, in dB
+const MIN_GAIN: f32 = -12.;
+const MAX_GAIN: f32 = 12.;
+const GAIN_STEP: f32 = 0.5;
+
+struct Band {
+    label: &'static str,
+    /// At most 15 characters long
+    #[cfg_attr(feature = "sim", allow(dead_code))]
+    key: &'static str,
+    /// Until the slider is moved for the first time, and after a reset
+    default: f32,
+}
+
+/// In the order of the page, a slight loudness curve by default
+const BANDS: [Band; 8] = [
+    Band {
+        label: "63 Hz",
+        key: "63hz",
+        default: 3.,
+    },
+    Band {
+        label: "125 Hz",
+        key: "125hz",
+        default: 2.,
+    },
+    Band {
+        label: "250 Hz",
+        key: "250hz",
+        default: 0.,
+    },
+    Band {
+        label: "500 Hz",
+        key: "500hz",
+        default: 0.,
+    },
+    Band {
+        label: "1 kHz",
+        key: "1khz",
+        default: 0.,
+    },
+    Band {
+        label: "2 kHz",
+        key: "2khz",
+        default: 0.,
+    },
+    Band {
+        label: "4 kHz",
+        key: "4khz",
+        default: 1.,
+    },
+    Band {
+        label: "8 kHz",
+        key: "8khz",
+        default: 2.,
+    },
+];
+
+/// Reads the gain of each band from NVS, and stores it again each time its slider is released
+/// or the bands are reset
+#[cfg(not(feature = "sim"))]
+pub fn init(window: &MainWindow, nvs_partition: EspDefaultNvsPartition) -> Result<(), EspError> {
+    let mut stored = Vec::with_capacity(BANDS.len());
+    let mut gains = Vec::with_capacity(BANDS.len());
+    for band in &BANDS {
+        let property =
+            PersistentProperty::<f32>::new(nvs_partition.clone(), NVS_NAMESPACE, band.key)?;
+        gains.push(property.get()?.unwrap_or(band.default));
+        stored.push(property);
+    }
+    set_items(window, gains);
+
+    let stored = Rc::new(RefCell::new(stored));
+    let equalizer = window.global::<Equalizer>();
+    equalizer.on_item_changed({
+        let stored = stored.clone();
+        move |index, gain| {
+            let index = index as usize;
+            if let Some(property) = stored.borrow_mut().get_mut(index) {
+                if let Err(err) = property.set(gain) {
+                    log::warn!("Cannot store the gain of {}: {err}", BANDS[index].label);
+                }
+            }
+        }
+    });
+    let window = window.as_weak();
+    equalizer.on_reset_to_defaults(move || {
+        for (property, band) in stored.borrow_mut().iter_mut().zip(&BANDS) {
+            if let Err(err) = property.set(band.default) {
+                log::warn!("Cannot store the gain of {}: {err}", band.label);
+            }
+        }
+        set_items(&window.unwrap(), defaults());
+    });
+    Ok(())
+}
+
+/// Sets the bands to their defaults, which are only kept until the end
+#[cfg(feature = "sim")]
+pub fn init(window: &MainWindow) {
+    set_items(window, defaults());
+    let weak = window.as_weak();
+    window
+        .global::<Equalizer>()
+        .on_reset_to_defaults(move || set_items(&weak.unwrap(), defaults()));
+}
+
+fn defaults() -> Vec<f32> {
+    BANDS.iter().map(|band| band.default).collect()
+}
+
+/// Replaces the model rather than its rows, which the sliders don't follow once they were moved
+fn set_items(window: &MainWindow, gains: Vec<f32>) {
+    let items: Vec<SliderItem> = BANDS
+        .iter()
+        .zip(gains)
+        .map(|(band, gain)| SliderItem {
+            label: band.label.into(),
+            value: gain,
+            min: MIN_GAIN,
+            max: MAX_GAIN,
+            step: GAIN_STEP,
+        })
+        .collect();
+    window
+        .global::<Equalizer>()
+        .set_items(Rc::new(VecModel::from(items)).into());
+}
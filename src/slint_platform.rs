@@ -1,391 +1,384 @@
+//! The Slint platform of the board: `display.rs` creates the LCD panel, `platform.rs` sets the
+//! platform that draws on it with the touch controller of `touch.rs`, and `event_loop.rs` runs
+//! it. The simulator has its own in `sim.rs`.
+
+#[cfg(not(feature = "sim"))]
 use core::cell::RefCell;
+#[cfg(not(feature = "sim"))]
 use std::rc::Rc;
-use std::sync::{Arc, Mutex};
-
+#[cfg(not(feature = "sim"))]
+use std::sync::Mutex;
+
+#[cfg(not(feature = "sim"))]
+use esp_idf_svc::sys::EspError;
+
+#[cfg(all(feature = "ble-remote", not(feature = "sim")))]
+mod ble_remote;
+mod cancellation;
+#[cfg(not(feature = "sim"))]
+mod display;
+#[cfg(not(feature = "sim"))]
+mod event_loop;
+#[cfg(not(feature = "sim"))]
+mod fps_overlay;
+#[cfg(not(feature = "sim"))]
+mod frame_buffer;
+#[cfg(all(feature = "gamma", not(feature = "sim")))]
+mod gamma;
+#[cfg(not(feature = "sim"))]
+mod i2c_recovery;
+// The simulator has no touch events to record
+#[cfg_attr(feature = "sim", allow(dead_code))]
+mod gesture;
+#[cfg(all(feature = "light-sleep", not(feature = "sim")))]
+mod light_sleep;
+#[cfg(not(feature = "sim"))]
+mod panel_ownership;
+#[cfg(not(feature = "sim"))]
+mod platform;
+#[cfg(feature = "sim")]
+mod sim;
+#[cfg(not(feature = "sim"))]
+mod spi;
+// The simulator can't draw into the window
+#[cfg_attr(feature = "sim", allow(dead_code))]
+mod stripes;
+mod tasks;
+mod text;
+#[cfg(not(feature = "sim"))]
+mod touch;
+#[cfg(all(feature = "diagnostics-trace", not(feature = "sim")))]
+mod trace;
+
+pub use cancellation::{spawn_cancellable, CancellationToken};
+#[cfg(not(feature = "sim"))]
+pub use display::{draw_panic_message, init_display};
+#[cfg(not(feature = "sim"))]
+pub use frame_buffer::SyncMode;
+pub use gesture::{pinch, set_dwell_handler, touch_velocity};
+#[cfg(not(feature = "sim"))]
+pub use platform::init_touch;
+#[cfg(feature = "sim")]
+pub use sim::{init_display, init_touch};
+#[cfg(not(feature = "sim"))]
+pub use spi::SpiConfig;
+pub use stripes::{set_stripe_pattern, StripePattern};
+pub use tasks::{spawn_high_priority, spawn_low_priority, TaskPriorities};
+pub use text::draw_message;
+#[cfg(not(feature = "sim"))]
+pub use touch::{detect_touch_controller, TouchControllerKind, TouchError};
+
+#[cfg(not(feature = "sim"))]
 const DISPLAY_WIDTH: usize = 800;
+#[cfg(not(feature = "sim"))]
 const DISPLAY_HEIGHT: usize = 480;
 
-mod sys {
-    #![allow(non_camel_case_types)]
-    use core::ffi::c_void;
-    use esp_idf_svc::hal::sys::*;
-    #[repr(C)]
-    pub struct esp_lcd_rgb_timing_t {
-        /// Frequency of pixel clock
-        pub pclk_hz: u32,
-        /// Horizontal resolution, i.e. the number of pixels in a line
-        pub h_res: u32,
-        /// Vertical resolution, i.e. the number of lines in the frame
-        pub v_res: u32,
-        /// Horizontal sync width, unit: PCLK period
-        pub hsync_pulse_width: u32,
-        /// Horizontal back porch, number of PCLK between hsync and start of line active data
-        pub hsync_back_porch: u32,
-        /// Horizontal front porch, number of PCLK between the end of active data and the next hsync
-        pub hsync_front_porch: u32,
-        /// Vertical sync width, unit: number of lines
-        pub vsync_pulse_width: u32,
-        /// Vertical back porch, number of invalid lines between vsync and start of frame
-        pub vsync_back_porch: u32,
-        /// Vertical front porch, number of invalid lines between the end of frame and the next vsync
-        pub vsync_front_porch: u32,
-        pub flags: u32,
-    }
-
-    #[repr(C)]
-    pub struct esp_lcd_rgb_panel_config_t {
-        /// Clock source for the RGB LCD peripheral
-        pub clk_src: lcd_clock_source_t,
-        /// RGB timing parameters, including the screen resolution
-        pub timings: esp_lcd_rgb_timing_t,
-        /// Number of data lines
-        pub data_width: usize,
-        /// Frame buffer color depth, in bpp, specially, if set to zero, it will default to `data_width`.
-        /// When using a Serial RGB interface, this value could be different from `data_width`
-        pub bits_per_pixel: usize,
-        /// Number of screen-sized frame buffers that allocated by the driver. By default (set to either 0 or 1) only one frame buffer will be used. Maximum number of buffers are 3
-        pub num_fbs: usize,
-        /// If it's non-zero, the driver allocates two DRAM bounce buffers for DMA use.
-        /// DMA fetching from DRAM bounce buffer is much faster than PSRAM frame buffer.
-        pub bounce_buffer_size_px: usize,
-        /// Alignment of buffers (frame buffer or bounce buffer) that allocated in SRAM
-        pub sram_trans_align: usize,
-        /// DMA burst size, in bytes
-        pub dma_burst_size: usize,
-        /// GPIO used for HSYNC signal
-        pub hsync_gpio_num: i32,
-        /// GPIO used for VSYNC signal
-        pub vsync_gpio_num: i32,
-        /// GPIO used for DE signal, set to -1 if it's not used
-        pub de_gpio_num: i32,
-        /// GPIO used for PCLK signal, set to -1 if it's not used
-        pub pclk_gpio_num: i32,
-        /// GPIO used for display control signal, set to -1 if it's not used
-        pub disp_gpio_num: i32,
-        /// GPIOs used for data lines
-        pub data_gpio_nums: [i32; SOC_LCDCAM_RGB_DATA_WIDTH as usize],
-
-        pub flags: u32,
-    }
+/// Options for the platform, passed to [`init_display`]
+#[derive(Clone)]
+#[cfg_attr(feature = "sim", derive(Default))]
+pub struct EspPlatformConfig {
+    /// Show the number of frames rendered per second in the top left corner of the screen
+    pub fps_overlay: bool,
+    /// Of the tasks spawned by [`spawn_high_priority`] and [`spawn_low_priority`]
+    pub task_priorities: TaskPriorities,
+    /// Corrects the colors for a panel with this gamma, typically 2.2. The extra pass over the
+    /// rendered pixels takes about 1 ms per frame.
+    #[cfg(feature = "gamma")]
+    pub gamma_correction: Option<f32>,
+    /// The touch controller of the display, or `None` to find it with
+    /// [`detect_touch_controller`]
+    #[cfg(not(feature = "sim"))]
+    pub touch_controller: Option<TouchControllerKind>,
+    /// The SCL and SDA GPIOs of the I2C bus passed to [`init_touch`], to free the bus when a
+    /// device holds SDA low. Those of the board by default, 9 and 8.
+    #[cfg(not(feature = "sim"))]
+    pub i2c_gpio_nums: (i32, i32),
+    /// How the display is connected, the RGB interface of the board by default
+    #[cfg(not(feature = "sim"))]
+    pub interface: Interface,
+    /// How many closures [`slint::invoke_from_event_loop`] can queue until the event loop runs
+    /// them. More are rejected and counted, with the error of an event loop that was quit, since
+    /// Slint has none for a full queue. The queue grows while a frame takes long to render, and
+    /// until the event loop starts.
+    #[cfg(not(feature = "sim"))]
+    pub max_queue_depth: usize,
+    /// When a frame can be rendered after the last one, with the RGB interface
+    #[cfg(not(feature = "sim"))]
+    pub sync_mode: SyncMode,
+    /// The weight of each new touch sample in the velocity of the touch point, between 0 and 1.
+    /// Lower values smooth out more of the jitter of fast swipes, but follow a change of speed
+    /// more slowly. 0.3 by default, which follows half of a change within 2 samples.
+    #[cfg(not(feature = "sim"))]
+    pub velocity_smoothing: f32,
+    /// Renders the first frame of each window once into the back buffer before the one that is
+    /// shown, for the images that the renderer caches, e.g. the SVG icons at their size, to be
+    /// rendered already, and the back buffer to be in the cache of the PSRAM. The first frame is
+    /// shown a render later, but doesn't take longer than the next ones. `true` by default.
+    #[cfg(not(feature = "sim"))]
+    pub pre_warm: bool,
+    /// How long the finger must rest at the same place to stand for hovering, see
+    /// [`set_dwell_handler`], or `None` not to track it. 1000 ms by default.
+    #[cfg(not(feature = "sim"))]
+    pub hover_dwell_ms: Option<u32>,
+}
 
-    #[repr(C)]
-    pub struct esp_lcd_rgb_panel_event_callbacks_t {
-        pub on_color_trans_done: Option<
-            extern "C" fn(
-                panel: esp_idf_svc::hal::sys::esp_lcd_panel_handle_t,
-                edata: *const c_void,
-                user_ctx: *mut c_void,
-            ) -> bool,
-        >,
-        pub on_vsync: Option<
-            extern "C" fn(
-                panel: esp_idf_svc::hal::sys::esp_lcd_panel_handle_t,
-                edata: *const c_void,
-                user_ctx: *mut c_void,
-            ) -> bool,
-        >,
-        pub on_bounce_empty: Option<
-            extern "C" fn(
-                panel: esp_idf_svc::hal::sys::esp_lcd_panel_handle_t,
-                edata: *const c_void,
-                user_ctx: *mut c_void,
-            ) -> bool,
-        >,
-        pub on_frame_buf_complete: Option<
-            extern "C" fn(
-                panel: esp_idf_svc::hal::sys::esp_lcd_panel_handle_t,
-                edata: *const c_void,
-                user_ctx: *mut c_void,
-            ) -> bool,
-        >,
+#[cfg(not(feature = "sim"))]
+impl Default for EspPlatformConfig {
+    fn default() -> Self {
+        Self {
+            fps_overlay: false,
+            task_priorities: TaskPriorities::default(),
+            #[cfg(feature = "gamma")]
+            gamma_correction: None,
+            touch_controller: None,
+            i2c_gpio_nums: (9, 8),
+            interface: Interface::Rgb(RgbConfig::default()),
+            max_queue_depth: 64,
+            sync_mode: SyncMode::default(),
+            velocity_smoothing: gesture::DEFAULT_VELOCITY_SMOOTHING,
+            pre_warm: true,
+            hover_dwell_ms: Some(1000),
+        }
     }
+}
 
-    extern "C" {
-        pub fn esp_lcd_new_rgb_panel(
-            rgb_panel_config: *const esp_lcd_rgb_panel_config_t,
-            ret_panel: *mut esp_lcd_panel_handle_t,
-        ) -> esp_err_t;
-
-        pub fn esp_lcd_rgb_panel_get_frame_buffer(
-            panel: esp_lcd_panel_handle_t,
-            fb_num: u32,
-            fb0: *mut *mut u8,
-            ...
-        );
+/// The interface of the LCD panel. The render loop is the same for both, only the panel draws
+/// its frame buffers differently.
+#[cfg(not(feature = "sim"))]
+#[derive(Clone, Copy, Debug)]
+pub enum Interface {
+    /// The 800x480 panel of the board, or another one of the same size
+    Rgb(RgbConfig),
+    // Not used by the board
+    #[allow(dead_code)]
+    Spi(SpiConfig),
+}
 
-        pub fn esp_lcd_rgb_panel_register_event_callbacks(
-            panel: esp_lcd_panel_handle_t,
-            callbacks: *const esp_lcd_rgb_panel_event_callbacks_t,
-            user_ctx: *mut c_void,
-        ) -> esp_err_t;
+#[cfg(not(feature = "sim"))]
+impl Interface {
+    /// The width and height of the display, in physical pixels
+    fn size(&self) -> (usize, usize) {
+        match self {
+            Interface::Rgb(_) => (DISPLAY_WIDTH, DISPLAY_HEIGHT),
+            Interface::Spi(config) => (config.width, config.height),
+        }
     }
 }
 
-type I2C = esp_idf_svc::hal::i2c::I2cDriver<'static>;
-type Gt911 = gt911::Gt911Blocking<I2C>;
-
-struct EspPlatform {
-    panel_handle: esp_idf_svc::hal::sys::esp_lcd_panel_handle_t,
-    touch: Gt911,
-    i2c: RefCell<I2C>,
-    window: Rc<slint::platform::software_renderer::MinimalSoftwareWindow>,
-    timer: esp_idf_svc::timer::EspTimerService<esp_idf_svc::timer::Task>,
-    queue: Arc<Mutex<Vec<Event>>>,
+/// The GPIOs of the RGB interface of the LCD panel. The default ones are those of the board,
+/// other displays of the same size only differ by their wiring.
+#[cfg(not(feature = "sim"))]
+#[derive(Clone, Copy, Debug)]
+pub struct RgbConfig {
+    pub hsync_gpio_num: i32,
+    pub vsync_gpio_num: i32,
+    pub de_gpio_num: i32,
+    pub pclk_gpio_num: i32,
+    /// From the least significant bit of the RGB565 pixels: blue, green, then red
+    pub data_gpio_nums: [i32; 16],
 }
 
-impl EspPlatform {
-    pub fn new(mut i2c: I2C) -> std::boxed::Box<Self> {
-        use esp_idf_svc::hal::sys::*;
-
-        // Initialize LCD panel and touch
-        let mut panel_handle: esp_lcd_panel_handle_t = std::ptr::null_mut();
-        let panel_config = sys::esp_lcd_rgb_panel_config_t {
-            clk_src: soc_module_clk_t_SOC_MOD_CLK_PLL_F160M, //LCD_CLK_SRC_DEFAULT,
-            timings: sys::esp_lcd_rgb_timing_t {
-                pclk_hz: 16 * 1000 * 1000,
-                h_res: DISPLAY_WIDTH as u32,
-                v_res: DISPLAY_HEIGHT as u32,
-                hsync_pulse_width: 4,
-                hsync_back_porch: 8,
-                hsync_front_porch: 8,
-                vsync_pulse_width: 4,
-                vsync_back_porch: 8,
-                vsync_front_porch: 8,
-                flags: 0b1000, // pclk_active_neg
-            },
-            data_width: 16,
-            bits_per_pixel: 16,
-            num_fbs: 2,
-            bounce_buffer_size_px: DISPLAY_WIDTH * 10,
-            sram_trans_align: 4,
-            dma_burst_size: 64,
+#[cfg(not(feature = "sim"))]
+impl Default for RgbConfig {
+    fn default() -> Self {
+        Self {
             hsync_gpio_num: 46,
             vsync_gpio_num: 3,
             de_gpio_num: 5,
             pclk_gpio_num: 7,
-            disp_gpio_num: -1,
             data_gpio_nums: [14, 38, 18, 17, 10, 39, 0, 45, 48, 47, 21, 1, 2, 42, 41, 40],
-            flags: 0b100, // fb_in_psram: Use PSRAM for framebuffer
-        };
-        unsafe {
-            assert_eq!(
-                sys::esp_lcd_new_rgb_panel(&panel_config, &mut panel_handle),
-                ESP_OK
-            );
-            assert_eq!(esp_lcd_panel_init(panel_handle), ESP_OK);
-            assert_eq!(
-                sys::esp_lcd_rgb_panel_register_event_callbacks(
-                    panel_handle,
-                    &sys::esp_lcd_rgb_panel_event_callbacks_t {
-                        on_color_trans_done: None,
-                        on_vsync: Some(vsync_callback),
-                        on_bounce_empty: None,
-                        on_frame_buf_complete: None,
-                    },
-                    core::ptr::null_mut()
-                ),
-                ESP_OK
-            );
         }
-
-        // Setup the touch
-        let touch = Gt911::default();
-        touch.init(&mut i2c).unwrap();
-
-        // Setup the window
-        let window = slint::platform::software_renderer::MinimalSoftwareWindow::new(
-            slint::platform::software_renderer::RepaintBufferType::SwappedBuffers,
-        );
-        window.set_size(slint::PhysicalSize::new(
-            DISPLAY_WIDTH as u32,
-            DISPLAY_HEIGHT as u32,
-        ));
-
-        std::boxed::Box::new(Self {
-            panel_handle,
-            touch,
-            i2c: i2c.into(),
-            window,
-            timer: esp_idf_svc::timer::EspTimerService::new().unwrap(),
-            queue: Default::default(),
-        })
     }
 }
 
-impl slint::platform::Platform for EspPlatform {
-    fn create_window_adapter(
-        &self,
-    ) -> Result<Rc<dyn slint::platform::WindowAdapter>, slint::PlatformError> {
-        // Since on MCUs, there can be only one window, just return a clone of self.window.
-        // We'll also use the same window in the event loop.
-        Ok(self.window.clone())
-    }
-    fn duration_since_start(&self) -> core::time::Duration {
-        self.timer.now()
-    }
-    fn run_event_loop(&self) -> Result<(), slint::PlatformError> {
-        use esp_idf_svc::hal::sys::*;
-
-        unsafe {
-            // Initialize the LCD panel
-            if esp_lcd_panel_init(self.panel_handle) != ESP_OK {
-                log::error!("Failed to initialize LCD panel");
-                return Err(slint::PlatformError::Other(
-                    "Failed to initialize LCD panel".into(),
-                ));
-            }
+/// The lifecycle of the platform, see [`transition`]
+#[cfg(not(feature = "sim"))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PlatformState {
+    /// Before [`init_touch`] sets the platform
+    Uninitialized,
+    /// The event loop isn't running
+    Ready,
+    /// In [`slint::run_event_loop`], which must not be called again from a callback
+    Running,
+    /// In [`slint::run_event_loop`], with the display switched off by [`set_display_on`]
+    DisplayOff,
+    /// [`deep_sleep`] was called, the chip restarts when it wakes up
+    DeepSleep,
+}
 
-            // Turn on the display
-            esp_lcd_panel_disp_on_off(self.panel_handle, true);
+#[cfg(not(feature = "sim"))]
+static STATE: Mutex<PlatformState> = Mutex::new(PlatformState::Uninitialized);
 
-            // Calling this function rotates the display by 180 degrees
-            esp_lcd_panel_mirror(self.panel_handle, true, true);
-        }
+/// Goes from the state `from` to `to`, or fails if the platform isn't in the state `from`
+#[cfg(not(feature = "sim"))]
+fn transition(from: PlatformState, to: PlatformState) -> Result<(), EspPlatformError> {
+    let mut state = STATE.lock().unwrap();
+    if *state != from {
+        return Err(EspPlatformError::InvalidState { state: *state, to });
+    }
+    log::info!("Platform state: {from:?} -> {to:?}");
+    *state = to;
+    Ok(())
+}
 
-        // Create a buffer to draw the scene
-        use slint::platform::software_renderer::Rgb565Pixel;
+#[cfg(not(feature = "sim"))]
+fn state() -> PlatformState {
+    *STATE.lock().unwrap()
+}
 
-        let (mut buffer1, mut buffer2) = unsafe {
-            let (mut b1, mut b2) = (std::ptr::null_mut(), std::ptr::null_mut());
-            sys::esp_lcd_rgb_panel_get_frame_buffer(self.panel_handle, 2, &mut b1, &mut b2);
-            (
-                core::slice::from_raw_parts_mut(
-                    b1 as *mut Rgb565Pixel,
-                    DISPLAY_WIDTH * DISPLAY_HEIGHT,
-                ),
-                core::slice::from_raw_parts_mut(
-                    b2 as *mut Rgb565Pixel,
-                    DISPLAY_WIDTH * DISPLAY_HEIGHT,
-                ),
-            )
-        };
+/// Switches the display off, or on again, while the event loop runs: the backlight of the board,
+/// through its IO expander, or an SPI panel. The event loop does it on its next iteration, and
+/// renders nothing while the display is off, but still reads the touch controller, e.g. to switch
+/// it on again when touched. Fails if the event loop isn't running, or if the display already is
+/// off or on. The event loop switches it on again when it returns.
+// Not used by the demo, which is always shown
+#[cfg(not(feature = "sim"))]
+#[allow(dead_code)]
+pub fn set_display_on(on: bool) -> Result<(), EspPlatformError> {
+    if on {
+        transition(PlatformState::DisplayOff, PlatformState::Running)
+    } else {
+        transition(PlatformState::Running, PlatformState::DisplayOff)
+    }
+}
 
-        let mut last_position = slint::LogicalPosition::default();
-        let mut touch_down = false;
+/// Puts the chip into deep sleep, from which it restarts once `wakeup` elapsed, or with the reset
+/// button if `None`. The display must have been switched off by [`set_display_on`]: the IO
+/// expander keeps the backlight off meanwhile. Only returns if it wasn't, or if `wakeup` is too
+/// long.
+// Not used by the demo either
+#[cfg(not(feature = "sim"))]
+#[allow(dead_code)]
+pub fn deep_sleep(
+    wakeup: Option<core::time::Duration>,
+) -> Result<core::convert::Infallible, EspPlatformError> {
+    use esp_idf_svc::sys::{esp, esp_deep_sleep_start, esp_sleep_enable_timer_wakeup};
+
+    if state() != PlatformState::DisplayOff {
+        return Err(EspPlatformError::InvalidState {
+            state: state(),
+            to: PlatformState::DeepSleep,
+        });
+    }
+    if let Some(wakeup) = wakeup {
+        esp!(unsafe { esp_sleep_enable_timer_wakeup(wakeup.as_micros() as u64) })
+            .map_err(EspPlatformError::Sleep)?;
+    }
+    transition(PlatformState::DisplayOff, PlatformState::DeepSleep)?;
+    unsafe { esp_deep_sleep_start() }
+}
 
-        loop {
-            slint::platform::update_timers_and_animations();
+#[cfg(not(feature = "sim"))]
+type I2C = esp_idf_svc::hal::i2c::I2cDriver<'static>;
 
-            let queue = std::mem::take(&mut *self.queue.lock().unwrap());
-            for event in queue {
-                match event {
-                    Event::Invoke(event) => event(),
-                    Event::Quit => break,
-                }
-            }
+/// The I2C bus of the touch controller, shared with the other devices on it, e.g. from Slint
+/// callbacks. The event loop only borrows it to read the touch points, and releases it before
+/// dispatching the touch events and rendering, which run the callbacks. Everything runs on the
+/// thread of the event loop, so a callback never finds it borrowed, as long as it doesn't keep
+/// the borrow across an `await`.
+#[cfg(not(feature = "sim"))]
+pub type SharedI2c = Rc<RefCell<I2C>>;
+
+/// Why [`init_display`] or [`init_touch`] failed
+#[cfg(not(feature = "sim"))]
+#[derive(Debug)]
+pub enum InitError {
+    /// Creating the LCD panel, initializing it or registering its callbacks failed
+    PanelInitFailed(EspError),
+    TouchInitFailed(TouchError),
+    TimerInitFailed(EspError),
+    /// [`init_touch`] was already called
+    PlatformAlreadySet,
+}
 
-            match self.touch.get_touch(&mut self.i2c.borrow_mut()) {
-                Ok(Some(point)) => {
-                    last_position = slint::PhysicalPosition::new(point.x as _, point.y as _)
-                        .to_logical(self.window.scale_factor());
-                    if !touch_down {
-                        self.window
-                            .dispatch_event(slint::platform::WindowEvent::PointerPressed {
-                                position: last_position,
-                                button: slint::platform::PointerEventButton::Left,
-                            });
-                    }
-                    self.window
-                        .dispatch_event(slint::platform::WindowEvent::PointerMoved {
-                            position: last_position,
-                        });
-                    touch_down = true;
-                }
-                Ok(None) => {
-                    if touch_down {
-                        self.window
-                            .dispatch_event(slint::platform::WindowEvent::PointerReleased {
-                                position: last_position,
-                                button: slint::platform::PointerEventButton::Left,
-                            });
-                        self.window
-                            .dispatch_event(slint::platform::WindowEvent::PointerExited);
-                    }
-                    touch_down = false;
-                }
-                Err(gt911::Error::NotReady) => {
-                    //skip
-                }
-                Err(err) => {
-                    log::error!("Error reading the touch screen: {:?}", err);
-                }
+#[cfg(not(feature = "sim"))]
+impl core::fmt::Display for InitError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            InitError::PanelInitFailed(err) => write!(f, "Cannot initialize the LCD panel: {err}"),
+            InitError::TouchInitFailed(err) => {
+                write!(f, "Cannot initialize the touch controller: {err}")
             }
+            InitError::TimerInitFailed(err) => write!(f, "Cannot create the timer: {err}"),
+            InitError::PlatformAlreadySet => f.write_str("The platform is already set"),
+        }
+    }
+}
 
-            // Draw the scene if something needs to be drawn.
-            self.window.draw_if_needed(|renderer| {
-                while !VSYNC.load(core::sync::atomic::Ordering::SeqCst) {
-                    esp_idf_svc::hal::task::do_yield();
-                }
-                renderer.render(buffer1, DISPLAY_WIDTH);
-                unsafe {
-                    esp_lcd_panel_draw_bitmap(
-                        self.panel_handle,
-                        0,
-                        0,
-                        DISPLAY_WIDTH as i32,
-                        DISPLAY_HEIGHT as i32,
-                        buffer1.as_ptr().cast(),
-                    )
-                };
-                VSYNC.store(false, core::sync::atomic::Ordering::SeqCst);
-
-                core::mem::swap(&mut buffer1, &mut buffer2);
-            });
+/// Why the event loop didn't start or stopped, see [`last_error`]. `slint::run_event_loop` only
+/// returns its message.
+#[cfg(not(feature = "sim"))]
+#[derive(Clone, Copy, Debug)]
+pub enum EspPlatformError {
+    /// The platform can't go from `state` to `to`, e.g. when the event loop is run again from a
+    /// callback
+    InvalidState {
+        state: PlatformState,
+        to: PlatformState,
+    },
+    /// Initializing the LCD panel again, or getting the frame buffers of an RGB panel, when the
+    /// event loop starts failed
+    PanelInit(EspError),
+    /// The panel stopped telling when it is done with a frame, see `frame_buffer::SyncTimeout`
+    VsyncTimeout,
+    /// A new closure of [`slint::invoke_from_event_loop`] was rejected because the queue was
+    /// full, the event loop goes on
+    QueueFull,
+    /// The wake-up of [`deep_sleep`] can't be set
+    Sleep(EspError),
+}
 
-            // Try to put the MCU to sleep
-            if !self.window.has_active_animations() {
-                continue;
+#[cfg(not(feature = "sim"))]
+impl core::fmt::Display for EspPlatformError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            EspPlatformError::InvalidState { state, to } => {
+                write!(f, "The platform can't go from {state:?} to {to:?}")
             }
-
-            // FIXME
-            esp_idf_svc::hal::task::do_yield();
+            EspPlatformError::PanelInit(err) => write!(f, "Cannot initialize the LCD panel: {err}"),
+            EspPlatformError::VsyncTimeout => f.write_str("The LCD panel stopped drawing frames"),
+            EspPlatformError::QueueFull => f.write_str("The event loop queue is full"),
+            EspPlatformError::Sleep(err) => write!(f, "Cannot set the wake-up: {err}"),
         }
     }
+}
 
-    fn debug_log(&self, arguments: core::fmt::Arguments) {
-        log::debug!("{}", arguments);
+#[cfg(not(feature = "sim"))]
+impl From<frame_buffer::SyncTimeout> for EspPlatformError {
+    fn from(_: frame_buffer::SyncTimeout) -> Self {
+        EspPlatformError::VsyncTimeout
     }
+}
 
-    fn new_event_loop_proxy(&self) -> Option<Box<dyn slint::platform::EventLoopProxy>> {
-        Some(Box::new(EspEventLoopProxy {
-            queue: self.queue.clone(),
-        }))
+#[cfg(not(feature = "sim"))]
+impl From<EspPlatformError> for slint::PlatformError {
+    fn from(err: EspPlatformError) -> Self {
+        slint::PlatformError::Other(format!("{err}"))
     }
 }
 
-enum Event {
-    Quit,
-    Invoke(Box<dyn FnOnce() + Send>),
-}
-struct EspEventLoopProxy {
-    queue: Arc<Mutex<Vec<Event>>>,
-}
-impl slint::platform::EventLoopProxy for EspEventLoopProxy {
-    fn quit_event_loop(&self) -> Result<(), slint::EventLoopError> {
-        self.queue.lock().unwrap().push(Event::Quit);
-        Ok(())
-    }
+#[cfg(not(feature = "sim"))]
+static TOUCH_CONTROLLER: Mutex<Option<TouchControllerKind>> = Mutex::new(None);
 
-    fn invoke_from_event_loop(
-        &self,
-        event: Box<dyn FnOnce() + Send>,
-    ) -> Result<(), slint::EventLoopError> {
-        self.queue.lock().unwrap().push(Event::Invoke(event));
-        Ok(())
-    }
+/// The touch controller that [`init_touch`] initialized, `None` before it or if none was found
+#[cfg(not(feature = "sim"))]
+pub fn touch_controller() -> Option<TouchControllerKind> {
+    *TOUCH_CONTROLLER.lock().unwrap()
 }
 
-pub fn init(i2c: I2C) {
-    slint::platform::set_platform(EspPlatform::new(i2c)).unwrap();
-}
+#[cfg(not(feature = "sim"))]
+static LAST_ERROR: Mutex<Option<EspPlatformError>> = Mutex::new(None);
 
-static VSYNC: core::sync::atomic::AtomicBool = core::sync::atomic::AtomicBool::new(false);
+/// The last error of the event loop, including the ones after which it went on, to decide
+/// whether to restart once it returned
+#[cfg(not(feature = "sim"))]
+pub fn last_error() -> Option<EspPlatformError> {
+    *LAST_ERROR.lock().unwrap()
+}
 
-extern "C" fn vsync_callback(
-    _panel: esp_idf_svc::hal::sys::esp_lcd_panel_handle_t,
-    _edata: *const core::ffi::c_void,
-    _user_ctx: *mut core::ffi::c_void,
-) -> bool {
-    VSYNC.store(true, core::sync::atomic::Ordering::SeqCst);
-    false
+#[cfg(not(feature = "sim"))]
+fn record_error(err: EspPlatformError) -> EspPlatformError {
+    *LAST_ERROR.lock().unwrap() = Some(err);
+    err
 }
@@ -0,0 +1,74 @@
+//! Sends the touch events to a phone over BLE, with the `ble-remote` feature, e.g. to an app that
+//! mirrors them for a demonstration. The board advertises a GATT service with a characteristic
+//! that notifies each touch event in 5 bytes: 0 when pressed, 1 when moved or 2 when released,
+//! then the position in logical pixels, x and y as little endian `u16`.
+
+use core::cell::RefCell;
+use std::sync::Arc;
+
+use esp32_nimble::utilities::mutex::Mutex;
+use esp32_nimble::utilities::BleUuid;
+use esp32_nimble::{
+    uuid128, BLEAdvertisementData, BLECharacteristic, BLEDevice, BLEError, BLEServer,
+    NimbleProperties,
+};
+use slint::platform::WindowEvent;
+
+const DEVICE_NAME: &str = "ESP32-S3-Touch-LCD-5";
+const SERVICE_UUID: BleUuid = uuid128!("b6c3e5a0-5f1d-4c2a-9a43-2d7f0e1c8a10");
+const TOUCH_UUID: BleUuid = uuid128!("b6c3e5a1-5f1d-4c2a-9a43-2d7f0e1c8a10");
+
+struct Remote {
+    server: &'static BLEServer,
+    touch: Arc<Mutex<BLECharacteristic>>,
+}
+
+thread_local! {
+    // Only accessed from the event loop thread
+    static REMOTE: RefCell<Option<Remote>> = const { RefCell::new(None) };
+}
+
+/// Starts advertising the service, once. NimBLE advertises it again after a disconnection.
+pub fn start() -> Result<(), BLEError> {
+    if REMOTE.with(|remote| remote.borrow().is_some()) {
+        return Ok(());
+    }
+    let device = BLEDevice::take();
+    let server = device.get_server();
+    let service = server.create_service(SERVICE_UUID);
+    let touch = service
+        .lock()
+        .create_characteristic(TOUCH_UUID, NimbleProperties::NOTIFY);
+    let mut advertising = device.get_advertising().lock();
+    advertising.set_data(
+        BLEAdvertisementData::new()
+            .name(DEVICE_NAME)
+            .add_service_uuid(SERVICE_UUID),
+    )?;
+    advertising.start()?;
+    REMOTE.with(|remote| *remote.borrow_mut() = Some(Remote { server, touch }));
+    Ok(())
+}
+
+/// Notifies `event` if it is a touch event and a phone is connected
+pub fn notify_touch(event: &WindowEvent) {
+    let (kind, position) = match event {
+        WindowEvent::PointerPressed { position, .. } => (0, position),
+        WindowEvent::PointerMoved { position } => (1, position),
+        WindowEvent::PointerReleased { position, .. } => (2, position),
+        _ => return,
+    };
+    REMOTE.with(|remote| {
+        let remote = remote.borrow();
+        let Some(remote) = remote.as_ref() else {
+            return;
+        };
+        if remote.server.connected_count() == 0 {
+            return;
+        }
+        let [x_low, x_high] = (position.x as u16).to_le_bytes();
+        let [y_low, y_high] = (position.y as u16).to_le_bytes();
+        let value = [kind, x_low, x_high, y_low, y_high];
+        remote.touch.lock().set_value(&value).notify();
+    });
+}
@@ -0,0 +1,70 @@
+//! Futures of [`slint::spawn_local`] that are dropped once a [`CancellationToken`] is cancelled,
+//! e.g. the requests of a page that isn't shown anymore, instead of running until they complete.
+
+use core::future::Future;
+use core::pin::pin;
+use core::sync::atomic::{AtomicBool, Ordering};
+use core::task::{Poll, Waker};
+use std::sync::{Arc, Mutex};
+
+/// Cancelled by [`cancel`](Self::cancel) on any of its clones, which can be sent to the tasks of
+/// [`super::spawn_low_priority`] too. It can't be reset, a new one is needed once cancelled.
+#[derive(Clone, Default)]
+pub struct CancellationToken(Arc<Shared>);
+
+#[derive(Default)]
+struct Shared {
+    cancelled: AtomicBool,
+    /// Of the tasks waiting for [`CancellationToken::cancelled`]
+    wakers: Mutex<Vec<Waker>>,
+}
+
+impl CancellationToken {
+    pub fn cancel(&self) {
+        self.0.cancelled.store(true, Ordering::SeqCst);
+        for waker in self.0.wakers.lock().unwrap().drain(..) {
+            waker.wake();
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Resolves once the token is cancelled, right away if it already is
+    pub async fn cancelled(&self) {
+        std::future::poll_fn(|cx| {
+            // Checked with the wakers locked, so that `cancel` can't wake them in between
+            let mut wakers = self.0.wakers.lock().unwrap();
+            if self.is_cancelled() {
+                return Poll::Ready(());
+            }
+            if !wakers.iter().any(|waker| waker.will_wake(cx.waker())) {
+                wakers.push(cx.waker().clone());
+            }
+            Poll::Pending
+        })
+        .await
+    }
+}
+
+/// Runs `future` on the event loop with [`slint::spawn_local`] until it completes, or until
+/// `token` is cancelled, whichever comes first. A cancelled future is dropped at the `.await`
+/// that it was waiting on, and the handle resolves to `None`. It isn't started if `token` is
+/// already cancelled.
+pub fn spawn_cancellable<T: 'static>(
+    future: impl Future<Output = T> + 'static,
+    token: CancellationToken,
+) -> Result<slint::JoinHandle<Option<T>>, slint::EventLoopError> {
+    slint::spawn_local(async move {
+        let mut future = pin!(future);
+        let mut cancelled = pin!(token.cancelled());
+        std::future::poll_fn(|cx| {
+            if cancelled.as_mut().poll(cx).is_ready() {
+                return Poll::Ready(None);
+            }
+            future.as_mut().poll(cx).map(Some)
+        })
+        .await
+    })
+}
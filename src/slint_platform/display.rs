@@ -0,0 +1,326 @@
+//! The LCD panel: the one of the RGB interface of the board, or an SPI one, see `spi.rs`, which
+//! the [`DisplayHandle`] draws on until the platform is set.
+
+use core::sync::atomic::{AtomicPtr, Ordering};
+
+use esp_idf_svc::sys::{esp, EspError};
+
+use super::{
+    frame_buffer, spi, tasks, text, EspPlatformConfig, InitError, Interface, RgbConfig, SyncMode,
+    DISPLAY_HEIGHT, DISPLAY_WIDTH, I2C,
+};
+
+pub(super) mod sys {
+    #![allow(non_camel_case_types)]
+    use core::ffi::c_void;
+    use esp_idf_svc::hal::sys::*;
+    #[repr(C)]
+    pub struct esp_lcd_rgb_timing_t {
+        /// Frequency of pixel clock
+        pub pclk_hz: u32,
+        /// Horizontal resolution, i.e. the number of pixels in a line
+        pub h_res: u32,
+        /// Vertical resolution, i.e. the number of lines in the frame
+        pub v_res: u32,
+        /// Horizontal sync width, unit: PCLK period
+        pub hsync_pulse_width: u32,
+        /// Horizontal back porch, number of PCLK between hsync and start of line active data
+        pub hsync_back_porch: u32,
+        /// Horizontal front porch, number of PCLK between the end of active data and the next hsync
+        pub hsync_front_porch: u32,
+        /// Vertical sync width, unit: number of lines
+        pub vsync_pulse_width: u32,
+        /// Vertical back porch, number of invalid lines between vsync and start of frame
+        pub vsync_back_porch: u32,
+        /// Vertical front porch, number of invalid lines between the end of frame and the next vsync
+        pub vsync_front_porch: u32,
+        pub flags: u32,
+    }
+
+    #[repr(C)]
+    pub struct esp_lcd_rgb_panel_config_t {
+        /// Clock source for the RGB LCD peripheral
+        pub clk_src: lcd_clock_source_t,
+        /// RGB timing parameters, including the screen resolution
+        pub timings: esp_lcd_rgb_timing_t,
+        /// Number of data lines
+        pub data_width: usize,
+        /// Frame buffer color depth, in bpp, specially, if set to zero, it will default to `data_width`.
+        /// When using a Serial RGB interface, this value could be different from `data_width`
+        pub bits_per_pixel: usize,
+        /// Number of screen-sized frame buffers that allocated by the driver. By default (set to either 0 or 1) only one frame buffer will be used. Maximum number of buffers are 3
+        pub num_fbs: usize,
+        /// If it's non-zero, the driver allocates two DRAM bounce buffers for DMA use.
+        /// DMA fetching from DRAM bounce buffer is much faster than PSRAM frame buffer.
+        pub bounce_buffer_size_px: usize,
+        /// Alignment of buffers (frame buffer or bounce buffer) that allocated in SRAM
+        pub sram_trans_align: usize,
+        /// DMA burst size, in bytes
+        pub dma_burst_size: usize,
+        /// GPIO used for HSYNC signal
+        pub hsync_gpio_num: i32,
+        /// GPIO used for VSYNC signal
+        pub vsync_gpio_num: i32,
+        /// GPIO used for DE signal, set to -1 if it's not used
+        pub de_gpio_num: i32,
+        /// GPIO used for PCLK signal, set to -1 if it's not used
+        pub pclk_gpio_num: i32,
+        /// GPIO used for display control signal, set to -1 if it's not used
+        pub disp_gpio_num: i32,
+        /// GPIOs used for data lines
+        pub data_gpio_nums: [i32; SOC_LCDCAM_RGB_DATA_WIDTH as usize],
+
+        pub flags: u32,
+    }
+
+    #[repr(C)]
+    pub struct esp_lcd_rgb_panel_event_callbacks_t {
+        pub on_color_trans_done: Option<
+            extern "C" fn(
+                panel: esp_idf_svc::hal::sys::esp_lcd_panel_handle_t,
+                edata: *const c_void,
+                user_ctx: *mut c_void,
+            ) -> bool,
+        >,
+        pub on_vsync: Option<
+            extern "C" fn(
+                panel: esp_idf_svc::hal::sys::esp_lcd_panel_handle_t,
+                edata: *const c_void,
+                user_ctx: *mut c_void,
+            ) -> bool,
+        >,
+        /// Fills `bounce_buf` with `len_bytes` of the frame from `pos_px`. The driver only calls
+        /// it without frame buffers, it copies the frame buffer to the bounce buffers itself
+        /// otherwise. So it can't be used to detect the refills that are too late for the DMA.
+        pub on_bounce_empty: Option<
+            extern "C" fn(
+                panel: esp_idf_svc::hal::sys::esp_lcd_panel_handle_t,
+                bounce_buf: *mut c_void,
+                pos_px: i32,
+                len_bytes: i32,
+                user_ctx: *mut c_void,
+            ) -> bool,
+        >,
+        pub on_frame_buf_complete: Option<
+            extern "C" fn(
+                panel: esp_idf_svc::hal::sys::esp_lcd_panel_handle_t,
+                edata: *const c_void,
+                user_ctx: *mut c_void,
+            ) -> bool,
+        >,
+    }
+
+    extern "C" {
+        pub fn esp_lcd_new_rgb_panel(
+            rgb_panel_config: *const esp_lcd_rgb_panel_config_t,
+            ret_panel: *mut esp_lcd_panel_handle_t,
+        ) -> esp_err_t;
+
+        pub fn esp_lcd_rgb_panel_get_frame_buffer(
+            panel: esp_lcd_panel_handle_t,
+            fb_num: u32,
+            fb0: *mut *mut u8,
+            ...
+        ) -> esp_err_t;
+
+        pub fn esp_lcd_rgb_panel_register_event_callbacks(
+            panel: esp_lcd_panel_handle_t,
+            callbacks: *const esp_lcd_rgb_panel_event_callbacks_t,
+            user_ctx: *mut c_void,
+        ) -> esp_err_t;
+    }
+}
+
+/// The LCD panel initialized by [`init_display`], which is drawn by Slint once [`init_touch`](super::init_touch)
+/// set the platform
+pub struct DisplayHandle {
+    pub(super) panel_handle: esp_idf_svc::hal::sys::esp_lcd_panel_handle_t,
+    pub(super) config: EspPlatformConfig,
+}
+
+impl DisplayHandle {
+    /// Fills the screen with `color` before Slint draws the first frame, e.g. with the
+    /// background of the first window to show
+    pub fn fill(
+        &self,
+        color: slint::platform::software_renderer::Rgb565Pixel,
+    ) -> Result<(), EspError> {
+        self.draw(|buffer, _| buffer.fill(color))
+    }
+
+    /// Draws on the screen before Slint draws the first frame, into a frame buffer with its
+    /// width, which is also the length of its lines. It isn't cleared beforehand, except for the
+    /// SPI panels, whose frame is drawn into a black one that is sent once drawn. Fails if the
+    /// frame buffer of the RGB panel can't be had, then `draw` isn't called.
+    pub fn draw(
+        &self,
+        draw: impl FnOnce(&mut [slint::platform::software_renderer::Rgb565Pixel], usize),
+    ) -> Result<(), EspError> {
+        let (width, height) = self.config.interface.size();
+        if let Interface::Spi(_) = self.config.interface {
+            let mut buffer =
+                vec![slint::platform::software_renderer::Rgb565Pixel(0); width * height];
+            draw(&mut buffer, width);
+            frame_buffer::send_to_spi_panel(self.panel_handle, &buffer, width);
+            return Ok(());
+        }
+        let buffer = unsafe {
+            let mut buffer = std::ptr::null_mut();
+            esp!(sys::esp_lcd_rgb_panel_get_frame_buffer(
+                self.panel_handle,
+                1,
+                &mut buffer
+            ))?;
+            core::slice::from_raw_parts_mut(
+                buffer as *mut slint::platform::software_renderer::Rgb565Pixel,
+                width * height,
+            )
+        };
+        draw(buffer, width);
+        // Flushes the cache of the frame buffer, which is in PSRAM
+        esp!(unsafe {
+            esp_idf_svc::hal::sys::esp_lcd_panel_draw_bitmap(
+                self.panel_handle,
+                0,
+                0,
+                width as i32,
+                height as i32,
+                buffer.as_ptr().cast(),
+            )
+        })
+    }
+}
+
+/// The panel of the RGB interface, for [`draw_panic_message`], until it is deleted
+pub(super) static RGB_PANEL: AtomicPtr<esp_idf_svc::hal::sys::esp_lcd_panel_t> =
+    AtomicPtr::new(core::ptr::null_mut());
+
+/// Writes `message` in white on a red screen, into both frame buffers of the RGB panel, which
+/// the LCD peripheral keeps on showing without Slint. It doesn't allocate or lock, so it can be
+/// called from the panic hook, whatever Slint was drawing meanwhile. Does nothing before
+/// [`init_display`], or with an SPI panel, whose bus may be in use.
+pub fn draw_panic_message(message: core::fmt::Arguments) {
+    let panel_handle = RGB_PANEL.load(Ordering::Relaxed);
+    if panel_handle.is_null() {
+        return;
+    }
+    let (mut back, mut front) = (core::ptr::null_mut(), core::ptr::null_mut());
+    if esp!(unsafe {
+        sys::esp_lcd_rgb_panel_get_frame_buffer(panel_handle, 2, &mut back, &mut front)
+    })
+    .is_err()
+    {
+        return;
+    }
+    for pointer in [back, front] {
+        let buffer = unsafe {
+            core::slice::from_raw_parts_mut(
+                pointer as *mut slint::platform::software_renderer::Rgb565Pixel,
+                DISPLAY_WIDTH * DISPLAY_HEIGHT,
+            )
+        };
+        text::draw_message(
+            buffer,
+            DISPLAY_WIDTH,
+            slint::platform::software_renderer::TargetPixel::from_rgb(0xb0, 0x00, 0x20),
+            message,
+        );
+        unsafe {
+            // Flushes the cache, and shows this frame buffer
+            esp_idf_svc::hal::sys::esp_lcd_panel_draw_bitmap(
+                panel_handle,
+                0,
+                0,
+                DISPLAY_WIDTH as i32,
+                DISPLAY_HEIGHT as i32,
+                pointer.cast(),
+            );
+        }
+    }
+}
+
+/// Initializes the LCD panel. Must be called once, before [`init_touch`](super::init_touch).
+pub fn init_display(config: EspPlatformConfig) -> Result<DisplayHandle, InitError> {
+    *tasks::PRIORITIES.lock().unwrap() = config.task_priorities;
+
+    let panel_handle = match &config.interface {
+        Interface::Rgb(rgb_config) => new_rgb_panel(rgb_config, config.sync_mode),
+        Interface::Spi(spi_config) => spi::new_panel(spi_config),
+    }
+    .map_err(InitError::PanelInitFailed)?;
+    if let Interface::Rgb(_) = config.interface {
+        RGB_PANEL.store(panel_handle, Ordering::Relaxed);
+    }
+    Ok(DisplayHandle {
+        panel_handle,
+        config,
+    })
+}
+
+/// The CH422G IO expander of the board is addressed by command: this one enables its outputs...
+const CH422G_MODE: u8 = 0x24;
+/// ...and this one sets them
+const CH422G_OUTPUT: u8 = 0x38;
+/// The EXIO2 output of the IO expander, which switches the backlight
+const CH422G_BACKLIGHT: u8 = 1 << 2;
+/// The other outputs, kept high: the resets of the touch controller on EXIO1 and of the panel on
+/// EXIO3, and the chip select of the SD card on EXIO4
+const CH422G_OTHER_OUTPUTS: u8 = (1 << 1) | (1 << 3) | (1 << 4);
+
+/// Switches the backlight of the RGB panel of the board, on the I2C bus of the touch controller
+pub(super) fn set_backlight(i2c: &mut I2C, on: bool) -> Result<(), EspError> {
+    let timeout = esp_idf_svc::hal::delay::TickType::new_millis(10).ticks();
+    i2c.write(CH422G_MODE, &[0x01], timeout)?;
+    let backlight = if on { CH422G_BACKLIGHT } else { 0 };
+    i2c.write(CH422G_OUTPUT, &[CH422G_OTHER_OUTPUTS | backlight], timeout)
+}
+
+/// Creates the panel of the RGB interface, with its two frame buffers
+fn new_rgb_panel(
+    config: &RgbConfig,
+    sync_mode: SyncMode,
+) -> Result<esp_idf_svc::hal::sys::esp_lcd_panel_handle_t, EspError> {
+    use esp_idf_svc::hal::sys::*;
+
+    let mut panel_handle: esp_lcd_panel_handle_t = std::ptr::null_mut();
+    let panel_config = sys::esp_lcd_rgb_panel_config_t {
+        clk_src: soc_module_clk_t_SOC_MOD_CLK_PLL_F160M, //LCD_CLK_SRC_DEFAULT,
+        timings: sys::esp_lcd_rgb_timing_t {
+            pclk_hz: 16 * 1000 * 1000,
+            h_res: DISPLAY_WIDTH as u32,
+            v_res: DISPLAY_HEIGHT as u32,
+            hsync_pulse_width: 4,
+            hsync_back_porch: 8,
+            hsync_front_porch: 8,
+            vsync_pulse_width: 4,
+            vsync_back_porch: 8,
+            vsync_front_porch: 8,
+            flags: 0b1000, // pclk_active_neg
+        },
+        data_width: 16,
+        bits_per_pixel: 16,
+        num_fbs: 2,
+        // Ten lines. The DMA reads stale lines when the bounce buffers aren't refilled in time,
+        // which shows as a shifted image; larger bounce buffers make that less likely.
+        bounce_buffer_size_px: DISPLAY_WIDTH * 10,
+        sram_trans_align: 4,
+        dma_burst_size: 64,
+        hsync_gpio_num: config.hsync_gpio_num,
+        vsync_gpio_num: config.vsync_gpio_num,
+        de_gpio_num: config.de_gpio_num,
+        pclk_gpio_num: config.pclk_gpio_num,
+        disp_gpio_num: -1,
+        data_gpio_nums: config.data_gpio_nums,
+        flags: 0b100, // fb_in_psram: Use PSRAM for framebuffer
+    };
+    unsafe {
+        esp!(sys::esp_lcd_new_rgb_panel(&panel_config, &mut panel_handle))?;
+        esp!(esp_lcd_panel_init(panel_handle))?;
+        esp!(sys::esp_lcd_rgb_panel_register_event_callbacks(
+            panel_handle,
+            &sync_mode.panel_callbacks(),
+            core::ptr::null_mut()
+        ))?;
+    }
+    Ok(panel_handle)
+}
@@ -0,0 +1,394 @@
+//! The event loop of the [`EspPlatform`]: it reads the touch controller, dispatches the touch
+//! events, and renders the frames into the frame buffers.
+
+use std::sync::{Arc, Mutex};
+
+#[cfg(feature = "ble-remote")]
+use super::ble_remote;
+#[cfg(feature = "light-sleep")]
+use super::light_sleep;
+use super::platform::EspPlatform;
+#[cfg(feature = "diagnostics-trace")]
+use super::trace;
+use super::{
+    display, fps_overlay, frame_buffer, gesture, record_error, state, stripes, touch, transition,
+    EspPlatformError, Interface, PlatformState,
+};
+
+/// The number of reads of the touch controller, one per iteration of the event loop, after which
+/// [`FrameStats`] checks how long they took
+const TOUCH_READ_WINDOW: usize = 60;
+/// How often the touch controller is read while the display is off
+const DISPLAY_OFF_POLL_PERIOD: core::time::Duration = core::time::Duration::from_millis(20);
+/// A 99th percentile of the reads above it suggests that the I2C bus is shared with a busy
+/// device, or that the controller stretches the clock
+const SLOW_TOUCH_READ_US: u32 = 2000;
+
+/// Statistics about the rendered frames, updated by the event loop
+#[derive(Default)]
+struct FrameStats {
+    /// Number of frames rendered per second, measured over the last second
+    fps: u32,
+    frames: u32,
+    period_start: core::time::Duration,
+    /// How long the reads of the touch controller since the last check took, in µs
+    touch_read_us: Vec<u32>,
+}
+
+impl FrameStats {
+    fn frame_rendered(&mut self, now: core::time::Duration) {
+        self.frames += 1;
+        let elapsed = now.saturating_sub(self.period_start);
+        if elapsed >= core::time::Duration::from_secs(1) {
+            self.fps = (self.frames as u128 * 1000 / elapsed.as_millis()) as u32;
+            self.frames = 0;
+            self.period_start = now;
+        }
+    }
+
+    /// Warns every [`TOUCH_READ_WINDOW`] reads if they were slow
+    fn touch_read(&mut self, duration: core::time::Duration) {
+        self.touch_read_us.push(duration.as_micros() as u32);
+        if self.touch_read_us.len() < TOUCH_READ_WINDOW {
+            return;
+        }
+        self.touch_read_us.sort_unstable();
+        let percentile_99 = self.touch_read_us[(self.touch_read_us.len() * 99).div_ceil(100) - 1];
+        if percentile_99 > SLOW_TOUCH_READ_US {
+            log::warn!("Slow touch controller, 99% of the reads take up to {percentile_99} µs");
+        }
+        self.touch_read_us.clear();
+    }
+}
+
+impl EspPlatform {
+    pub(super) fn run(&self) -> Result<(), EspPlatformError> {
+        use esp_idf_svc::hal::sys::*;
+
+        transition(PlatformState::Ready, PlatformState::Running)?;
+
+        unsafe {
+            // Initialize the LCD panel
+            if let Err(err) = esp!(esp_lcd_panel_init(self.panel_handle)) {
+                self.stop(true)?;
+                return Err(EspPlatformError::PanelInit(err));
+            }
+
+            // Turn on the display
+            esp_lcd_panel_disp_on_off(self.panel_handle, true);
+
+            // Calling this function rotates the display by 180 degrees, the board has it
+            // upside down
+            if let Interface::Rgb(_) = self.config.interface {
+                esp_lcd_panel_mirror(self.panel_handle, true, true);
+            }
+        }
+
+        let (width, height) = self.config.interface.size();
+        let mut frame_buffers = match self.config.interface {
+            // `DisplayHandle::draw` is done with them once the event loop runs
+            Interface::Rgb(_) => match unsafe {
+                frame_buffer::FrameBufferPair::from_rgb_panel(self.panel_handle, width, height)
+            } {
+                Ok(frame_buffers) => frame_buffers,
+                Err(err) => {
+                    self.stop(true)?;
+                    return Err(EspPlatformError::PanelInit(err));
+                }
+            },
+            Interface::Spi(_) => {
+                frame_buffer::FrameBufferPair::allocate(self.panel_handle, width, height)
+            }
+        };
+
+        if self.config.pre_warm {
+            let mut rendered = Ok(());
+            self.window.draw_if_needed(|renderer| {
+                rendered = frame_buffers.wait_for_back_buffer();
+                if rendered.is_ok() {
+                    renderer.render(frame_buffers.back_mut(), width);
+                }
+            });
+            if let Err(err) = rendered {
+                self.stop(true)?;
+                return Err(err.into());
+            }
+            // Not swapped, the first iteration renders the whole frame again into the same
+            // buffer: with swapped buffers, the renderer also repaints what it rendered last
+            self.window.request_redraw();
+        }
+
+        let mut touch_slots = touch::TouchSlots::default();
+        gesture::TOUCH_HISTORY.with(|history| {
+            history
+                .borrow_mut()
+                .set_velocity_smoothing(self.config.velocity_smoothing)
+        });
+        let mut dwell = gesture::Dwell::default();
+        #[cfg(feature = "light-sleep")]
+        let mut light_sleep = match self.config.interface {
+            // The touch controller would only be read less often, see `light_sleep.rs`
+            Interface::Rgb(_) => {
+                log::info!(
+                    "No light sleep with the RGB interface, whose panel is always refreshed"
+                );
+                None
+            }
+            Interface::Spi(_) => light_sleep::LightSleep::new()
+                .inspect_err(|err| log::warn!("Cannot enable the light sleep: {err}"))
+                .ok(),
+        };
+
+        // Follows `set_display_on`
+        let mut display_on = true;
+        let mut frame_stats = FrameStats::default();
+        let mut fps_overlay = self.config.fps_overlay.then(fps_overlay::FpsOverlay::new);
+        #[cfg(feature = "diagnostics-trace")]
+        let mut frame_number = 0u32;
+
+        #[cfg(feature = "ble-remote")]
+        if let Err(err) = ble_remote::start() {
+            log::warn!("Cannot advertise the touch events over BLE: {err:?}");
+        }
+
+        let dispatch = |event: slint::platform::WindowEvent| {
+            #[cfg(feature = "diagnostics-trace")]
+            trace::touch(&event);
+            #[cfg(feature = "ble-remote")]
+            ble_remote::notify_touch(&event);
+            self.window.dispatch_event(event);
+        };
+
+        'event_loop: loop {
+            slint::platform::update_timers_and_animations();
+
+            let mut queue = std::mem::take(&mut *self.queue.lock().unwrap()).into_iter();
+            while let Some(event) = queue.next() {
+                match event {
+                    Event::Invoke(event) => event(),
+                    Event::Quit => {
+                        // Before the ones queued meanwhile, for the next run of the event loop
+                        self.queue.lock().unwrap().splice(0..0, queue);
+                        break 'event_loop;
+                    }
+                }
+            }
+
+            // Released before the events are dispatched, see `SharedI2c`
+            let read_start = self.timer.now();
+            let points = self.touch.borrow_mut().read(&mut self.i2c.borrow_mut());
+            frame_stats.touch_read(self.timer.now() - read_start);
+            match points {
+                Ok(Some(points)) => {
+                    let change = touch_slots.update(points.slots.map(|position| {
+                        position.map(|position| position.to_logical(self.window.scale_factor()))
+                    }));
+                    if let touch::PointerChange::Pressed(position)
+                    | touch::PointerChange::Moved(position) = change
+                    {
+                        gesture::PINCH.set(
+                            touch_slots
+                                .second()
+                                .map(|second| gesture::Pinch::new(position, second)),
+                        );
+                        gesture::TOUCH_HISTORY.with(|history| {
+                            let mut history = history.borrow_mut();
+                            if let touch::PointerChange::Pressed(_) = change {
+                                history.clear();
+                            }
+                            history.push(position, self.timer.now());
+                        });
+                    } else {
+                        gesture::PINCH.set(None);
+                    }
+                    match change {
+                        touch::PointerChange::Pressed(position) => {
+                            dispatch(slint::platform::WindowEvent::PointerPressed {
+                                position,
+                                button: slint::platform::PointerEventButton::Left,
+                            });
+                            dispatch(slint::platform::WindowEvent::PointerMoved { position });
+                        }
+                        touch::PointerChange::Moved(position) => {
+                            dispatch(slint::platform::WindowEvent::PointerMoved { position });
+                        }
+                        touch::PointerChange::Released(position) => {
+                            dispatch(slint::platform::WindowEvent::PointerReleased {
+                                position,
+                                button: slint::platform::PointerEventButton::Left,
+                            });
+                            dispatch(slint::platform::WindowEvent::PointerExited);
+                        }
+                        touch::PointerChange::None => {}
+                    }
+                }
+                Ok(None) => {
+                    //skip
+                }
+                Err(err) => {
+                    log::error!("Error reading the touch screen: {err}");
+                }
+            }
+            // Also while the finger doesn't move, and the controller has no new reading
+            if let Some(dwell_ms) = self.config.hover_dwell_ms {
+                dwell.update(
+                    touch_slots.pointer(),
+                    self.timer.now(),
+                    core::time::Duration::from_millis(dwell_ms.into()),
+                );
+            }
+
+            // Also off until the chip sleeps, once `deep_sleep` was called from another thread
+            let display_off = matches!(
+                state(),
+                PlatformState::DisplayOff | PlatformState::DeepSleep
+            );
+            if display_off == display_on {
+                display_on = !display_on;
+                self.switch_display(display_on);
+            }
+            if !display_on {
+                // Rendered once the display is on again
+                std::thread::sleep(DISPLAY_OFF_POLL_PERIOD);
+                continue;
+            }
+
+            // Draw the scene if something needs to be drawn.
+            let mut drawn = Ok(());
+            self.window.draw_if_needed(|renderer| {
+                #[cfg(feature = "diagnostics-trace")]
+                trace::render_started(frame_number);
+                drawn = frame_buffers.wait_for_back_buffer();
+                if drawn.is_err() {
+                    return;
+                }
+                let buffer = frame_buffers.back_mut();
+                if let Some(fps_overlay) = &fps_overlay {
+                    fps_overlay.restore(buffer, width);
+                }
+                let region = renderer.render(buffer, width);
+                stripes::draw(buffer, width, &region);
+                #[cfg(feature = "gamma")]
+                if let Some(gamma_table) = &self.gamma_table {
+                    // After the stripes, and before the overlay, which saves the corrected pixels
+                    // to restore them
+                    gamma_table.apply(buffer, width, &region);
+                }
+                frame_stats.frame_rendered(self.timer.now());
+                drawn = match &mut fps_overlay {
+                    Some(fps_overlay) => {
+                        fps_overlay.draw(buffer, width, frame_stats.fps);
+                        frame_buffers.swap()
+                    }
+                    // The overlay draws into each buffer in turn, so they are always swapped
+                    None => frame_buffers.swap_if_changed(&region),
+                };
+                #[cfg(feature = "diagnostics-trace")]
+                {
+                    trace::render_finished(frame_number);
+                    frame_number = frame_number.wrapping_add(1);
+                }
+            });
+            if let Err(err) = drawn {
+                self.stop(display_on)?;
+                return Err(err.into());
+            }
+
+            // Try to put the MCU to sleep
+            #[cfg(feature = "light-sleep")]
+            if let Some(light_sleep) = &mut light_sleep {
+                let idle = !self.window.has_active_animations() && !touch_slots.pressed();
+                light_sleep.allow(idle);
+                if idle {
+                    let wait = slint::platform::duration_until_next_timer_update()
+                        .map_or(light_sleep::POLL_PERIOD, |next| {
+                            next.min(light_sleep::POLL_PERIOD)
+                        });
+                    std::thread::sleep(wait);
+                    continue;
+                }
+            }
+            if !self.window.has_active_animations() {
+                continue;
+            }
+
+            // FIXME
+            esp_idf_svc::hal::task::do_yield();
+        }
+
+        self.stop(display_on)
+    }
+
+    /// Leaves the `Running` state, or the `DisplayOff` one, whose display is switched on again,
+    /// e.g. for the next component or an error screen. `display_on` tells whether the event loop
+    /// switched it off.
+    fn stop(&self, display_on: bool) -> Result<(), EspPlatformError> {
+        if transition(PlatformState::DisplayOff, PlatformState::Ready).is_err() {
+            transition(PlatformState::Running, PlatformState::Ready)?;
+        }
+        if !display_on {
+            self.switch_display(true);
+        }
+        Ok(())
+    }
+
+    /// Switches the backlight of the board, or the SPI panel, which has no backlight of its own
+    fn switch_display(&self, on: bool) {
+        let switched = match self.config.interface {
+            Interface::Rgb(_) => display::set_backlight(&mut self.i2c.borrow_mut(), on),
+            Interface::Spi(_) => esp_idf_svc::sys::esp!(unsafe {
+                esp_idf_svc::hal::sys::esp_lcd_panel_disp_on_off(self.panel_handle, on)
+            }),
+        };
+        let on = if on { "on" } else { "off" };
+        match switched {
+            Ok(()) => log::info!("Switched the display {on}"),
+            Err(err) => log::warn!("Cannot switch the display {on}: {err}"),
+        }
+    }
+}
+
+pub(super) enum Event {
+    Quit,
+    Invoke(Box<dyn FnOnce() + Send>),
+}
+pub(super) struct EspEventLoopProxy {
+    pub(super) queue: Arc<Mutex<Vec<Event>>>,
+    pub(super) max_queue_depth: usize,
+}
+
+// SAFETY: the proxies are moved to the threads that call `slint::invoke_from_event_loop`, unlike
+// `EspPlatform`, whose `Rc` of the window stays on the thread of the event loop. They only hold
+// the `Arc<Mutex<_>>` of the queue, whose events are `Send`, so they are `Send` and `Sync`
+// without an `unsafe impl`. Fails to build if a field that isn't is added.
+const _: () = {
+    const fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<EspEventLoopProxy>();
+};
+
+/// The number of closures that couldn't be queued by [`EspEventLoopProxy`] since the start
+static QUEUE_DROPPED_COUNT: core::sync::atomic::AtomicU32 = core::sync::atomic::AtomicU32::new(0);
+
+impl slint::platform::EventLoopProxy for EspEventLoopProxy {
+    fn quit_event_loop(&self) -> Result<(), slint::EventLoopError> {
+        self.queue.lock().unwrap().push(Event::Quit);
+        Ok(())
+    }
+
+    fn invoke_from_event_loop(
+        &self,
+        event: Box<dyn FnOnce() + Send>,
+    ) -> Result<(), slint::EventLoopError> {
+        let mut queue = self.queue.lock().unwrap();
+        if queue.len() >= self.max_queue_depth {
+            let dropped =
+                QUEUE_DROPPED_COUNT.fetch_add(1, core::sync::atomic::Ordering::Relaxed) + 1;
+            log::warn!("The event loop queue is full, {dropped} closures dropped so far");
+            record_error(EspPlatformError::QueueFull);
+            // There is no error for a full queue
+            return Err(slint::EventLoopError::EventLoopTerminated);
+        }
+        queue.push(Event::Invoke(event));
+        Ok(())
+    }
+}
@@ -0,0 +1,71 @@
+//! A frames-per-second counter drawn directly into the frame buffer, on top of the Slint scene.
+
+use slint::platform::software_renderer::Rgb565Pixel;
+
+use super::text::{glyph, GLYPH_HEIGHT, GLYPH_WIDTH};
+
+/// Every pixel of the font is drawn as a `SCALE`×`SCALE` square
+const SCALE: usize = 2;
+/// Maximum number of characters in the overlay, e.g. "120 FPS"
+const MAX_CHARS: usize = 7;
+/// Size of the overlay, in physical pixels. There is one empty font pixel around each glyph.
+const WIDTH: usize = (MAX_CHARS * GLYPH_WIDTH + 1) * SCALE;
+const HEIGHT: usize = (GLYPH_HEIGHT + 1) * SCALE;
+
+pub struct FpsOverlay {
+    /// The Slint pixels that were covered by the overlay in each of the two frame buffers.
+    /// They must be put back before Slint renders into that buffer again, since Slint only
+    /// redraws the dirty regions.
+    saved: [Vec<Rgb565Pixel>; 2],
+    /// Index in `saved` for the next buffer to be drawn
+    current: usize,
+}
+
+impl FpsOverlay {
+    pub fn new() -> Self {
+        Self {
+            saved: [
+                Vec::with_capacity(WIDTH * HEIGHT),
+                Vec::with_capacity(WIDTH * HEIGHT),
+            ],
+            current: 0,
+        }
+    }
+
+    /// Puts back the pixels that were under the overlay the last time it was drawn in this buffer.
+    /// Must be called before rendering the Slint scene into the buffer.
+    pub fn restore(&self, buffer: &mut [Rgb565Pixel], stride: usize) {
+        for (row, pixels) in self.saved[self.current].chunks(WIDTH).enumerate() {
+            buffer[row * stride..][..WIDTH].copy_from_slice(pixels);
+        }
+    }
+
+    /// Draws the counter in the top left corner of the buffer, over a darkened background.
+    pub fn draw(&mut self, buffer: &mut [Rgb565Pixel], stride: usize, fps: u32) {
+        let saved = &mut self.saved[self.current];
+        saved.clear();
+        for row in 0..HEIGHT {
+            let line = &mut buffer[row * stride..][..WIDTH];
+            saved.extend_from_slice(line);
+            for pixel in line {
+                // Halve each of the color components
+                *pixel = Rgb565Pixel((pixel.0 >> 1) & 0x7bef);
+            }
+        }
+        self.current ^= 1;
+
+        let text = format!("{fps} FPS");
+        for (index, c) in text.chars().take(MAX_CHARS).enumerate() {
+            let x = (1 + index * GLYPH_WIDTH) * SCALE;
+            for (glyph_y, glyph_row) in glyph(c).chunks(GLYPH_WIDTH).enumerate() {
+                for (glyph_x, _) in glyph_row.iter().enumerate().filter(|(_, set)| **set != 0) {
+                    for dy in 0..SCALE {
+                        let y = (1 + glyph_y) * SCALE + dy;
+                        buffer[y * stride + x + glyph_x * SCALE..][..SCALE]
+                            .fill(Rgb565Pixel(0xffff));
+                    }
+                }
+            }
+        }
+    }
+}
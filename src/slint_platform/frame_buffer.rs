@@ -0,0 +1,250 @@
+//! The two frame buffers of the panel, which draws one while Slint renders into the other, and
+//! the interrupt that tells when the panel is done with the one it drew before, see [`SyncMode`].
+//!
+//! An RGB panel keeps reading the front buffer of the last frame until the interrupt, so the
+//! next frame can only be rendered after it. An SPI panel only reads the front buffer while it is
+//! sent, with the DMA: the next frame is rendered meanwhile, and only the swap waits for it.
+
+use core::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use esp_idf_svc::hal::sys::{
+    esp_lcd_panel_draw_bitmap, esp_lcd_panel_handle_t, esp_lcd_panel_io_event_data_t,
+    esp_lcd_panel_io_handle_t, esp_rom_crc32_le,
+};
+use esp_idf_svc::sys::{esp, EspError};
+use slint::platform::software_renderer::{PhysicalRegion, Rgb565Pixel};
+use slint::{PhysicalPosition, PhysicalSize};
+
+use super::display::sys;
+
+/// When the back buffer can be rendered into again after a [`FrameBufferPair::swap`], with the
+/// RGB interface. An SPI panel is done with it once it was sent.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SyncMode {
+    /// At the next VSYNC, once the panel draws the new front buffer, so that the frames don't
+    /// tear
+    #[default]
+    Vsync,
+    /// As soon as `esp_lcd_panel_draw_bitmap` took the new front buffer, which the driver of the
+    /// RGB panel does right away, before the panel draws it: the old front buffer is rendered
+    /// into while the panel still draws it, so the frames tear, but this never waits for the
+    /// panel, e.g. for factory tests or dashboards
+    // Only chosen by the benchmark, the animations of the demo would tear
+    #[allow(dead_code)]
+    ColorTransDone,
+}
+
+impl SyncMode {
+    /// With the callback of the interrupt of this mode
+    pub(super) fn panel_callbacks(self) -> sys::esp_lcd_rgb_panel_event_callbacks_t {
+        let (on_color_trans_done, on_vsync) = match self {
+            SyncMode::Vsync => (None, Some(sync_callback as _)),
+            SyncMode::ColorTransDone => (Some(sync_callback as _), None),
+        };
+        sys::esp_lcd_rgb_panel_event_callbacks_t {
+            on_color_trans_done,
+            on_vsync,
+            on_bounce_empty: None,
+            on_frame_buf_complete: None,
+        }
+    }
+}
+
+/// Set by [`sync_callback`] at each interrupt of the [`SyncMode`], or by [`spi_sync_callback`]
+/// once the DMA sent a frame, cleared once a frame was handed to the panel
+static SYNCED: AtomicBool = AtomicBool::new(false);
+
+/// The callback of the interrupt of the [`SyncMode`]. It runs in an interrupt handler.
+extern "C" fn sync_callback(
+    _panel: esp_lcd_panel_handle_t,
+    _edata: *const core::ffi::c_void,
+    _user_ctx: *mut core::ffi::c_void,
+) -> bool {
+    SYNCED.store(true, Ordering::SeqCst);
+    false
+}
+
+/// The same for the SPI panels, called once a bitmap was sent
+pub(super) extern "C" fn spi_sync_callback(
+    _panel_io: esp_lcd_panel_io_handle_t,
+    _edata: *mut esp_lcd_panel_io_event_data_t,
+    _user_ctx: *mut core::ffi::c_void,
+) -> bool {
+    SYNCED.store(true, Ordering::SeqCst);
+    false
+}
+
+/// Many frames, even for a slow SPI panel
+const SYNC_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// The interrupt of the panel didn't come within [`SYNC_TIMEOUT`], it stopped drawing or sending
+/// the frames
+#[derive(Clone, Copy, Debug)]
+pub struct SyncTimeout;
+
+/// Waits for [`sync_callback`] or [`spi_sync_callback`]. Busy waits, yielding to the other tasks.
+fn wait_for_sync() -> Result<(), SyncTimeout> {
+    let start = Instant::now();
+    while !SYNCED.load(Ordering::SeqCst) {
+        if start.elapsed() > SYNC_TIMEOUT {
+            return Err(SyncTimeout);
+        }
+        esp_idf_svc::hal::task::do_yield();
+    }
+    Ok(())
+}
+
+/// Sends `buffer` to the panel, `width` pixels per line, or for the RGB interface, flushes the
+/// cache of the frame buffer and draws it from the next VSYNC on
+fn draw_bitmap(panel_handle: esp_lcd_panel_handle_t, buffer: &[Rgb565Pixel], width: usize) {
+    SYNCED.store(false, Ordering::SeqCst);
+    unsafe {
+        esp_lcd_panel_draw_bitmap(
+            panel_handle,
+            0,
+            0,
+            width as i32,
+            (buffer.len() / width) as i32,
+            buffer.as_ptr().cast(),
+        )
+    };
+}
+
+/// Sends `buffer` to an SPI panel, and waits until it is sent, so that it can be dropped
+pub(super) fn send_to_spi_panel(
+    panel_handle: esp_lcd_panel_handle_t,
+    buffer: &[Rgb565Pixel],
+    width: usize,
+) {
+    draw_bitmap(panel_handle, buffer, width);
+    // The DMA may still read the buffer, which is freed afterwards
+    while wait_for_sync().is_err() {
+        log::warn!("The SPI panel is still sending the frame");
+    }
+}
+
+/// The frame buffers of the panel, `width` pixels per line: the front one is on the screen, the
+/// back one is rendered
+pub struct FrameBufferPair {
+    panel_handle: esp_lcd_panel_handle_t,
+    width: usize,
+    /// Whether the panel is done with the back buffer once it is swapped, and only reads the
+    /// front one, see the module documentation
+    pipelined: bool,
+    front: &'static mut [Rgb565Pixel],
+    back: &'static mut [Rgb565Pixel],
+    /// The rectangles rendered into the front buffer before it was swapped, and the CRC32 of
+    /// their pixels, see [`swap_if_changed`](Self::swap_if_changed)
+    last_swapped: Option<(Vec<(PhysicalPosition, PhysicalSize)>, u32)>,
+}
+
+impl FrameBufferPair {
+    /// The frame buffers of an RGB panel, allocated by its driver
+    ///
+    /// # Safety
+    ///
+    /// The panel must have been created with two frame buffers of this size, which are only
+    /// accessed through this pair from now on
+    pub unsafe fn from_rgb_panel(
+        panel_handle: esp_lcd_panel_handle_t,
+        width: usize,
+        height: usize,
+    ) -> Result<Self, EspError> {
+        let (mut back, mut front) = (core::ptr::null_mut(), core::ptr::null_mut());
+        esp!(sys::esp_lcd_rgb_panel_get_frame_buffer(
+            panel_handle,
+            2,
+            &mut back,
+            &mut front
+        ))?;
+        let buffer = |pointer: *mut u8| {
+            core::slice::from_raw_parts_mut(pointer as *mut Rgb565Pixel, width * height)
+        };
+        Ok(Self {
+            panel_handle,
+            width,
+            pipelined: false,
+            front: buffer(front),
+            back: buffer(back),
+            last_swapped: None,
+        })
+    }
+
+    /// Black frame buffers for an SPI panel, which has none. They are big enough to be taken
+    /// from the PSRAM by the allocator, and are never freed.
+    pub fn allocate(panel_handle: esp_lcd_panel_handle_t, width: usize, height: usize) -> Self {
+        let buffer = || Vec::leak(vec![Rgb565Pixel(0); width * height]);
+        // Nothing is being sent yet
+        SYNCED.store(true, Ordering::SeqCst);
+        Self {
+            panel_handle,
+            width,
+            pipelined: true,
+            front: buffer(),
+            back: buffer(),
+            last_swapped: None,
+        }
+    }
+
+    // Nothing reads the pixels on the screen yet
+    #[allow(dead_code)]
+    pub fn front(&self) -> &[Rgb565Pixel] {
+        self.front
+    }
+
+    pub fn back_mut(&mut self) -> &mut [Rgb565Pixel] {
+        self.back
+    }
+
+    /// Waits for the panel to be done with the back buffer, which an RGB panel may still draw
+    /// after the last [`swap`](Self::swap), see [`SyncMode`]. Busy waits, yielding to the other
+    /// tasks.
+    pub fn wait_for_back_buffer(&self) -> Result<(), SyncTimeout> {
+        if !self.pipelined {
+            wait_for_sync()?;
+        }
+        Ok(())
+    }
+
+    /// Hands the back buffer to the panel, and makes the front buffer the next one to render.
+    /// With an SPI panel, it first waits for the front buffer to be sent, and the buffers aren't
+    /// swapped if it isn't.
+    pub fn swap(&mut self) -> Result<(), SyncTimeout> {
+        if self.pipelined {
+            wait_for_sync()?;
+        }
+        draw_bitmap(self.panel_handle, self.back, self.width);
+        core::mem::swap(&mut self.front, &mut self.back);
+        self.last_swapped = None;
+        Ok(())
+    }
+
+    /// Like [`swap`](Self::swap), unless Slint rendered the same pixels as in the front buffer,
+    /// e.g. when an idle clock redraws the same second, so that the panel isn't sent a frame
+    /// that it already shows. `rendered` also covers the pixels of the front buffer that were
+    /// rendered again, so both buffers are the same when they match, and Slint can render into
+    /// either one next.
+    pub fn swap_if_changed(&mut self, rendered: &PhysicalRegion) -> Result<(), SyncTimeout> {
+        let rectangles: Vec<_> = rendered.iter().collect();
+        let crc = rectangles.iter().fold(0, |crc, (origin, size)| {
+            let (left, width) = (origin.x as usize, size.width as usize);
+            (origin.y as usize..origin.y as usize + size.height as usize).fold(crc, |crc, y| {
+                let line = &self.back[y * self.width + left..][..width];
+                unsafe { esp_rom_crc32_le(crc, line.as_ptr().cast(), (width * 2) as u32) }
+            })
+        });
+        let unchanged = self
+            .last_swapped
+            .as_ref()
+            .is_some_and(|(last_rectangles, last_crc)| {
+                *last_rectangles == rectangles && *last_crc == crc
+            });
+        if unchanged {
+            return Ok(());
+        }
+        self.swap()?;
+        self.last_swapped = Some((rectangles, crc));
+        Ok(())
+    }
+}
@@ -0,0 +1,53 @@
+//! A gamma curve applied to the rendered pixels, for panels that show the mid tones too dark.
+
+use slint::platform::software_renderer::{PhysicalRegion, Rgb565Pixel};
+
+/// Lookup tables mapping each color channel of an [`Rgb565Pixel`] to its corrected value,
+/// indexed by the 5 bits of red and blue or the 6 bits of green
+pub struct GammaTable {
+    red_blue: [u16; 32],
+    green: [u16; 64],
+}
+
+impl GammaTable {
+    /// Raises each channel, normalized to 0..1, to the power `1 / gamma`, which compensates a
+    /// panel with that gamma. 2.2 is typical, 1 leaves the colors unchanged.
+    pub fn new(gamma: f32) -> Self {
+        let curve = |value: usize, max: usize| {
+            ((value as f32 / max as f32).powf(1. / gamma) * max as f32 + 0.5) as u16
+        };
+        Self {
+            red_blue: core::array::from_fn(|value| curve(value, 31)),
+            green: core::array::from_fn(|value| curve(value, 63)),
+        }
+    }
+
+    /// Corrects the pixels of `region`, which must only contain pixels rendered since the last
+    /// correction, since correcting a pixel twice brightens it again
+    pub fn apply(&self, buffer: &mut [Rgb565Pixel], stride: usize, region: &PhysicalRegion) {
+        for (origin, size) in region.iter() {
+            for y in origin.y as usize..origin.y as usize + size.height as usize {
+                let line = &mut buffer[y * stride + origin.x as usize..][..size.width as usize];
+                // Four pixels per iteration, the loop overhead is noticeable on a whole frame
+                let mut chunks = line.chunks_exact_mut(4);
+                for chunk in &mut chunks {
+                    chunk[0] = self.correct(chunk[0]);
+                    chunk[1] = self.correct(chunk[1]);
+                    chunk[2] = self.correct(chunk[2]);
+                    chunk[3] = self.correct(chunk[3]);
+                }
+                for pixel in chunks.into_remainder() {
+                    *pixel = self.correct(*pixel);
+                }
+            }
+        }
+    }
+
+    #[inline(always)]
+    fn correct(&self, pixel: Rgb565Pixel) -> Rgb565Pixel {
+        let red = self.red_blue[(pixel.0 >> 11) as usize];
+        let green = self.green[((pixel.0 >> 5) & 0x3f) as usize];
+        let blue = self.red_blue[(pixel.0 & 0x1f) as usize];
+        Rgb565Pixel(red << 11 | green << 5 | blue)
+    }
+}
@@ -0,0 +1,195 @@
+//! Keeps track of the recent touch positions in order to compute the velocity of the touch
+//! point, and of the second touch point for pinch gestures. They are queried from the Slint
+//! code, see `ui/gesture.slint`. The finger resting at the same place stands for hovering, which
+//! a touch screen can't tell, see [`set_dwell_handler`].
+
+use core::cell::{Cell, RefCell};
+use core::time::Duration;
+
+/// Number of touch samples kept in the history
+const HISTORY_LEN: usize = 8;
+/// A sample received more than that after the previous one starts the velocity over, instead of
+/// averaging it with the one of a finger that had stopped
+const VELOCITY_WINDOW: Duration = Duration::from_millis(100);
+/// The default of [`EspPlatformConfig::velocity_smoothing`](super::EspPlatformConfig)
+pub const DEFAULT_VELOCITY_SMOOTHING: f32 = 0.3;
+/// How far the finger can move while it rests at the same place, in logical pixels
+const DWELL_RADIUS: f32 = 10.;
+
+/// See [`set_dwell_handler`]
+type DwellHandler = Box<dyn Fn(Option<slint::LogicalPosition>)>;
+
+#[derive(Clone, Copy, Default)]
+struct TouchSample {
+    position: slint::LogicalPosition,
+    time: Duration,
+    /// Filtered, in logical pixels per second
+    velocity: (f32, f32),
+}
+
+/// The last few positions of the touch point, with the time at which they were received and the
+/// velocity of the touch point at that time. The velocity between two samples jitters at the rate
+/// of the touch controller, so it is smoothed by an exponential moving average:
+/// `alpha * raw + (1 - alpha) * previous`, for x and y.
+pub struct TouchRingBuffer {
+    samples: [TouchSample; HISTORY_LEN],
+    /// Index where the next sample will be written
+    next: usize,
+    len: usize,
+    /// The weight of the newest sample in the velocity, between 0 and 1
+    alpha: f32,
+}
+
+impl Default for TouchRingBuffer {
+    fn default() -> Self {
+        Self {
+            samples: Default::default(),
+            next: 0,
+            len: 0,
+            alpha: DEFAULT_VELOCITY_SMOOTHING,
+        }
+    }
+}
+
+impl TouchRingBuffer {
+    pub fn set_velocity_smoothing(&mut self, alpha: f32) {
+        self.alpha = alpha.clamp(0., 1.);
+    }
+
+    pub fn push(&mut self, position: slint::LogicalPosition, time: Duration) {
+        let velocity = match self.iter().next() {
+            Some(previous) if time > previous.time => {
+                let elapsed = time - previous.time;
+                let seconds = elapsed.as_secs_f32();
+                let raw = (
+                    (position.x - previous.position.x) / seconds,
+                    (position.y - previous.position.y) / seconds,
+                );
+                // The first velocity of a gesture has nothing to be averaged with
+                if self.len == 1 || elapsed > VELOCITY_WINDOW {
+                    raw
+                } else {
+                    (
+                        self.alpha * raw.0 + (1. - self.alpha) * previous.velocity.0,
+                        self.alpha * raw.1 + (1. - self.alpha) * previous.velocity.1,
+                    )
+                }
+            }
+            // Received in the same tick of the timer
+            Some(previous) => previous.velocity,
+            None => (0., 0.),
+        };
+        self.samples[self.next] = TouchSample {
+            position,
+            time,
+            velocity,
+        };
+        self.next = (self.next + 1) % HISTORY_LEN;
+        self.len = (self.len + 1).min(HISTORY_LEN);
+    }
+
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    /// Iterate over the samples, from the most recent to the oldest
+    fn iter(&self) -> impl Iterator<Item = &TouchSample> {
+        (1..=self.len).map(|i| &self.samples[(self.next + HISTORY_LEN - i) % HISTORY_LEN])
+    }
+
+    /// Velocity of the touch point in logical pixels per second, at the newest sample
+    pub fn velocity(&self) -> (f32, f32) {
+        self.iter()
+            .next()
+            .map_or((0., 0.), |newest| newest.velocity)
+    }
+}
+
+/// The first two fingers on the screen
+#[derive(Clone, Copy, Debug)]
+pub struct Pinch {
+    /// In logical pixels
+    pub distance: f32,
+    /// Midpoint of the two fingers, in logical window coordinates
+    pub center: slint::LogicalPosition,
+}
+
+impl Pinch {
+    pub fn new(first: slint::LogicalPosition, second: slint::LogicalPosition) -> Self {
+        Self {
+            distance: (second.x - first.x).hypot(second.y - first.y),
+            center: slint::LogicalPosition::new(
+                (first.x + second.x) / 2.,
+                (first.y + second.y) / 2.,
+            ),
+        }
+    }
+}
+
+/// Whether the finger has rested at the same place for long enough, see
+/// [`EspPlatformConfig::hover_dwell_ms`](super::EspPlatformConfig)
+#[derive(Default)]
+pub struct Dwell {
+    /// Where the finger came to rest, and when
+    anchor: Option<(slint::LogicalPosition, Duration)>,
+    dwelling: bool,
+}
+
+impl Dwell {
+    /// Takes the position of the finger at `now`, `None` once it is lifted, and calls the handler
+    /// of [`set_dwell_handler`] when the finger starts or stops dwelling
+    pub fn update(
+        &mut self,
+        position: Option<slint::LogicalPosition>,
+        now: Duration,
+        duration: Duration,
+    ) {
+        self.anchor = match (position, self.anchor) {
+            (Some(position), Some((anchor, since)))
+                if (position.x - anchor.x).hypot(position.y - anchor.y) <= DWELL_RADIUS =>
+            {
+                Some((anchor, since))
+            }
+            (Some(position), _) => Some((position, now)),
+            (None, _) => None,
+        };
+        let dwelling = self
+            .anchor
+            .filter(|(_, since)| now.saturating_sub(*since) >= duration)
+            .map(|(anchor, _)| anchor);
+        if dwelling.is_some() != self.dwelling {
+            self.dwelling = dwelling.is_some();
+            DWELL_HANDLER.with(|handler| {
+                if let Some(handler) = &*handler.borrow() {
+                    handler(dwelling);
+                }
+            });
+        }
+    }
+}
+
+thread_local! {
+    /// Filled by the event loop, and read by the Slint callbacks, which both run in the same thread
+    pub static TOUCH_HISTORY: RefCell<TouchRingBuffer> = Default::default();
+    /// Set by the event loop while at least two fingers touch the screen
+    pub static PINCH: Cell<Option<Pinch>> = const { Cell::new(None) };
+    static DWELL_HANDLER: RefCell<Option<DwellHandler>> = const { RefCell::new(None) };
+}
+
+/// Velocity of the current (or last) touch point, in logical pixels per second
+pub fn touch_velocity() -> (f32, f32) {
+    TOUCH_HISTORY.with(|history| history.borrow().velocity())
+}
+
+/// The first two fingers on the screen, if there are at least two of them
+pub fn pinch() -> Option<Pinch> {
+    PINCH.get()
+}
+
+/// Sets the function that the event loop calls with the position where the finger came to rest,
+/// in logical window coordinates, once it has rested there for
+/// [`EspPlatformConfig::hover_dwell_ms`](super::EspPlatformConfig), and with `None` once it moves
+/// more than 10 logical pixels away or is lifted
+pub fn set_dwell_handler(handler: impl Fn(Option<slint::LogicalPosition>) + 'static) {
+    DWELL_HANDLER.with(|dwell_handler| *dwell_handler.borrow_mut() = Some(Box::new(handler)));
+}
@@ -0,0 +1,62 @@
+//! Frees the I2C bus when a device holds SDA low, e.g. when the GT911 was reset in the middle of
+//! a read, and still waits for the clock to send the rest of a byte. The I2C peripheral then
+//! times out, since it can't send a start condition.
+
+use esp_idf_svc::hal::sys::{
+    esp, esp_rom_delay_us, gpio_config, gpio_config_t, gpio_get_level,
+    gpio_mode_t_GPIO_MODE_INPUT_OUTPUT_OD, gpio_pullup_t_GPIO_PULLUP_ENABLE, gpio_set_level,
+    i2c_mode_t_I2C_MODE_MASTER, i2c_set_pin, EspError,
+};
+
+use super::I2C;
+
+/// A clock of 100 kHz, which all the devices support
+const HALF_PERIOD_US: u32 = 5;
+
+/// Pulses SCL until the device releases SDA, at most 9 times, for the rest of a byte and the
+/// acknowledgement, and sends a stop condition. Both GPIOs are driven as open-drain outputs
+/// meanwhile, and given back to `i2c` afterwards. Returns whether SDA was released.
+pub fn i2c_bus_recover(i2c: &I2C, scl_gpio: i32, sda_gpio: i32) -> Result<bool, EspError> {
+    let config = gpio_config_t {
+        pin_bit_mask: (1 << scl_gpio) | (1 << sda_gpio),
+        mode: gpio_mode_t_GPIO_MODE_INPUT_OUTPUT_OD,
+        pull_up_en: gpio_pullup_t_GPIO_PULLUP_ENABLE,
+        ..Default::default()
+    };
+    let set = |gpio, level| {
+        esp!(unsafe { gpio_set_level(gpio, level) })?;
+        unsafe { esp_rom_delay_us(HALF_PERIOD_US) };
+        Ok::<_, EspError>(())
+    };
+    let sda_released = || unsafe { gpio_get_level(sda_gpio) } != 0;
+
+    esp!(unsafe { gpio_config(&config) })?;
+    set(sda_gpio, 1)?;
+    set(scl_gpio, 1)?;
+    for _ in 0..9 {
+        if sda_released() {
+            break;
+        }
+        set(scl_gpio, 0)?;
+        set(scl_gpio, 1)?;
+    }
+    // SDA rises while SCL is high
+    set(scl_gpio, 0)?;
+    set(sda_gpio, 0)?;
+    set(scl_gpio, 1)?;
+    set(sda_gpio, 1)?;
+    let released = sda_released();
+
+    // Connects the GPIOs to the I2C peripheral again, with the pull-ups of the driver
+    esp!(unsafe {
+        i2c_set_pin(
+            i2c.port(),
+            sda_gpio,
+            scl_gpio,
+            true,
+            true,
+            i2c_mode_t_I2C_MODE_MASTER,
+        )
+    })?;
+    Ok(released)
+}
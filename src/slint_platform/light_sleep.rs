@@ -0,0 +1,91 @@
+//! Light sleep while the UI is idle, with the `light-sleep` feature. The event loop then waits
+//! between the reads of the touch controller instead of polling it, and the tickless idle of
+//! FreeRTOS sleeps until the next wakeup of a timer.
+//!
+//! Light sleep stops the clocks of the peripherals. The drivers of those that must keep running
+//! hold a lock against it, but it may still delay their interrupts. The driver of the RGB panel
+//! holds its lock for as long as it refreshes the panel, which is always: the CPU would neither
+//! sleep nor slow down, so the event loop only sleeps with an SPI panel, which keeps its frame.
+
+use core::time::Duration;
+
+use esp_idf_svc::hal::sys::{
+    esp, esp_pm_config_t, esp_pm_configure, esp_pm_lock_acquire, esp_pm_lock_create,
+    esp_pm_lock_delete, esp_pm_lock_handle_t, esp_pm_lock_release,
+    esp_pm_lock_type_t_ESP_PM_NO_LIGHT_SLEEP, EspError,
+};
+
+/// Between two reads of the touch controller while the UI is idle, short enough for a tap
+pub const POLL_PERIOD: Duration = Duration::from_millis(20);
+
+/// Whether the CPU may sleep, which the event loop only allows while nothing is animated or
+/// touched
+pub struct LightSleep {
+    /// Held while the CPU must not sleep
+    lock: esp_pm_lock_handle_t,
+    allowed: bool,
+}
+
+impl LightSleep {
+    /// Enables the light sleep, and the lowering of the CPU frequency to 80 MHz while idle. The
+    /// CPU doesn't sleep until [`allow`](Self::allow) is called.
+    pub fn new() -> Result<Self, EspError> {
+        let mut lock = core::ptr::null_mut();
+        unsafe {
+            esp!(esp_pm_lock_create(
+                esp_pm_lock_type_t_ESP_PM_NO_LIGHT_SLEEP,
+                0,
+                c"event_loop".as_ptr(),
+                &mut lock,
+            ))?;
+            esp!(esp_pm_lock_acquire(lock))?;
+        }
+        configure(80, true)?;
+        Ok(Self {
+            lock,
+            allowed: false,
+        })
+    }
+
+    pub fn allow(&mut self, allowed: bool) {
+        if allowed == self.allowed {
+            return;
+        }
+        let result = unsafe {
+            if allowed {
+                esp!(esp_pm_lock_release(self.lock))
+            } else {
+                esp!(esp_pm_lock_acquire(self.lock))
+            }
+        };
+        match result {
+            Ok(()) => self.allowed = allowed,
+            Err(err) => log::warn!("Cannot change the light sleep lock: {err}"),
+        }
+    }
+}
+
+/// The CPU doesn't sleep anymore once the event loop returns
+impl Drop for LightSleep {
+    fn drop(&mut self) {
+        let result = configure(240, false).and_then(|()| unsafe {
+            if !self.allowed {
+                esp!(esp_pm_lock_release(self.lock))?;
+            }
+            esp!(esp_pm_lock_delete(self.lock))
+        });
+        if let Err(err) = result {
+            log::warn!("Cannot disable the light sleep: {err}");
+        }
+    }
+}
+
+/// The CPU runs at 240 MHz while it is busy, the frequency of `sdkconfig.defaults`
+fn configure(min_freq_mhz: i32, light_sleep_enable: bool) -> Result<(), EspError> {
+    let config = esp_pm_config_t {
+        max_freq_mhz: 240,
+        min_freq_mhz,
+        light_sleep_enable,
+    };
+    esp!(unsafe { esp_pm_configure(core::ptr::from_ref(&config).cast()) })
+}
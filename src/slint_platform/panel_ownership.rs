@@ -0,0 +1,24 @@
+//! Whether the platform deletes the LCD panel when it is dropped. Without ESP-IDF, so that
+//! `tests/panel_ownership.rs` checks it on the host.
+
+use core::cell::Cell;
+use std::rc::Rc;
+
+/// Shared by the platform and [`super::init_touch`], which [`take`](Self::take)s the panel for
+/// the platform once `set_platform` accepted it. Until then the panel belongs to the
+/// [`super::display::DisplayHandle`], which is given back if the platform is rejected.
+#[derive(Clone, Default)]
+pub struct PanelOwnership(Rc<Cell<bool>>);
+
+impl PanelOwnership {
+    pub fn take(&self) {
+        self.0.set(true);
+    }
+
+    /// Runs `delete` if the panel was taken, and gives it up, so that it runs at most once
+    pub fn release(&self, delete: impl FnOnce()) {
+        if self.0.replace(false) {
+            delete();
+        }
+    }
+}
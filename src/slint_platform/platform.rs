@@ -0,0 +1,194 @@
+//! The Slint platform on the display and the touch controller, set by [`init_touch`]. Its event
+//! loop is in `event_loop.rs`.
+
+use core::cell::RefCell;
+use core::sync::atomic::Ordering;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+
+use super::display::{set_backlight, sys, DisplayHandle, RGB_PANEL};
+use super::event_loop::{EspEventLoopProxy, Event};
+#[cfg(feature = "gamma")]
+use super::gamma;
+use super::i2c_recovery::i2c_bus_recover;
+use super::panel_ownership::PanelOwnership;
+use super::{
+    detect_touch_controller, record_error, touch, transition, EspPlatformConfig, InitError,
+    Interface, PlatformState, SharedI2c, TouchControllerKind, I2C, TOUCH_CONTROLLER,
+};
+
+pub(super) struct EspPlatform {
+    pub(super) panel_handle: esp_idf_svc::hal::sys::esp_lcd_panel_handle_t,
+    pub(super) touch: RefCell<Box<dyn touch::TouchController>>,
+    pub(super) i2c: SharedI2c,
+    pub(super) window: Rc<slint::platform::software_renderer::MinimalSoftwareWindow>,
+    pub(super) timer: esp_idf_svc::timer::EspTimerService<esp_idf_svc::timer::Task>,
+    pub(super) queue: Arc<Mutex<Vec<Event>>>,
+    pub(super) config: EspPlatformConfig,
+    /// Computed from [`EspPlatformConfig::gamma_correction`]
+    #[cfg(feature = "gamma")]
+    pub(super) gamma_table: Option<gamma::GammaTable>,
+    /// Taken once `set_platform` took this platform
+    panel_ownership: PanelOwnership,
+}
+
+/// Initializes the touch controller and sets the platform that draws on the display, which takes
+/// the panel of `display`. Must be called once, before any Slint component is created. When this
+/// fails, `display` is given back to show the error. If no touch controller is found, the UI is
+/// shown all the same but can't be touched.
+// Only called once, the size of the error doesn't matter
+#[allow(clippy::result_large_err)]
+pub fn init_touch(
+    display: DisplayHandle,
+    i2c: SharedI2c,
+) -> Result<(), (InitError, DisplayHandle)> {
+    let panel_ownership = PanelOwnership::default();
+    let platform = match new_platform(&display, i2c, panel_ownership.clone()) {
+        Ok(platform) => platform,
+        Err(err) => return Err((err, display)),
+    };
+    // The rejected platform is dropped without deleting the panel
+    if slint::platform::set_platform(Box::new(platform)).is_err() {
+        return Err((InitError::PlatformAlreadySet, display));
+    }
+    panel_ownership.take();
+    // Can't fail, `set_platform` only succeeds once
+    let _ = transition(PlatformState::Uninitialized, PlatformState::Ready);
+    Ok(())
+}
+
+/// The platform on the panel of `display`, with the touch controller on `i2c`
+fn new_platform(
+    display: &DisplayHandle,
+    i2c: SharedI2c,
+    panel_ownership: PanelOwnership,
+) -> Result<EspPlatform, InitError> {
+    let mut bus = i2c.borrow_mut();
+    let (scl_gpio, sda_gpio) = display.config.i2c_gpio_nums;
+    let recover = |bus: &I2C| match i2c_bus_recover(bus, scl_gpio, sda_gpio) {
+        Ok(true) => log::info!("Freed the I2C bus"),
+        Ok(false) => log::warn!("A device still holds SDA of the I2C bus low"),
+        Err(err) => log::warn!("Cannot free the I2C bus: {err}"),
+    };
+    let touch_controller = display.config.touch_controller.or_else(|| {
+        // No controller answers while the bus is held
+        let detected = detect_touch_controller(&mut bus).or_else(|| {
+            recover(&bus);
+            detect_touch_controller(&mut bus)
+        });
+        match detected {
+            Some(kind) => log::info!("Detected the {kind:?} touch controller"),
+            None => log::warn!("No touch controller found, the screen can't be touched"),
+        }
+        // The IO expander of the board took the probe of the FT5x06 for its outputs, including
+        // the resets of the touch controller and of the panel, see `detect_touch_controller`
+        let probed_ft5x06 = !matches!(
+            detected,
+            Some(TouchControllerKind::Gt911 | TouchControllerKind::Ft5x06)
+        );
+        if probed_ft5x06 && matches!(display.config.interface, Interface::Rgb(_)) {
+            if let Err(err) = set_backlight(&mut bus, true) {
+                log::warn!("Cannot set the outputs of the IO expander again: {err}");
+            }
+        }
+        detected
+    });
+    let touch: Box<dyn touch::TouchController> = match touch_controller {
+        Some(kind) => match kind.init(&mut bus) {
+            Err(err) if err.is_bus_timeout() => {
+                log::warn!("{err}, initializing the touch controller again on a free bus");
+                recover(&bus);
+                kind.init(&mut bus)
+            }
+            initialized => initialized,
+        }
+        .map_err(InitError::TouchInitFailed)?,
+        None => Box::new(touch::NoTouchController),
+    };
+    drop(bus);
+    *TOUCH_CONTROLLER.lock().unwrap() = touch_controller;
+
+    let window = slint::platform::software_renderer::MinimalSoftwareWindow::new(
+        slint::platform::software_renderer::RepaintBufferType::SwappedBuffers,
+    );
+    let (width, height) = display.config.interface.size();
+    window.set_size(slint::PhysicalSize::new(width as u32, height as u32));
+
+    let timer = esp_idf_svc::timer::EspTimerService::new().map_err(InitError::TimerInitFailed)?;
+    Ok(EspPlatform {
+        panel_handle: display.panel_handle,
+        touch: touch.into(),
+        i2c,
+        window,
+        timer,
+        queue: Default::default(),
+        config: display.config.clone(),
+        #[cfg(feature = "gamma")]
+        gamma_table: display
+            .config
+            .gamma_correction
+            .filter(|gamma| *gamma > 0.)
+            .map(gamma::GammaTable::new),
+        panel_ownership,
+    })
+}
+
+impl slint::platform::Platform for EspPlatform {
+    fn create_window_adapter(
+        &self,
+    ) -> Result<Rc<dyn slint::platform::WindowAdapter>, slint::PlatformError> {
+        // Since on MCUs, there can be only one window, just return a clone of self.window.
+        // We'll also use the same window in the event loop.
+        Ok(self.window.clone())
+    }
+    fn duration_since_start(&self) -> core::time::Duration {
+        self.timer.now()
+    }
+    fn run_event_loop(&self) -> Result<(), slint::PlatformError> {
+        self.run().map_err(|err| record_error(err).into())
+    }
+
+    fn debug_log(&self, arguments: core::fmt::Arguments) {
+        log::debug!("{}", arguments);
+    }
+
+    fn new_event_loop_proxy(&self) -> Option<Box<dyn slint::platform::EventLoopProxy>> {
+        Some(Box::new(EspEventLoopProxy {
+            queue: self.queue.clone(),
+            max_queue_depth: self.config.max_queue_depth,
+        }))
+    }
+}
+
+/// `set_platform` keeps the platform until the end, but a platform that is replaced must not
+/// leave the interrupts of the panel calling into it. The [`DisplayHandle`] that the panel comes
+/// from was taken by [`init_touch`]. The SPI bus and the panel IO of an SPI panel are kept.
+impl Drop for EspPlatform {
+    fn drop(&mut self) {
+        use esp_idf_svc::hal::sys::*;
+
+        // Not when rejected by `set_platform`, the panel is still drawn on by the `DisplayHandle`
+        self.panel_ownership.release(|| unsafe {
+            if matches!(self.config.interface, Interface::Rgb(_)) {
+                // A panic must not draw into the frame buffers that are freed below
+                RGB_PANEL.store(core::ptr::null_mut(), Ordering::Relaxed);
+                let no_callbacks = sys::esp_lcd_rgb_panel_event_callbacks_t {
+                    on_color_trans_done: None,
+                    on_vsync: None,
+                    on_bounce_empty: None,
+                    on_frame_buf_complete: None,
+                };
+                if let Err(err) = esp!(sys::esp_lcd_rgb_panel_register_event_callbacks(
+                    self.panel_handle,
+                    &no_callbacks,
+                    core::ptr::null_mut()
+                )) {
+                    log::warn!("Cannot remove the callbacks of the LCD panel: {err}");
+                }
+            }
+            if let Err(err) = esp!(esp_lcd_panel_del(self.panel_handle)) {
+                log::warn!("Cannot delete the LCD panel: {err}");
+            }
+        });
+    }
+}
@@ -0,0 +1,89 @@
+//! Replaces the platform of the board with the winit backend of Slint, which shows the UI in a
+//! window on the desktop, see the README. The mouse is the only finger, so there are no pinches,
+//! the touch velocity is zero and the finger never dwells. The FPS overlay, the gamma correction
+//! and the stripes of the `AnimatedBackground` component aren't drawn: the background shows its
+//! key color.
+
+use core::convert::Infallible;
+use core::future::Future;
+use core::task::{Context, Poll, Waker};
+use std::sync::Arc;
+use std::task::Wake;
+
+use slint::platform::software_renderer::Rgb565Pixel;
+
+use super::EspPlatformConfig;
+
+/// Why [`init_touch`] failed
+#[derive(Debug)]
+pub enum InitError {
+    /// The winit backend can't be created, e.g. without a display server
+    BackendInitFailed(slint::PlatformError),
+}
+
+impl core::fmt::Display for InitError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            InitError::BackendInitFailed(err) => {
+                write!(f, "Cannot initialize the winit backend: {err}")
+            }
+        }
+    }
+}
+
+/// Stands for the LCD panel, the window is only opened when the first component is shown
+pub struct DisplayHandle;
+
+impl DisplayHandle {
+    /// Does nothing, there is no window to fill yet
+    pub fn fill(&self, _color: Rgb565Pixel) -> Result<(), Infallible> {
+        Ok(())
+    }
+
+    /// Does nothing either
+    pub fn draw(&self, _draw: impl FnOnce(&mut [Rgb565Pixel], usize)) -> Result<(), Infallible> {
+        Ok(())
+    }
+}
+
+pub fn init_display(config: EspPlatformConfig) -> Result<DisplayHandle, InitError> {
+    if config.fps_overlay {
+        log::warn!("The simulator doesn't show the FPS overlay");
+    }
+    #[cfg(feature = "gamma")]
+    if config.gamma_correction.is_some() {
+        log::warn!("The simulator doesn't correct the gamma");
+    }
+    *super::tasks::PRIORITIES.lock().unwrap() = config.task_priorities;
+    Ok(DisplayHandle)
+}
+
+/// Selects the winit backend. Must be called once, before any Slint component is created.
+/// `display` is given back when this fails, like on the board.
+pub fn init_touch(display: DisplayHandle) -> Result<(), (InitError, DisplayHandle)> {
+    slint::BackendSelector::new()
+        .backend_name("winit".into())
+        .select()
+        .map_err(|err| (InitError::BackendInitFailed(err), display))
+}
+
+/// Runs `future` on the current thread, which sleeps whenever the future is pending
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    struct ThreadWaker(std::thread::Thread);
+
+    impl Wake for ThreadWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    let waker = Waker::from(Arc::new(ThreadWaker(std::thread::current())));
+    let mut context = Context::from_waker(&waker);
+    let mut future = core::pin::pin!(future);
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut context) {
+            return output;
+        }
+        std::thread::park();
+    }
+}
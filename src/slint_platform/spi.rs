@@ -0,0 +1,104 @@
+//! The SPI interface of the smaller displays, e.g. 240x320 ST7789 modules, as an alternative to
+//! the RGB interface of the board. Unlike the RGB panels, which keep drawing their frame buffer,
+//! an SPI panel keeps the last frame that was sent in its own memory, so Slint renders into two
+//! frame buffers of [`super::frame_buffer::FrameBufferPair::allocate`].
+//!
+//! Only the ST7789 driver is part of ESP-IDF. The ILI9341 is driven by the `esp_lcd_ili9341`
+//! component of the ESP component registry, which isn't built with the firmware.
+
+use esp_idf_svc::hal::sys::*;
+
+use super::frame_buffer;
+
+/// The SPI bus, the GPIOs and the size of the display
+#[derive(Clone, Copy, Debug)]
+pub struct SpiConfig {
+    /// `spi_host_device_t_SPI2_HOST` or `spi_host_device_t_SPI3_HOST`, which isn't used by
+    /// anything else
+    pub host: spi_host_device_t,
+    pub sclk_gpio_num: i32,
+    pub mosi_gpio_num: i32,
+    pub cs_gpio_num: i32,
+    /// Tells the commands from their parameters and the pixels
+    pub dc_gpio_num: i32,
+    /// -1 if the reset of the panel isn't wired, it is then reset by a command
+    pub reset_gpio_num: i32,
+    /// The clock of the bus, at most 80 MHz, often 40 MHz with long wires
+    pub pclk_hz: u32,
+    pub width: usize,
+    pub height: usize,
+    /// Many ST7789 modules show the inverted colors otherwise
+    pub invert_colors: bool,
+}
+
+/// The lines sent per DMA transfer, the driver splits the frames into as many transfers as
+/// needed
+const LINES_PER_TRANSFER: usize = 40;
+
+/// The RAMCTRL command of the ST7789, whose second parameter sets the byte order of the pixels
+const ST7789_RAMCTRL: i32 = 0xb0;
+
+/// Initializes the SPI bus and the panel on it
+pub(super) fn new_panel(config: &SpiConfig) -> Result<esp_lcd_panel_handle_t, EspError> {
+    let bus_config = spi_bus_config_t {
+        __bindgen_anon_1: spi_bus_config_t__bindgen_ty_1 {
+            mosi_io_num: config.mosi_gpio_num,
+        },
+        __bindgen_anon_2: spi_bus_config_t__bindgen_ty_2 { miso_io_num: -1 },
+        sclk_io_num: config.sclk_gpio_num,
+        __bindgen_anon_3: spi_bus_config_t__bindgen_ty_3 { quadwp_io_num: -1 },
+        __bindgen_anon_4: spi_bus_config_t__bindgen_ty_4 { quadhd_io_num: -1 },
+        max_transfer_sz: (config.width * LINES_PER_TRANSFER * 2) as i32,
+        ..Default::default()
+    };
+    let io_config = esp_lcd_panel_io_spi_config_t {
+        cs_gpio_num: config.cs_gpio_num,
+        dc_gpio_num: config.dc_gpio_num,
+        pclk_hz: config.pclk_hz,
+        trans_queue_depth: 10,
+        on_color_trans_done: Some(frame_buffer::spi_sync_callback as _),
+        lcd_cmd_bits: 8,
+        lcd_param_bits: 8,
+        ..Default::default()
+    };
+    let panel_config = esp_lcd_panel_dev_config_t {
+        reset_gpio_num: config.reset_gpio_num,
+        bits_per_pixel: 16,
+        ..Default::default()
+    };
+    let mut io_handle: esp_lcd_panel_io_handle_t = core::ptr::null_mut();
+    let mut panel_handle: esp_lcd_panel_handle_t = core::ptr::null_mut();
+    unsafe {
+        esp!(spi_bus_initialize(
+            config.host,
+            &bus_config,
+            spi_common_dma_t_SPI_DMA_CH_AUTO as _
+        ))?;
+        esp!(esp_lcd_new_panel_io_spi(
+            config.host as _,
+            &io_config,
+            &mut io_handle
+        ))?;
+        esp!(esp_lcd_new_panel_st7789(
+            io_handle,
+            &panel_config,
+            &mut panel_handle
+        ))?;
+        esp!(esp_lcd_panel_reset(panel_handle))?;
+        esp!(esp_lcd_panel_init(panel_handle))?;
+        // Little endian pixels, as Slint renders them, instead of swapping their bytes before
+        // sending them
+        let ram_control = [0x00u8, 0xf8];
+        esp!(esp_lcd_panel_io_tx_param(
+            io_handle,
+            ST7789_RAMCTRL,
+            ram_control.as_ptr().cast(),
+            ram_control.len()
+        ))?;
+        esp!(esp_lcd_panel_invert_color(
+            panel_handle,
+            config.invert_colors
+        ))?;
+    }
+    Ok(panel_handle)
+}
@@ -0,0 +1,71 @@
+//! The scrolling stripes of the `AnimatedBackground` component, see `ui/animated_background.slint`,
+//! drawn into the frame buffer after Slint rendered a frame.
+
+use core::cell::RefCell;
+
+use slint::platform::software_renderer::{PhysicalRegion, Rgb565Pixel};
+
+/// How to draw the stripes, in physical pixels
+#[derive(Clone, Copy, Debug)]
+pub struct StripePattern {
+    /// Added to the phase of the stripes at each frame
+    pub pixels_per_frame: f32,
+    /// Of a stripe and of the gap after it, measured horizontally
+    pub stripe_width: usize,
+    pub stripe_color: Rgb565Pixel,
+    pub gap_color: Rgb565Pixel,
+    /// Filled in by Slint where the stripes are visible, and nowhere else
+    pub key_color: Rgb565Pixel,
+}
+
+struct Stripes {
+    pattern: StripePattern,
+    /// In pixels, less than two stripe widths
+    phase: f32,
+}
+
+thread_local! {
+    // Only accessed from the event loop thread
+    static STRIPES: RefCell<Option<Stripes>> = const { RefCell::new(None) };
+}
+
+/// Draws `pattern` from the next frame on, or nothing if it is `None`. The phase of the stripes
+/// goes on from the previous pattern.
+pub fn set_stripe_pattern(pattern: Option<StripePattern>) {
+    STRIPES.with(|stripes| {
+        let mut stripes = stripes.borrow_mut();
+        let phase = stripes.as_ref().map_or(0., |stripes| stripes.phase);
+        *stripes = pattern.map(|pattern| Stripes { pattern, phase });
+    });
+}
+
+/// Replaces the pixels of the key color with the stripes, in the region that was just rendered:
+/// the pixels that weren't rendered again already show the stripes of a previous frame. Then
+/// advances the phase for the next frame.
+pub fn draw(buffer: &mut [Rgb565Pixel], stride: usize, rendered: &PhysicalRegion) {
+    STRIPES.with(|stripes| {
+        let mut stripes = stripes.borrow_mut();
+        let Some(Stripes { pattern, phase }) = stripes.as_mut() else {
+            return;
+        };
+        let period = pattern.stripe_width.max(1) * 2;
+        let offset = *phase as usize;
+        for (origin, size) in rendered.iter() {
+            let (left, top) = (origin.x as usize, origin.y as usize);
+            for y in top..top + size.height as usize {
+                for x in left..left + size.width as usize {
+                    let pixel = &mut buffer[y * stride + x];
+                    if *pixel == pattern.key_color {
+                        // Going down and to the left along a stripe
+                        *pixel = if (x + y + offset) % period < period / 2 {
+                            pattern.stripe_color
+                        } else {
+                            pattern.gap_color
+                        };
+                    }
+                }
+            }
+        }
+        *phase = (*phase + pattern.pixels_per_frame).rem_euclid(period as f32);
+    });
+}
@@ -0,0 +1,98 @@
+//! Futures that run on their own FreeRTOS task instead of the event loop, at the priorities of
+//! [`TaskPriorities`]. They don't have access to the Slint components, the properties must be
+//! set with [`slint::invoke_from_event_loop`].
+
+use core::future::Future;
+use std::sync::Mutex;
+
+#[cfg(not(feature = "sim"))]
+use esp_idf_svc::hal::task::thread::ThreadSpawnConfiguration;
+
+/// The FreeRTOS priorities of the tasks spawned by [`spawn_high_priority`] and
+/// [`spawn_low_priority`]. The event loop runs in the main task, at priority 1.
+#[derive(Clone, Copy, Debug)]
+pub struct TaskPriorities {
+    /// For the real-time sensor reads, which mustn't wait for a frame to be rendered
+    pub high: u8,
+    /// For the network requests, which can wait. FreeRTOS keeps priority 0 for its idle tasks,
+    /// so this is the priority of the event loop, which they share the processor with.
+    pub low: u8,
+}
+
+impl TaskPriorities {
+    const DEFAULT: Self = Self { high: 5, low: 1 };
+}
+
+impl Default for TaskPriorities {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// Set by [`super::init_display`]
+pub(super) static PRIORITIES: Mutex<TaskPriorities> = Mutex::new(TaskPriorities::DEFAULT);
+
+const STACK_SIZE: usize = 8 * 1024;
+
+/// The priorities that `ThreadSpawnConfiguration` accepts, it panics on the others
+#[cfg(not(feature = "sim"))]
+const VALID_PRIORITIES: core::ops::RangeInclusive<u8> = 1..=24;
+
+/// Runs `future` on a new task with the high priority of [`TaskPriorities`]
+pub fn spawn_high_priority(
+    future: impl Future<Output = ()> + Send + 'static,
+) -> std::io::Result<()> {
+    let priority = PRIORITIES.lock().unwrap().high;
+    spawn(b"high-priority\0", priority, future)
+}
+
+/// Runs `future` on a new task with the low priority of [`TaskPriorities`]
+pub fn spawn_low_priority(
+    future: impl Future<Output = ()> + Send + 'static,
+) -> std::io::Result<()> {
+    let priority = PRIORITIES.lock().unwrap().low;
+    spawn(b"low-priority\0", priority, future)
+}
+
+#[cfg(not(feature = "sim"))]
+fn spawn(
+    name: &'static [u8],
+    priority: u8,
+    future: impl Future<Output = ()> + Send + 'static,
+) -> std::io::Result<()> {
+    if !VALID_PRIORITIES.contains(&priority) {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("The priority of a task must be in {VALID_PRIORITIES:?}, not {priority}"),
+        ));
+    }
+    // Only applies to the threads spawned by the current thread
+    ThreadSpawnConfiguration {
+        name: Some(name),
+        stack_size: STACK_SIZE,
+        priority,
+        ..Default::default()
+    }
+    .set()
+    .map_err(std::io::Error::other)?;
+    let spawned = std::thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(move || esp_idf_svc::hal::task::block_on(future));
+    ThreadSpawnConfiguration::default()
+        .set()
+        .map_err(std::io::Error::other)?;
+    spawned.map(drop)
+}
+
+/// The threads of the desktop have no priority
+#[cfg(feature = "sim")]
+fn spawn(
+    _name: &'static [u8],
+    _priority: u8,
+    future: impl Future<Output = ()> + Send + 'static,
+) -> std::io::Result<()> {
+    std::thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(move || super::sim::block_on(future))
+        .map(drop)
+}
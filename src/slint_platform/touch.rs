@@ -0,0 +1,395 @@
+//! The touch controllers that the display may come with, found by their I2C address and their ID
+//! register, see
+//! [`detect_touch_controller`]. The board has a GT911, other revisions and other displays of
+//! the same size have one of the others.
+
+use esp_idf_svc::hal::delay::TickType;
+use esp_idf_svc::sys::{EspError, ESP_ERR_TIMEOUT};
+
+use super::I2C;
+
+/// How long a controller has to answer the probe or a read
+const I2C_TIMEOUT_MS: u64 = 10;
+
+/// The number of contacts of the GT911, the other controllers have fewer
+pub const MAX_SLOTS: usize = 5;
+
+/// The touch points, in physical pixels, by the slot that the controller assigned to their
+/// contact. A contact keeps its slot until it is lifted.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TouchPoints {
+    pub slots: [Option<slint::PhysicalPosition>; MAX_SLOTS],
+}
+
+/// What a reading did to the pointer of the window, see [`TouchSlots`]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PointerChange {
+    Pressed(slint::LogicalPosition),
+    Moved(slint::LogicalPosition),
+    Released(slint::LogicalPosition),
+    /// The pointer isn't pressed
+    None,
+}
+
+/// Follows the contacts by their slot from a reading to the next. `WindowEvent` has no pointer
+/// ID, so the window has a single pointer: the first contact, until it is lifted, even while
+/// other ones come and go. The contacts that are still down then don't press the pointer again,
+/// only a new one does.
+#[derive(Default)]
+pub struct TouchSlots {
+    slots: [Option<slint::LogicalPosition>; MAX_SLOTS],
+    /// The slot of the contact that presses the pointer
+    pointer_slot: Option<usize>,
+}
+
+impl TouchSlots {
+    /// Takes the contacts of a reading, in logical pixels
+    pub fn update(&mut self, slots: [Option<slint::LogicalPosition>; MAX_SLOTS]) -> PointerChange {
+        let previous = core::mem::replace(&mut self.slots, slots);
+        if let Some(slot) = self.pointer_slot {
+            return match (slots[slot], previous[slot]) {
+                (Some(position), _) => PointerChange::Moved(position),
+                (None, position) => {
+                    self.pointer_slot = None;
+                    PointerChange::Released(position.unwrap_or_default())
+                }
+            };
+        }
+        // A slot that was free in the last reading
+        match (0..MAX_SLOTS).find(|&slot| slots[slot].is_some() && previous[slot].is_none()) {
+            Some(slot) => {
+                self.pointer_slot = Some(slot);
+                PointerChange::Pressed(slots[slot].unwrap())
+            }
+            None => PointerChange::None,
+        }
+    }
+
+    /// Whether a contact presses the pointer
+    // Only needed to decide whether the CPU may sleep
+    #[cfg_attr(not(feature = "light-sleep"), allow(dead_code))]
+    pub fn pressed(&self) -> bool {
+        self.pointer_slot.is_some()
+    }
+
+    /// The contact that presses the pointer
+    pub fn pointer(&self) -> Option<slint::LogicalPosition> {
+        self.slots[self.pointer_slot?]
+    }
+
+    /// The first contact that doesn't press the pointer, the second finger of a pinch
+    pub fn second(&self) -> Option<slint::LogicalPosition> {
+        let pointer_slot = self.pointer_slot?;
+        (0..MAX_SLOTS)
+            .filter(|&slot| slot != pointer_slot)
+            .find_map(|slot| self.slots[slot])
+    }
+}
+
+/// Why a touch controller can't be initialized or read
+#[derive(Debug)]
+pub enum TouchError {
+    Gt911(gt911::Error<esp_idf_svc::hal::i2c::I2cError>),
+    I2c(EspError),
+}
+
+impl TouchError {
+    /// Whether the I2C transfer timed out, e.g. when a device holds SDA low
+    pub fn is_bus_timeout(&self) -> bool {
+        let err = match self {
+            TouchError::Gt911(gt911::Error::I2C(err)) => err.cause(),
+            TouchError::I2c(err) => *err,
+            _ => return false,
+        };
+        err.code() == ESP_ERR_TIMEOUT
+    }
+}
+
+impl core::fmt::Display for TouchError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            TouchError::Gt911(err) => write!(f, "GT911 error: {err:?}"),
+            TouchError::I2c(err) => write!(f, "I2C error: {err}"),
+        }
+    }
+}
+
+/// Reads the touch points from a controller on the I2C bus
+pub trait TouchController {
+    /// Returns `None` if there is no new data since the last read, and no points once the
+    /// screen is released
+    fn read(&mut self, i2c: &mut I2C) -> Result<Option<TouchPoints>, TouchError>;
+}
+
+/// The touch controllers that [`detect_touch_controller`] knows
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TouchControllerKind {
+    Gt911,
+    Ft5x06,
+    Cst816s,
+}
+
+impl TouchControllerKind {
+    const ALL: [TouchControllerKind; 3] = [
+        TouchControllerKind::Gt911,
+        TouchControllerKind::Ft5x06,
+        TouchControllerKind::Cst816s,
+    ];
+
+    /// Its default I2C address
+    pub fn address(self) -> u8 {
+        self.addresses()[0]
+    }
+
+    /// The I2C addresses that it may be strapped to, the default one first
+    fn addresses(self) -> &'static [u8] {
+        match self {
+            TouchControllerKind::Gt911 => &[0x5d, 0x14],
+            TouchControllerKind::Ft5x06 => &[0x38],
+            TouchControllerKind::Cst816s => &[0x15],
+        }
+    }
+
+    /// Whether the device at `address` is this controller, from its ID register. An ACK isn't
+    /// enough: the IO expander of the board also answers at 0x38, the address of the FT5x06.
+    fn identify(self, i2c: &mut I2C, address: u8) -> bool {
+        match self {
+            TouchControllerKind::Gt911 => {
+                let mut product_id = [0; 4];
+                read_registers(i2c, address, &GT911_PRODUCT_ID_REGISTER, &mut product_id).is_ok()
+                    && product_id.starts_with(b"911")
+            }
+            TouchControllerKind::Ft5x06 => {
+                let (mut chip_id, mut vendor_id) = ([0], [0]);
+                read_registers(i2c, address, &[0xa3], &mut chip_id).is_ok()
+                    && read_registers(i2c, address, &[0xa8], &mut vendor_id).is_ok()
+                    && (FOCALTECH_CHIP_IDS.contains(&chip_id[0])
+                        || vendor_id[0] == FOCALTECH_VENDOR_ID)
+            }
+            TouchControllerKind::Cst816s => {
+                let mut chip_id = [0];
+                read_registers(i2c, address, &[0xa7], &mut chip_id).is_ok()
+                    && CST816_CHIP_IDS.contains(&chip_id[0])
+            }
+        }
+    }
+
+    /// The first of its addresses that it is identified at
+    fn find(self, i2c: &mut I2C) -> Option<u8> {
+        self.addresses()
+            .iter()
+            .copied()
+            .find(|&address| self.identify(i2c, address))
+    }
+
+    /// Initializes the controller, which must be on the bus. The GT911 is looked up at its other
+    /// address if it isn't at the default one.
+    pub fn init(self, i2c: &mut I2C) -> Result<Box<dyn TouchController>, TouchError> {
+        Ok(match self {
+            TouchControllerKind::Gt911 => {
+                let address = self.find(i2c).unwrap_or(self.address());
+                let gt911 = gt911::Gt911Blocking::new(address);
+                gt911.init(i2c).map_err(TouchError::Gt911)?;
+                Box::new(Gt911 {
+                    address,
+                    track_ids: Default::default(),
+                })
+            }
+            TouchControllerKind::Ft5x06 => Box::new(Ft5x06),
+            TouchControllerKind::Cst816s => Box::new(Cst816s),
+        })
+    }
+}
+
+/// The product ID of the GT911, "911" in ASCII and a null or a revision letter
+const GT911_PRODUCT_ID_REGISTER: [u8; 2] = 0x8140u16.to_be_bytes();
+/// The chip IDs of the FT5x06, FT5606, FT5x16, FT6206, FT6236 and FT6336U
+const FOCALTECH_CHIP_IDS: [u8; 6] = [0x55, 0x08, 0x0a, 0x06, 0x36, 0x64];
+/// Of FocalTech, which not all the chips report
+const FOCALTECH_VENDOR_ID: u8 = 0x11;
+/// The chip IDs of the CST716, CST816S, CST816T and CST816D
+const CST816_CHIP_IDS: [u8; 4] = [0x20, 0xb4, 0xb5, 0xb6];
+
+/// Probes the addresses of the known controllers, in the order of [`TouchControllerKind`], and
+/// returns the first one whose ID register is that of the controller. The CST816S sleeps until
+/// it is touched, so it may not be found then.
+///
+/// The probe of the FT5x06 writes the address of its ID register to 0x38, which the IO expander
+/// of the board takes for the level of its outputs: they must be set again if no FT5x06 was found,
+/// see `init_touch`.
+pub fn detect_touch_controller(i2c: &mut I2C) -> Option<TouchControllerKind> {
+    TouchControllerKind::ALL
+        .into_iter()
+        .find(|kind| kind.find(i2c).is_some())
+}
+
+fn timeout() -> u32 {
+    TickType::new_millis(I2C_TIMEOUT_MS).ticks()
+}
+
+/// Reads the registers of the controller at `address` from `register` on, which the controllers
+/// below increment. The address of the register, big-endian, is written and the registers are
+/// read in a single transaction, with a repeated start in between.
+fn read_registers(
+    i2c: &mut I2C,
+    address: u8,
+    register: &[u8],
+    buf: &mut [u8],
+) -> Result<(), TouchError> {
+    i2c.write_read(address, register, buf, timeout())
+        .map_err(TouchError::I2c)
+}
+
+/// The 12 bits of a coordinate, after the flags in the high nibble of the first byte
+fn coordinate(high: u8, low: u8) -> i32 {
+    ((high as i32 & 0x0f) << 8) | low as i32
+}
+
+/// The status of the GT911: whether there is a new reading, and its number of points. The
+/// points follow it, [`GT911_POINT_LEN`] registers each.
+const GT911_STATUS_REGISTER: u16 = 0x814e;
+/// The track ID, the X and Y coordinates and the size of the point, little-endian, and a
+/// reserved register
+const GT911_POINT_LEN: usize = 8;
+
+/// The GT911, which the crate only initializes: its `get_multi_touch` reads the status and the
+/// points in separate transactions, while this reads the first point with the status, which is
+/// all there is to read for a single finger.
+struct Gt911 {
+    /// One of [`TouchControllerKind::addresses`]
+    address: u8,
+    /// The track ID of the contact in each slot. The controller numbers the contacts with IDs
+    /// that may be above the number of slots, so they are given the first free slot, unless the
+    /// slot of their ID is free.
+    track_ids: [Option<u8>; MAX_SLOTS],
+}
+
+impl Gt911 {
+    /// The slot of the contact with `track_id`, which keeps it from the last reading, or `None`
+    /// if there is no free slot left
+    fn slot(&mut self, track_id: u8) -> Option<usize> {
+        if let Some(slot) = self.track_ids.iter().position(|&id| id == Some(track_id)) {
+            return Some(slot);
+        }
+        let slot = Some(track_id as usize)
+            .filter(|&slot| slot < MAX_SLOTS && self.track_ids[slot].is_none())
+            .or_else(|| self.track_ids.iter().position(Option::is_none))?;
+        self.track_ids[slot] = Some(track_id);
+        Some(slot)
+    }
+}
+
+impl TouchController for Gt911 {
+    fn read(&mut self, i2c: &mut I2C) -> Result<Option<TouchPoints>, TouchError> {
+        let mut registers = [0; 1 + MAX_SLOTS * GT911_POINT_LEN];
+        let (status, points) = registers.split_at_mut(1 + GT911_POINT_LEN);
+        read_registers(
+            i2c,
+            self.address,
+            &GT911_STATUS_REGISTER.to_be_bytes(),
+            status,
+        )?;
+        // Not ready yet, the reading is the same as the last one
+        if status[0] & 0x80 == 0 {
+            return Ok(None);
+        }
+        let count = ((status[0] & 0x0f) as usize).min(MAX_SLOTS);
+        if count > 1 {
+            let register = GT911_STATUS_REGISTER + 1 + GT911_POINT_LEN as u16;
+            read_registers(
+                i2c,
+                self.address,
+                &register.to_be_bytes(),
+                &mut points[..(count - 1) * GT911_POINT_LEN],
+            )?;
+        }
+        // Lets the controller write the next reading
+        let [high, low] = GT911_STATUS_REGISTER.to_be_bytes();
+        i2c.write(self.address, &[high, low, 0], timeout())
+            .map_err(TouchError::I2c)?;
+
+        let points = registers[1..].chunks_exact(GT911_POINT_LEN).take(count);
+        // The slots of the lifted contacts are free again
+        for id in &mut self.track_ids {
+            if id.is_some_and(|id| !points.clone().any(|point| point[0] == id)) {
+                *id = None;
+            }
+        }
+        let mut touch_points = TouchPoints::default();
+        for point in points {
+            let Some(slot) = self.slot(point[0]) else {
+                log::warn!(
+                    "No free slot for the contact with the track ID {}",
+                    point[0]
+                );
+                continue;
+            };
+            touch_points.slots[slot] = Some(slint::PhysicalPosition::new(
+                u16::from_le_bytes([point[1], point[2]]).into(),
+                u16::from_le_bytes([point[3], point[4]]).into(),
+            ));
+        }
+        Ok(Some(touch_points))
+    }
+}
+
+/// The FT5x06 family of FocalTech, also the FT6x36, with up to two points
+struct Ft5x06;
+
+impl TouchController for Ft5x06 {
+    fn read(&mut self, i2c: &mut I2C) -> Result<Option<TouchPoints>, TouchError> {
+        // The number of points, then 6 registers per point
+        let mut registers = [0; 13];
+        read_registers(
+            i2c,
+            TouchControllerKind::Ft5x06.address(),
+            &[0x02],
+            &mut registers,
+        )?;
+        let count = (registers[0] & 0x0f) as usize;
+        let mut touch_points = TouchPoints::default();
+        for point in registers[1..].chunks(6).take(count) {
+            // The touch ID is in the high nibble of the Y coordinate
+            if let Some(slot) = touch_points.slots.get_mut((point[2] >> 4) as usize) {
+                *slot = Some(slint::PhysicalPosition::new(
+                    coordinate(point[0], point[1]),
+                    coordinate(point[2], point[3]),
+                ));
+            }
+        }
+        Ok(Some(touch_points))
+    }
+}
+
+/// The CST816S of Hynitron, with one point
+struct Cst816s;
+
+impl TouchController for Cst816s {
+    fn read(&mut self, i2c: &mut I2C) -> Result<Option<TouchPoints>, TouchError> {
+        // The number of points, then the coordinates of the point
+        let mut registers = [0; 5];
+        read_registers(
+            i2c,
+            TouchControllerKind::Cst816s.address(),
+            &[0x02],
+            &mut registers,
+        )?;
+        let mut touch_points = TouchPoints::default();
+        touch_points.slots[0] = (registers[0] > 0).then(|| {
+            slint::PhysicalPosition::new(
+                coordinate(registers[1], registers[2]),
+                coordinate(registers[3], registers[4]),
+            )
+        });
+        Ok(Some(touch_points))
+    }
+}
+
+/// Stands for a touch controller that wasn't found: the screen is never touched
+pub struct NoTouchController;
+
+impl TouchController for NoTouchController {
+    fn read(&mut self, _i2c: &mut I2C) -> Result<Option<TouchPoints>, TouchError> {
+        Ok(None)
+    }
+}
@@ -0,0 +1,61 @@
+//! Trace events of the event loop, recorded by the `esp_diagnostics` component and shown in the
+//! ESP Insights dashboard, see the README. They are formatted like the `ESP_DIAG_EVENT` macro of
+//! the component does, which can't be called from Rust.
+
+// Otherwise the firmware only fails to link, with `esp_diag_log_event` missing
+#[cfg(not(esp_idf_comp_espressif__esp_diagnostics_enabled))]
+compile_error!(
+    "The `diagnostics-trace` feature needs the `espressif/esp_diagnostics` component, add it to \
+     `Cargo.toml` as the README shows"
+);
+
+use core::ffi::{c_char, CStr};
+
+use esp_idf_svc::sys::{esp_err_t, esp_log_timestamp};
+
+extern "C" {
+    fn esp_diag_log_event(tag: *const c_char, format: *const c_char, ...) -> esp_err_t;
+}
+
+/// Before the frame `frame` is rendered
+pub fn render_started(frame: u32) {
+    render(c"start", frame);
+}
+
+/// After the frame `frame` is sent to the display
+pub fn render_finished(frame: u32) {
+    render(c"end", frame);
+}
+
+fn render(phase: &CStr, frame: u32) {
+    unsafe {
+        esp_diag_log_event(
+            c"render".as_ptr(),
+            c"EV (%lu) %s: %s %lu".as_ptr(),
+            esp_log_timestamp(),
+            c"render".as_ptr(),
+            phase.as_ptr(),
+            frame,
+        );
+    }
+}
+
+/// When a touch event is dispatched to the window
+pub fn touch(event: &slint::platform::WindowEvent) {
+    let event_type = match event {
+        slint::platform::WindowEvent::PointerPressed { .. } => c"pressed",
+        slint::platform::WindowEvent::PointerMoved { .. } => c"moved",
+        slint::platform::WindowEvent::PointerReleased { .. } => c"released",
+        slint::platform::WindowEvent::PointerExited => c"exited",
+        _ => c"other",
+    };
+    unsafe {
+        esp_diag_log_event(
+            c"touch".as_ptr(),
+            c"EV (%lu) %s: %s".as_ptr(),
+            esp_log_timestamp(),
+            c"touch".as_ptr(),
+            event_type.as_ptr(),
+        );
+    }
+}
@@ -0,0 +1,27 @@
+//! Keeps the ratio of the split page across restarts, see `ui/split_view.slint`.
+
+use std::cell::RefCell;
+
+use esp_idf_svc::nvs::EspDefaultNvsPartition;
+use esp_idf_svc::sys::EspError;
+
+use crate::persistent_property::PersistentProperty;
+use crate::MainWindow;
+
+const NVS_NAMESPACE: &str = "split_view";
+const NVS_RATIO_KEY: &str = "ratio";
+
+/// Restores the stored ratio, and stores it again each time it is changed
+pub fn init(window: &MainWindow, nvs_partition: EspDefaultNvsPartition) -> Result<(), EspError> {
+    let ratio = PersistentProperty::<f32>::new(nvs_partition, NVS_NAMESPACE, NVS_RATIO_KEY)?;
+    if let Some(stored) = ratio.get()? {
+        window.set_split_ratio(stored);
+    }
+    let ratio = RefCell::new(ratio);
+    window.on_split_ratio_changed(move |changed| {
+        if let Err(err) = ratio.borrow_mut().set(changed) {
+            log::warn!("Cannot store the split ratio: {err}");
+        }
+    });
+    Ok(())
+}
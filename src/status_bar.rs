@@ -0,0 +1,47 @@
+//! Sets the time of the `Clock` global, shown by the status bar, see `ui/status_bar.slint`.
+//!
+//! The time is in UTC. The board has no RTC and doesn't synchronize its clock with SNTP, so it
+//! starts at 00:00 on each boot. It has no battery gauge either, so the `Battery` global keeps
+//! its unknown level.
+
+use std::time::{Duration, SystemTime};
+
+use slint::ComponentHandle;
+
+use crate::{Clock, MainWindow};
+
+/// The time only changes once a minute, but it is shown less than a second late
+const UPDATE_INTERVAL: Duration = Duration::from_secs(1);
+
+thread_local! {
+    // Only accessed from the event loop thread
+    static TIMER: slint::Timer = Default::default();
+}
+
+/// Must be called once; the time is kept up to date from then on
+pub fn init(window: &MainWindow) {
+    let window = window.as_weak();
+    let update = move || {
+        let Some(window) = window.upgrade() else {
+            return;
+        };
+        let clock = window.global::<Clock>();
+        let time = time_str(SystemTime::now());
+        // Redraws the status bar once a minute only
+        if clock.get_time_str() != time.as_str() {
+            clock.set_time_str(time.into());
+        }
+    };
+    update();
+    TIMER.with(|timer| timer.start(slint::TimerMode::Repeated, UPDATE_INTERVAL, update));
+}
+
+/// As hours and minutes, such as `09:41`
+fn time_str(now: SystemTime) -> String {
+    let minutes = now
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+        / 60;
+    format!("{:02}:{:02}", minutes / 60 % 24, minutes % 60)
+}
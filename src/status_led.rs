@@ -0,0 +1,192 @@
+//! The status LED of `ui/status_led.slint`, an RGB LED wired to the board. It is a WS2812, driven
+//! by the RMT peripheral, or with the `status-led-rgb` feature, an LED with a common cathode and
+//! a pin for each color, dimmed by three LEDC channels. The `status_led_rgb` cfg of `build.rs`
+//! stands for that feature without the `microphone` one, which takes the pins of the colors.
+//!
+//! The blinking is timed by an `EspTimer`, so that it goes on while the event loop is busy, and
+//! the LED is shared with the timer task.
+
+use std::sync::Mutex;
+use std::time::Duration;
+
+use esp_idf_svc::hal::gpio::OutputPin;
+#[cfg(status_led_rgb)]
+use esp_idf_svc::hal::ledc::{
+    config::TimerConfig, LedcChannel, LedcDriver, LedcTimer, LedcTimerDriver,
+};
+use esp_idf_svc::hal::peripheral::Peripheral;
+#[cfg(not(status_led_rgb))]
+use esp_idf_svc::hal::rmt::{
+    config::TransmitConfig, FixedLengthSignal, PinState, Pulse, RmtChannel, TxRmtDriver,
+};
+use esp_idf_svc::sys::EspError;
+use esp_idf_svc::timer::{EspTaskTimerService, EspTimer};
+use slint::ComponentHandle;
+
+use crate::{MainWindow, StatusLed};
+
+/// Of `blink-error`
+const ERROR_COLOR: (u8, u8, u8) = (255, 0, 0);
+const ERROR_INTERVAL_MS: u32 = 250;
+
+pub struct Led {
+    #[cfg(not(status_led_rgb))]
+    tx: TxRmtDriver<'static>,
+    /// Red, green and blue
+    #[cfg(status_led_rgb)]
+    channels: [LedcDriver<'static>; 3],
+}
+
+impl Led {
+    #[cfg(not(status_led_rgb))]
+    pub fn ws2812<C: RmtChannel>(
+        channel: impl Peripheral<P = C> + 'static,
+        pin: impl Peripheral<P = impl OutputPin> + 'static,
+    ) -> Result<Self, EspError> {
+        // Ticks of 12.5 ns, for the timing of the bits
+        let config = TransmitConfig::new().clock_divider(1);
+        Ok(Self {
+            tx: TxRmtDriver::new(channel, pin, &config)?,
+        })
+    }
+
+    #[cfg(status_led_rgb)]
+    pub fn rgb<T: LedcTimer + 'static>(
+        timer: impl Peripheral<P = T> + 'static,
+        red: (
+            impl Peripheral<P = impl LedcChannel<SpeedMode = T::SpeedMode>> + 'static,
+            impl Peripheral<P = impl OutputPin> + 'static,
+        ),
+        green: (
+            impl Peripheral<P = impl LedcChannel<SpeedMode = T::SpeedMode>> + 'static,
+            impl Peripheral<P = impl OutputPin> + 'static,
+        ),
+        blue: (
+            impl Peripheral<P = impl LedcChannel<SpeedMode = T::SpeedMode>> + 'static,
+            impl Peripheral<P = impl OutputPin> + 'static,
+        ),
+    ) -> Result<Self, EspError> {
+        // The channels stop when the driver of their timer is dropped, and the LED is never
+        // released
+        let timer = &*Box::leak(Box::new(LedcTimerDriver::new(
+            timer,
+            &TimerConfig::default(),
+        )?));
+        Ok(Self {
+            channels: [
+                LedcDriver::new(red.0, timer, red.1)?,
+                LedcDriver::new(green.0, timer, green.1)?,
+                LedcDriver::new(blue.0, timer, blue.1)?,
+            ],
+        })
+    }
+
+    #[cfg(not(status_led_rgb))]
+    fn show(&mut self, (red, green, blue): (u8, u8, u8)) -> Result<(), EspError> {
+        let ticks_hz = self.tx.counter_clock()?;
+        let pulse =
+            |state, nanos| Pulse::new_with_duration(ticks_hz, state, &Duration::from_nanos(nanos));
+        let zero = (pulse(PinState::High, 350)?, pulse(PinState::Low, 800)?);
+        let one = (pulse(PinState::High, 700)?, pulse(PinState::Low, 600)?);
+        // Green first, the most significant bit first
+        let bits = u32::from_be_bytes([0, green, red, blue]);
+        let mut signal = FixedLengthSignal::<24>::new();
+        for index in 0..24 {
+            let bit = (bits >> (23 - index)) & 1 != 0;
+            signal.set(index, if bit { &one } else { &zero })?;
+        }
+        self.tx.start_blocking(&signal)
+    }
+
+    #[cfg(status_led_rgb)]
+    fn show(&mut self, (red, green, blue): (u8, u8, u8)) -> Result<(), EspError> {
+        for (channel, value) in self.channels.iter_mut().zip([red, green, blue]) {
+            let duty = channel.get_max_duty() * u32::from(value) / 255;
+            channel.set_duty(duty)?;
+        }
+        Ok(())
+    }
+}
+
+struct State {
+    led: Led,
+    blink_timer: EspTimer<'static>,
+    /// The color that is shown, or that alternates with black while blinking
+    color: (u8, u8, u8),
+    lit: bool,
+}
+
+/// Set by [`init`], and changed by the timer task while blinking
+static STATE: Mutex<Option<State>> = Mutex::new(None);
+
+/// Switches the LED off, until the UI sets its color
+pub fn init(
+    window: &MainWindow,
+    led: Led,
+    timer_service: &EspTaskTimerService,
+) -> Result<(), EspError> {
+    // Called from the timer task
+    let blink_timer = timer_service.timer(|| {
+        let Ok(mut state) = STATE.lock() else {
+            return;
+        };
+        if let Some(state) = state.as_mut() {
+            state.lit = !state.lit;
+            let color = if state.lit { state.color } else { (0, 0, 0) };
+            if let Err(err) = state.led.show(color) {
+                log::warn!("Cannot blink the status LED: {err}");
+            }
+        }
+    })?;
+    *STATE.lock().unwrap() = Some(State {
+        led,
+        blink_timer,
+        color: (0, 0, 0),
+        lit: false,
+    });
+    set_color(0, 0, 0);
+
+    let status_led = window.global::<StatusLed>();
+    status_led.on_set_color(|red, green, blue| set_color(to_u8(red), to_u8(green), to_u8(blue)));
+    status_led.on_blink_error(|| blink(ERROR_COLOR, ERROR_INTERVAL_MS));
+    Ok(())
+}
+
+/// Stops the blinking
+pub fn set_color(red: u8, green: u8, blue: u8) {
+    update(|state| {
+        state.blink_timer.cancel()?;
+        state.color = (red, green, blue);
+        state.lit = true;
+        state.led.show(state.color)
+    });
+}
+
+/// Alternates `color` with black, each for `interval_ms`, until the color is set
+pub fn blink(color: (u8, u8, u8), interval_ms: u32) {
+    update(|state| {
+        state.color = color;
+        state.lit = true;
+        state.led.show(color)?;
+        state.blink_timer.cancel()?;
+        state
+            .blink_timer
+            .every(Duration::from_millis(interval_ms.into()))
+    });
+}
+
+/// Does nothing before [`init`]
+fn update(change: impl FnOnce(&mut State) -> Result<(), EspError>) {
+    let Ok(mut state) = STATE.lock() else {
+        return;
+    };
+    if let Some(state) = state.as_mut() {
+        if let Err(err) = change(state) {
+            log::warn!("Cannot change the status LED: {err}");
+        }
+    }
+}
+
+fn to_u8(value: i32) -> u8 {
+    value.clamp(0, 255) as u8
+}
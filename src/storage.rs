@@ -0,0 +1,62 @@
+//! The `storage` SPIFFS partition, for the data files that are too big to be embedded in the
+//! firmware. See the README for how to flash it.
+
+use std::path::Path;
+
+#[cfg(not(feature = "sim"))]
+use esp_idf_svc::sys::{esp, esp_vfs_spiffs_conf_t, esp_vfs_spiffs_register, EspError};
+use slint::{Rgb8Pixel, SharedPixelBuffer};
+
+/// Where the files of the partition are found, e.g. `/spiffs/words.z`
+#[cfg(not(feature = "sim"))]
+pub const MOUNT_POINT: &str = "/spiffs";
+/// The simulator reads the directory that the partition is created from, see the README
+#[cfg(feature = "sim")]
+pub const MOUNT_POINT: &str = "storage";
+
+/// Must be called once before any file is read from [`MOUNT_POINT`]
+#[cfg(not(feature = "sim"))]
+pub fn mount() -> Result<(), EspError> {
+    let conf = esp_vfs_spiffs_conf_t {
+        base_path: c"/spiffs".as_ptr(),
+        partition_label: c"storage".as_ptr(),
+        max_files: 4,
+        // The partition is flashed separately, formatting it would only hide that it is missing
+        format_if_mount_failed: false,
+    };
+    esp!(unsafe { esp_vfs_spiffs_register(&conf) })
+}
+
+/// Reads an image of `width`×`height` pixels in the RGB565 format, in little endian, without
+/// header. See the README for how to convert images to this format.
+pub fn read_rgb565_image(path: &Path, width: u32, height: u32) -> std::io::Result<slint::Image> {
+    let data = std::fs::read(path)?;
+    let expected_len = (width * height * 2) as usize;
+    if data.len() != expected_len {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("{} bytes instead of {expected_len}", data.len()),
+        ));
+    }
+    Ok(rgb565_image(&data, width, height))
+}
+
+/// Converts `data` in the format of [`read_rgb565_image`], also used for the images embedded in
+/// the firmware. The missing pixels are black if `data` is too short.
+pub fn rgb565_image(data: &[u8], width: u32, height: u32) -> slint::Image {
+    let mut buffer = SharedPixelBuffer::<Rgb8Pixel>::new(width, height);
+    for (pixel, rgb565) in buffer.make_mut_slice().iter_mut().zip(data.chunks_exact(2)) {
+        *pixel = rgb565_to_rgb8(u16::from_le_bytes([rgb565[0], rgb565[1]]));
+    }
+    slint::Image::from_rgb8(buffer)
+}
+
+pub fn rgb565_to_rgb8(rgb565: u16) -> Rgb8Pixel {
+    let (r, g, b) = (rgb565 >> 11, (rgb565 >> 5) & 0x3f, rgb565 & 0x1f);
+    // Replicating the high bits makes white stay white
+    Rgb8Pixel::new(
+        (r << 3 | r >> 2) as u8,
+        (g << 2 | g >> 4) as u8,
+        (b << 3 | b >> 2) as u8,
+    )
+}
@@ -0,0 +1,122 @@
+//! Keeps the switches of the preferences page across restarts, see `ui/switch_list.slint`. Each
+//! item of `Preferences.items` is stored in its own key of the `preferences` NVS namespace.
+//!
+//! The simulator has no NVS: the switches start from their defaults and aren't stored.
+
+#[cfg(not(feature = "sim"))]
+use std::cell::RefCell;
+use std::rc::Rc;
+
+#[cfg(not(feature = "sim"))]
+use esp_idf_svc::nvs::EspDefaultNvsPartition;
+#[cfg(not(feature = "sim"))]
+use esp_idf_svc::sys::EspError;
+use slint::{ComponentHandle, Model, VecModel};
+
+#[cfg(not(feature = "sim"))]
+use crate::persistent_property::PersistentProperty;
+use crate::{MainWindow, Preferences, SwitchItem};
+
+#[cfg(not(feature = "sim"))]
+const NVS_NAMESPACE: &str = "preferences";
+
+struct Switch {
+    label: &'static str,
+    /// At most 15 characters long
+    #[cfg_attr(feature = "sim", allow(dead_code))]
+    key: &'static str,
+    /// Until the switch is toggled for the first time
+    default: bool,
+}
+
+/// In the order of the page
+const SWITCHES: [Switch; 4] = [
+    Switch {
+        label: "Lock with the PIN on start",
+        key: "pin_on_start",
+        default: false,
+    },
+    Switch {
+        label: "Keep the screen on",
+        key: "screen_on",
+        default: true,
+    },
+    Switch {
+        label: "Sound on touch",
+        key: "touch_sound",
+        default: false,
+    },
+    Switch {
+        label: "Send diagnostics",
+        key: "diagnostics",
+        default: true,
+    },
+];
+
+/// Reads the state of each switch from NVS, and stores it again each time it is toggled
+#[cfg(not(feature = "sim"))]
+pub fn init(window: &MainWindow, nvs_partition: EspDefaultNvsPartition) -> Result<(), EspError> {
+    let mut stored = Vec::with_capacity(SWITCHES.len());
+    let mut enabled = Vec::with_capacity(SWITCHES.len());
+    for switch in &SWITCHES {
+        let property =
+            PersistentProperty::<bool>::new(nvs_partition.clone(), NVS_NAMESPACE, switch.key)?;
+        enabled.push(property.get()?.unwrap_or(switch.default));
+        stored.push(property);
+    }
+    let items = set_items(window, enabled);
+
+    let stored = RefCell::new(stored);
+    window
+        .global::<Preferences>()
+        .on_item_toggled(move |index, enabled| {
+            let index = index as usize;
+            let mut stored = stored.borrow_mut();
+            let Some(property) = stored.get_mut(index) else {
+                return;
+            };
+            if let Err(err) = property.set(enabled) {
+                log::warn!("Cannot store the {} preference: {err}", SWITCHES[index].key);
+            }
+            toggle(&items, index, enabled);
+        });
+    Ok(())
+}
+
+/// Sets the switches to their defaults, which are kept until the end
+#[cfg(feature = "sim")]
+pub fn init(window: &MainWindow) {
+    let items = set_items(
+        window,
+        SWITCHES.iter().map(|switch| switch.default).collect(),
+    );
+    window
+        .global::<Preferences>()
+        .on_item_toggled(move |index, enabled| toggle(&items, index as usize, enabled));
+}
+
+fn set_items(window: &MainWindow, enabled: Vec<bool>) -> Rc<VecModel<SwitchItem>> {
+    let items: Rc<VecModel<SwitchItem>> = Rc::new(
+        SWITCHES
+            .iter()
+            .zip(enabled)
+            .map(|(switch, enabled)| SwitchItem {
+                label: switch.label.into(),
+                enabled,
+            })
+            .collect::<Vec<_>>()
+            .into(),
+    );
+    window
+        .global::<Preferences>()
+        .set_items(items.clone().into());
+    items
+}
+
+/// Keeps the model in sync with the switch, which was already toggled
+fn toggle(items: &VecModel<SwitchItem>, index: usize, enabled: bool) {
+    if let Some(mut item) = items.row_data(index) {
+        item.enabled = enabled;
+        items.set_row_data(index, item);
+    }
+}
@@ -0,0 +1,93 @@
+//! Invokes the callbacks of the `SystemEvents` global, see `ui/system_events.slint`, when the
+//! memory is almost full or the network is disconnected.
+//!
+//! The board has no battery gauge, so there is no event for a low battery.
+
+use core::ffi::c_void;
+use std::time::Duration;
+
+use esp_idf_svc::sys::*;
+use slint::ComponentHandle;
+
+use crate::{MainWindow, SystemEvents};
+
+/// Of the internal RAM, which the drivers and the stacks need; the PSRAM is much bigger
+const LOW_MEMORY: usize = 16 * 1024;
+const MEMORY_POLL_PERIOD: Duration = Duration::from_secs(1);
+
+/// Must be called once. The network events are received once Wi-Fi is started.
+pub fn init(window: &MainWindow) -> Result<(), EspError> {
+    unsafe {
+        // Wi-Fi creates the default event loop too
+        if let Err(err) = esp!(esp_event_loop_create_default()) {
+            if err.code() != ESP_ERR_INVALID_STATE {
+                return Err(err);
+            }
+        }
+        // Never freed, the handlers are never unregistered
+        let window = Box::into_raw(Box::new(window.as_weak())).cast();
+        esp!(esp_event_handler_register(
+            WIFI_EVENT,
+            wifi_event_t_WIFI_EVENT_STA_DISCONNECTED as i32,
+            Some(network_disconnected),
+            window,
+        ))?;
+        esp!(esp_event_handler_register(
+            IP_EVENT,
+            ip_event_t_IP_EVENT_STA_LOST_IP as i32,
+            Some(network_disconnected),
+            window,
+        ))?;
+    }
+
+    let window = window.as_weak();
+    std::thread::spawn(move || {
+        let mut low = false;
+        loop {
+            std::thread::sleep(MEMORY_POLL_PERIOD);
+            let free = unsafe { heap_caps_get_free_size(MALLOC_CAP_INTERNAL) };
+            // Only once until the memory is freed again
+            if low {
+                low = free < 2 * LOW_MEMORY;
+                continue;
+            }
+            if free >= LOW_MEMORY {
+                continue;
+            }
+            low = true;
+            log::warn!("Only {free} bytes of internal RAM are free");
+            let window = window.clone();
+            let result = slint::invoke_from_event_loop(move || {
+                if let Some(window) = window.upgrade() {
+                    window.global::<SystemEvents>().invoke_low_memory();
+                }
+            });
+            // The event loop was quit
+            if result.is_err() {
+                break;
+            }
+        }
+    });
+    Ok(())
+}
+
+/// Called in the task of the default event loop, with the window given to
+/// `esp_event_handler_register`
+extern "C" fn network_disconnected(
+    window: *mut c_void,
+    _event_base: esp_event_base_t,
+    _event_id: i32,
+    _event_data: *mut c_void,
+) {
+    let window = unsafe { &*(window as *const slint::Weak<MainWindow>) }.clone();
+    let result = slint::invoke_from_event_loop(move || {
+        if let Some(window) = window.upgrade() {
+            window
+                .global::<SystemEvents>()
+                .invoke_network_disconnected();
+        }
+    });
+    if let Err(err) = result {
+        log::error!("Cannot show that the network was disconnected: {err}");
+    }
+}
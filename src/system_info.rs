@@ -0,0 +1,117 @@
+//! The details of the cards of the system page, see `ui/expandable_card.slint`. They are only put
+//! together when a card is expanded, so the free memory is read again each time.
+//!
+//! The simulator has no touch controller or heap of its own to show.
+
+use std::rc::Rc;
+
+#[cfg(not(feature = "sim"))]
+use esp_idf_svc::sys::{
+    heap_caps_get_free_size, heap_caps_get_minimum_free_size, MALLOC_CAP_INTERNAL,
+    MALLOC_CAP_SPIRAM,
+};
+use slint::{ComponentHandle, Model, SharedString, VecModel};
+
+use crate::{MainWindow, SystemInfo};
+
+/// In the order of the page: the display, the touch controller and the memory
+const CARD_COUNT: usize = 3;
+
+/// Must be called once before the system page is shown
+pub fn init(window: &MainWindow) {
+    let details = Rc::new(VecModel::from(vec![SharedString::default(); CARD_COUNT]));
+    let info = window.global::<SystemInfo>();
+    info.set_details(details.clone().into());
+    info.set_touch_controller(touch_controller_name().into());
+    let window = window.as_weak();
+    info.on_content_requested(move |card| {
+        let Some(window) = window.upgrade() else {
+            return;
+        };
+        let card = card as usize;
+        if card < details.row_count() {
+            details.set_row_data(card, details_of(&window, card).into());
+        }
+    });
+}
+
+fn details_of(window: &MainWindow, card: usize) -> String {
+    match card {
+        0 => {
+            let size = window.window().size();
+            format!(
+                "{}x{} pixels, drawn at a scale factor of {}",
+                size.width,
+                size.height,
+                window.window().scale_factor()
+            )
+        }
+        1 => touch_controller().into(),
+        _ => memory(),
+    }
+}
+
+/// For the summary of the card, the details are in [`touch_controller`]
+#[cfg(not(feature = "sim"))]
+fn touch_controller_name() -> &'static str {
+    use crate::slint_platform::TouchControllerKind;
+
+    match crate::slint_platform::touch_controller() {
+        Some(TouchControllerKind::Gt911) => "GT911",
+        Some(TouchControllerKind::Ft5x06) => "FT5x06",
+        Some(TouchControllerKind::Cst816s) => "CST816S",
+        None => "None",
+    }
+}
+
+#[cfg(not(feature = "sim"))]
+fn touch_controller() -> &'static str {
+    use crate::slint_platform::TouchControllerKind;
+
+    match crate::slint_platform::touch_controller() {
+        Some(TouchControllerKind::Gt911) => {
+            "GT911, up to 5 touch points, read over I2C on each iteration of the event loop"
+        }
+        Some(TouchControllerKind::Ft5x06) => {
+            "FT5x06, up to 2 touch points, read over I2C on each iteration of the event loop"
+        }
+        Some(TouchControllerKind::Cst816s) => {
+            "CST816S, 1 touch point, read over I2C on each iteration of the event loop"
+        }
+        None => "None found, the screen can't be touched",
+    }
+}
+
+#[cfg(feature = "sim")]
+fn touch_controller_name() -> &'static str {
+    "Mouse"
+}
+
+#[cfg(feature = "sim")]
+fn touch_controller() -> &'static str {
+    "The mouse of the host stands for the touch screen"
+}
+
+#[cfg(not(feature = "sim"))]
+fn memory() -> String {
+    let kib = |caps| {
+        let (free, minimum) = unsafe {
+            (
+                heap_caps_get_free_size(caps),
+                heap_caps_get_minimum_free_size(caps),
+            )
+        };
+        (free / 1024, minimum / 1024)
+    };
+    let (internal, internal_minimum) = kib(MALLOC_CAP_INTERNAL);
+    let (psram, psram_minimum) = kib(MALLOC_CAP_SPIRAM);
+    format!(
+        "Internal RAM: {internal} KiB free, at least {internal_minimum} KiB since the start\n\
+         PSRAM: {psram} KiB free, at least {psram_minimum} KiB since the start"
+    )
+}
+
+#[cfg(feature = "sim")]
+fn memory() -> String {
+    "The simulator uses the memory of the host".into()
+}
@@ -0,0 +1,181 @@
+//! Loads the thumbnails of the photos on the storage partition for the image grid, and the
+//! photo that is opened in the image viewer, see `ui/image_grid.slint`.
+
+use std::cell::RefCell;
+use std::num::NonZeroUsize;
+use std::ops::Range;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+use lru::LruCache;
+use slint::{ComponentHandle, Model, VecModel};
+
+use crate::{MainWindow, Thumbnails};
+
+/// Directory of the photos on the storage partition, see the README
+const GALLERY_DIRECTORY: &str = "gallery";
+/// Directory of the thumbnails in [`GALLERY_DIRECTORY`], with the same names as the photos
+const THUMBNAIL_DIRECTORY: &str = "thumbs";
+/// Size of the photos in physical pixels, the size of the screen
+const PHOTO_SIZE: (u32, u32) = (800, 480);
+const THUMBNAIL_SIZE: (u32, u32) = (160, 96);
+/// A few more than the thumbnails that fit on the screen, so that scrolling back doesn't
+/// read them again
+const CACHE_SIZE: usize = 32;
+
+/// Keeps the most recently shown thumbnails decoded in memory, and loads the other visible
+/// thumbnails one at a time, letting the event loop render between them.
+struct ThumbnailLoader {
+    /// File names of the photos, sorted
+    names: Vec<String>,
+    /// Thumbnails that couldn't be loaded are cached as empty images, so they aren't read again
+    cache: LruCache<usize, slint::Image>,
+    visible: Range<usize>,
+    /// Shown by the grid, only the visible thumbnails are set
+    images: Rc<VecModel<slint::Image>>,
+    loading: bool,
+}
+
+impl Default for ThumbnailLoader {
+    fn default() -> Self {
+        Self {
+            names: Vec::new(),
+            cache: LruCache::new(NonZeroUsize::new(CACHE_SIZE).unwrap()),
+            visible: 0..0,
+            images: Default::default(),
+            loading: false,
+        }
+    }
+}
+
+impl ThumbnailLoader {
+    fn set_visible(&mut self, start: usize, end: usize) {
+        let end = end.min(self.names.len());
+        self.visible = start.min(end)..end;
+        self.update_images();
+
+        if !self.loading && self.next_missing().is_some() {
+            self.loading = true;
+            if let Err(err) = slint::spawn_local(load_missing_thumbnails()) {
+                log::error!("Cannot load the thumbnails: {err}");
+                self.loading = false;
+            }
+        }
+    }
+
+    fn next_missing(&self) -> Option<usize> {
+        self.visible
+            .clone()
+            .find(|index| !self.cache.contains(index))
+    }
+
+    /// Shows the visible thumbnails that are loaded, and releases the others
+    fn update_images(&mut self) {
+        for index in 0..self.images.row_count() {
+            let image = if self.visible.contains(&index) {
+                // Also marks the thumbnail as the most recently used
+                self.cache.get(&index).cloned().unwrap_or_default()
+            } else {
+                slint::Image::default()
+            };
+            if self.images.row_data(index).as_ref() != Some(&image) {
+                self.images.set_row_data(index, image);
+            }
+        }
+    }
+}
+
+thread_local! {
+    // Only accessed from the event loop thread
+    static LOADER: RefCell<ThumbnailLoader> = Default::default();
+}
+
+/// Must be called once before the grid is shown, after the storage is mounted
+pub fn init(window: &MainWindow) {
+    let names = list_photos().unwrap_or_else(|err| {
+        log::warn!("Cannot list the photos: {err}");
+        Vec::new()
+    });
+    let thumbnails = window.global::<Thumbnails>();
+    thumbnails.on_visible_range_changed(|start, end| {
+        LOADER.with(|loader| {
+            loader
+                .borrow_mut()
+                .set_visible(start.max(0) as usize, end.max(0) as usize)
+        })
+    });
+    thumbnails.on_opened({
+        let window = window.as_weak();
+        move |index| {
+            let Some(name) =
+                LOADER.with(|loader| loader.borrow().names.get(index as usize).cloned())
+            else {
+                return;
+            };
+            let path = photo_path(&[&name]);
+            match crate::storage::read_rgb565_image(&path, PHOTO_SIZE.0, PHOTO_SIZE.1) {
+                Ok(photo) => window.unwrap().set_photo(photo),
+                Err(err) => log::warn!("Cannot read {}: {err}", path.display()),
+            }
+        }
+    });
+    LOADER.with(|loader| {
+        let mut loader = loader.borrow_mut();
+        loader.images = Rc::new(VecModel::from(vec![slint::Image::default(); names.len()]));
+        loader.names = names;
+        thumbnails.set_images(loader.images.clone().into());
+    });
+}
+
+/// Loads the visible thumbnails that aren't in the cache until there are none left
+async fn load_missing_thumbnails() {
+    while let Some((index, name)) = LOADER.with(|loader| {
+        let loader = loader.borrow();
+        loader
+            .next_missing()
+            .map(|index| (index, loader.names[index].clone()))
+    }) {
+        // Not borrowing the loader while reading, which takes a while
+        let path = photo_path(&[THUMBNAIL_DIRECTORY, &name]);
+        let image = crate::storage::read_rgb565_image(&path, THUMBNAIL_SIZE.0, THUMBNAIL_SIZE.1)
+            .unwrap_or_else(|err| {
+                log::warn!("Cannot load the thumbnail {}: {err}", path.display());
+                slint::Image::default()
+            });
+        LOADER.with(|loader| {
+            let mut loader = loader.borrow_mut();
+            loader.cache.put(index, image);
+            loader.update_images();
+        });
+        crate::map::yield_now().await;
+    }
+    LOADER.with(|loader| loader.borrow_mut().loading = false);
+}
+
+/// The names of the photos in [`GALLERY_DIRECTORY`], sorted. SPIFFS has no directories, the
+/// thumbnails are listed with a slash in their name and skipped.
+fn list_photos() -> std::io::Result<Vec<String>> {
+    let directory = photo_path(&[]);
+    let mut names = Vec::new();
+    for entry in std::fs::read_dir(&directory)? {
+        let path = entry?.path();
+        if path.parent() == Some(directory.as_path())
+            && path.extension().is_some_and(|extension| extension == "rgb")
+            && path.is_file()
+        {
+            if let Some(name) = path.file_name() {
+                names.push(name.to_string_lossy().into_owned());
+            }
+        }
+    }
+    names.sort();
+    Ok(names)
+}
+
+/// The path of `components` in [`GALLERY_DIRECTORY`]
+fn photo_path(components: &[&str]) -> PathBuf {
+    [crate::storage::MOUNT_POINT, GALLERY_DIRECTORY]
+        .iter()
+        .chain(components)
+        .collect()
+}
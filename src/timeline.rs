@@ -0,0 +1,53 @@
+//! Records the pages that are shown for the timeline page, see `ui/timeline.slint`.
+
+use std::rc::Rc;
+use std::time::Instant;
+
+use slint::{Color, ComponentHandle, Model, VecModel};
+
+use crate::{AuditLog, MainWindow, TimelineEvent};
+
+/// The number of events kept, the older ones are dropped
+const MAX_EVENTS: usize = 100;
+/// Of the events, by the index of their page, so that a page always has the same color
+const COLORS: [Color; 4] = [
+    Color::from_rgb_u8(0x19, 0x76, 0xd2),
+    Color::from_rgb_u8(0x38, 0x8e, 0x3c),
+    Color::from_rgb_u8(0xf5, 0x7c, 0x00),
+    Color::from_rgb_u8(0x7b, 0x1f, 0xa2),
+];
+
+/// Records the page that is shown, and returns the handler of `MainWindow.page-changed` that
+/// records each page that replaces it
+pub fn init(window: &MainWindow) -> impl Fn(slint::SharedString) {
+    let start = Instant::now();
+    let events = Rc::new(VecModel::<TimelineEvent>::default());
+    let log = window.global::<AuditLog>();
+    log.set_events(events.clone().into());
+
+    let record = {
+        let window = window.as_weak();
+        move |page: slint::SharedString| {
+            let window = window.unwrap();
+            let now_ms = start.elapsed().as_millis() as i32;
+            let index = window.get_pages().iter().position(|name| name == page);
+            if events.row_count() == MAX_EVENTS {
+                events.remove(0);
+            }
+            events.push(TimelineEvent {
+                timestamp_ms: now_ms,
+                label: page,
+                color: COLORS[index.unwrap_or(0) % COLORS.len()],
+            });
+            // The indicator of the timeline only moves while it is shown
+            window.global::<AuditLog>().set_now_ms(now_ms);
+        }
+    };
+    record(
+        window
+            .get_pages()
+            .row_data(window.get_current_page() as usize)
+            .unwrap_or_default(),
+    );
+    record
+}
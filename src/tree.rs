@@ -0,0 +1,187 @@
+//! The tree of the files on the storage partition and of the settings, shown by the tree view,
+//! see `ui/tree_view.slint`.
+
+use std::cell::RefCell;
+use std::path::Path;
+use std::rc::Rc;
+
+use slint::{ComponentHandle, Model, SharedString, VecModel};
+
+use crate::{FlatNode, MainWindow, TreeState};
+
+/// A node of the tree shown by the tree view. Slint structs can't be recursive, so the view
+/// only gets the visible nodes, see [`flatten_tree`].
+#[derive(Clone, Debug, Default)]
+pub struct TreeNode {
+    pub label: SharedString,
+    pub children: Vec<TreeNode>,
+    pub expanded: bool,
+}
+
+impl TreeNode {
+    fn new(label: &str, children: Vec<TreeNode>) -> Self {
+        Self {
+            label: label.into(),
+            children,
+            expanded: false,
+        }
+    }
+
+    /// Adds the node of the file at `path`, with a node for each of its directories
+    fn insert_path(&mut self, path: &Path) {
+        let mut node = self;
+        for component in path.iter() {
+            let label = component.to_string_lossy();
+            let index = match node.children.iter().position(|child| child.label == *label) {
+                Some(index) => index,
+                None => {
+                    node.children.push(TreeNode::new(&label, Vec::new()));
+                    node.children.len() - 1
+                }
+            };
+            node = &mut node.children[index];
+        }
+    }
+
+    /// Sorts the children by label, recursively
+    fn sort(&mut self) {
+        self.children.sort_by(|a, b| a.label.cmp(&b.label));
+        self.children.iter_mut().for_each(TreeNode::sort);
+    }
+}
+
+#[derive(Default)]
+struct Tree {
+    roots: Vec<TreeNode>,
+    /// Shown by the view, updated from `roots`
+    nodes: Rc<VecModel<FlatNode>>,
+}
+
+impl Tree {
+    fn update_nodes(&self) {
+        let mut nodes = Vec::with_capacity(self.nodes.row_count());
+        flatten_tree(&self.roots, &mut nodes);
+        self.nodes.set_vec(nodes);
+    }
+}
+
+thread_local! {
+    // Only accessed from the event loop thread
+    static TREE: RefCell<Tree> = Default::default();
+}
+
+/// Must be called once, after the storage is mounted. The files are listed in the background.
+pub fn init(window: &MainWindow) {
+    let state = window.global::<TreeState>();
+    state.on_node_toggled(|index| {
+        TREE.with(|tree| {
+            let mut tree = tree.borrow_mut();
+            let mut index = index as usize;
+            if let Some(node) = visible_node_mut(&mut tree.roots, &mut index) {
+                node.expanded = !node.expanded;
+                tree.update_nodes();
+            }
+        })
+    });
+    TREE.with(|tree| {
+        let mut tree = tree.borrow_mut();
+        state.set_nodes(tree.nodes.clone().into());
+        tree.roots = vec![settings_tree()];
+        tree.update_nodes();
+    });
+
+    std::thread::spawn(|| {
+        let mut files = TreeNode::new("Storage partition", Vec::new());
+        let root = Path::new(crate::storage::MOUNT_POINT);
+        if let Err(err) = list_files(root, root, &mut files) {
+            log::warn!("Cannot list the files of the storage partition: {err}");
+        }
+        files.sort();
+        let result = slint::invoke_from_event_loop(move || {
+            TREE.with(|tree| {
+                let mut tree = tree.borrow_mut();
+                tree.roots.insert(0, files);
+                tree.update_nodes();
+            })
+        });
+        if let Err(err) = result {
+            log::error!("Cannot show the files of the storage partition: {err}");
+        }
+    });
+}
+
+/// Appends the nodes that are visible, that is those whose ancestors are all expanded, to
+/// `result`, each node before its children
+pub fn flatten_tree(nodes: &[TreeNode], result: &mut Vec<FlatNode>) {
+    fn flatten(nodes: &[TreeNode], depth: i32, result: &mut Vec<FlatNode>) {
+        for node in nodes {
+            result.push(FlatNode {
+                label: node.label.clone(),
+                depth,
+                has_children: !node.children.is_empty(),
+                expanded: node.expanded,
+            });
+            if node.expanded {
+                flatten(&node.children, depth + 1, result);
+            }
+        }
+    }
+    flatten(nodes, 0, result);
+}
+
+/// The node at `index` in the list made by [`flatten_tree`]. `index` is decremented by the
+/// number of visible nodes that were skipped.
+fn visible_node_mut<'a>(nodes: &'a mut [TreeNode], index: &mut usize) -> Option<&'a mut TreeNode> {
+    for node in nodes {
+        if *index == 0 {
+            return Some(node);
+        }
+        *index -= 1;
+        if node.expanded {
+            if let Some(node) = visible_node_mut(&mut node.children, index) {
+                return Some(node);
+            }
+        }
+    }
+    None
+}
+
+/// Adds the files below `dir` to `node` by their path relative to `root`. SPIFFS has no
+/// directories, but the names of its files can contain slashes.
+fn list_files(root: &Path, dir: &Path, node: &mut TreeNode) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            list_files(root, &path, node)?;
+        } else if let Ok(relative) = path.strip_prefix(root) {
+            node.insert_path(relative);
+        }
+    }
+    Ok(())
+}
+
+fn settings_tree() -> TreeNode {
+    let leaves = |labels: &[&str]| {
+        labels
+            .iter()
+            .map(|label| TreeNode::new(label, Vec::new()))
+            .collect()
+    };
+    TreeNode::new(
+        "Settings",
+        vec![
+            TreeNode::new(
+                "Display",
+                leaves(&["Brightness", "Sleep timeout", "Orientation"]),
+            ),
+            TreeNode::new(
+                "Network",
+                vec![
+                    TreeNode::new("Wi-Fi", leaves(&["Network name", "Password"])),
+                    TreeNode::new("Address", leaves(&["DHCP", "Static address"])),
+                ],
+            ),
+            TreeNode::new("Sound", leaves(&["Volume", "Touch clicks"])),
+        ],
+    )
+}
@@ -0,0 +1,68 @@
+//! The state of the video shown with the playback controls, see `ui/video_controls.slint`.
+//!
+//! The board isn't connected to a network yet, so there is no WebSocket to stream the video
+//! from: a timer pretends to receive the frames while the video page plays, and the seeks are
+//! only logged.
+
+use std::time::Duration;
+
+use slint::ComponentHandle;
+
+use crate::{MainWindow, VideoStream};
+
+const DURATION: Duration = Duration::from_secs(3 * 60 + 25);
+/// 25 frames per second
+const FRAME_INTERVAL: Duration = Duration::from_millis(40);
+
+thread_local! {
+    // Only accessed from the event loop thread
+    static TIMER: slint::Timer = Default::default();
+}
+
+pub fn init(window: &MainWindow) {
+    let stream = window.global::<VideoStream>();
+    stream.set_duration_ms(DURATION.as_millis() as i32);
+    stream.on_seek_to({
+        let window = window.as_weak();
+        move |position_ms| {
+            // This is where the seek command would be sent over the WebSocket
+            log::info!("Seeking to {position_ms} ms");
+            if let Some(window) = window.upgrade() {
+                window.global::<VideoStream>().set_position_ms(position_ms);
+            }
+        }
+    });
+
+    let window = window.as_weak();
+    stream.on_start(move || {
+        let Some(token) = window
+            .upgrade()
+            .map(|window| crate::navigation::page_token(&window))
+        else {
+            return;
+        };
+        let window = window.clone();
+        let receive_frame = move || {
+            let Some(window) = window.upgrade() else {
+                return;
+            };
+            let stream = window.global::<VideoStream>();
+            if token.is_cancelled() || !stream.get_playing() {
+                TIMER.with(|timer| timer.stop());
+                return;
+            }
+            let duration_ms = stream.get_duration_ms();
+            // Playing again from the start once the end was reached
+            let position_ms = match stream.get_position_ms() {
+                position_ms if position_ms >= duration_ms => 0,
+                position_ms => position_ms + FRAME_INTERVAL.as_millis() as i32,
+            };
+            stream.set_position_ms(position_ms.min(duration_ms));
+            if position_ms >= duration_ms {
+                stream.set_playing(false);
+            }
+        };
+        // Restarted with the token of the page that plays now
+        TIMER.with(|timer| timer.start(slint::TimerMode::Repeated, FRAME_INTERVAL, receive_frame));
+    });
+}
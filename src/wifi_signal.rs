@@ -0,0 +1,61 @@
+//! Sets the strength of the `WifiSignal` global, see `ui/wifi_signal.slint`, from the RSSI of the
+//! access point that the station is connected to.
+//!
+//! The RSSI is read from the Wi-Fi driver of ESP-IDF, whichever part of the firmware started it:
+//! there is no bar until the board is connected to a network, which it doesn't join yet.
+
+use std::time::Duration;
+
+use esp_idf_svc::sys::{esp, esp_wifi_sta_get_ap_info, wifi_ap_record_t, EspError};
+use slint::ComponentHandle;
+
+use crate::{MainWindow, WifiSignal};
+
+const POLL_PERIOD: Duration = Duration::from_secs(5);
+
+pub fn init(window: &MainWindow) {
+    let window = window.as_weak();
+    let spawned = crate::slint_platform::spawn_low_priority(async move {
+        let mut shown = 0;
+        loop {
+            // Fails while the station isn't connected
+            let strength = rssi().map_or(0, bars);
+            if strength != shown {
+                shown = strength;
+                let window = window.clone();
+                let updated = slint::invoke_from_event_loop(move || {
+                    if let Some(window) = window.upgrade() {
+                        window.global::<WifiSignal>().set_signal_strength(strength);
+                    }
+                });
+                // The event loop was quit
+                if updated.is_err() {
+                    break;
+                }
+            }
+            // The task only runs this future, which can block it
+            std::thread::sleep(POLL_PERIOD);
+        }
+    });
+    if let Err(err) = spawned {
+        log::error!("Cannot read the strength of the Wi-Fi signal: {err}");
+    }
+}
+
+/// In dBm
+fn rssi() -> Result<i8, EspError> {
+    let mut record = wifi_ap_record_t::default();
+    esp!(unsafe { esp_wifi_sta_get_ap_info(&mut record) })?;
+    Ok(record.rssi)
+}
+
+/// From 0 below -80 dBm to 4 above -50 dBm, one more every 10 dB
+fn bars(rssi: i8) -> i32 {
+    match rssi {
+        ..-80 => 0,
+        -80..-70 => 1,
+        -70..-60 => 2,
+        -60..=-50 => 3,
+        _ => 4,
+    }
+}
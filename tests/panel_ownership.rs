@@ -0,0 +1,90 @@
+//! Checks that the LCD panel is deleted once, by the platform that `set_platform` took only, with
+//! the `PanelOwnership` of `EspPlatform` and a platform that counts the deletions instead.
+
+// Each test has its own platform, on its own thread, with the standard library only
+#![cfg(feature = "sim")]
+
+use std::rc::Rc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+#[path = "../src/slint_platform/panel_ownership.rs"]
+mod panel_ownership;
+
+use panel_ownership::PanelOwnership;
+
+/// Like `EspPlatform`, deletes the panel when it is dropped
+struct PanelPlatform {
+    panel_ownership: PanelOwnership,
+    deletions: Arc<AtomicUsize>,
+}
+
+impl slint::platform::Platform for PanelPlatform {
+    fn create_window_adapter(
+        &self,
+    ) -> Result<Rc<dyn slint::platform::WindowAdapter>, slint::PlatformError> {
+        Err(slint::PlatformError::Other("No window".into()))
+    }
+}
+
+impl Drop for PanelPlatform {
+    fn drop(&mut self) {
+        self.panel_ownership.release(|| {
+            self.deletions.fetch_add(1, Ordering::Relaxed);
+        });
+    }
+}
+
+/// Sets the platform like `init_touch`, its panel deletions are counted in `deletions`
+fn init_touch(
+    deletions: &Arc<AtomicUsize>,
+) -> Result<PanelOwnership, slint::platform::SetPlatformError> {
+    let panel_ownership = PanelOwnership::default();
+    slint::platform::set_platform(Box::new(PanelPlatform {
+        panel_ownership: panel_ownership.clone(),
+        deletions: deletions.clone(),
+    }))?;
+    panel_ownership.take();
+    Ok(panel_ownership)
+}
+
+#[test]
+fn rejected_platform_keeps_the_panel() {
+    let accepted = Arc::new(AtomicUsize::new(0));
+    let rejected = Arc::new(AtomicUsize::new(0));
+    init_touch(&accepted).unwrap();
+    assert!(init_touch(&rejected).is_err());
+    // The panel is still the `DisplayHandle`'s
+    assert_eq!(rejected.load(Ordering::Relaxed), 0);
+    assert_eq!(accepted.load(Ordering::Relaxed), 0);
+}
+
+#[test]
+fn accepted_platform_deletes_the_panel_once() {
+    let deletions = Arc::new(AtomicUsize::new(0));
+    // The platform is dropped at the end of the thread that set it
+    std::thread::spawn({
+        let deletions = deletions.clone();
+        move || {
+            let panel_ownership = init_touch(&deletions).unwrap();
+            assert_eq!(deletions.load(Ordering::Relaxed), 0);
+            drop(panel_ownership);
+        }
+    })
+    .join()
+    .unwrap();
+    assert_eq!(deletions.load(Ordering::Relaxed), 1);
+}
+
+#[test]
+fn panel_is_released_once() {
+    let deletions = AtomicUsize::new(0);
+    let panel_ownership = PanelOwnership::default();
+    panel_ownership.take();
+    for _ in 0..2 {
+        panel_ownership.release(|| {
+            deletions.fetch_add(1, Ordering::Relaxed);
+        });
+    }
+    assert_eq!(deletions.load(Ordering::Relaxed), 1);
+}
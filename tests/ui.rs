@@ -0,0 +1,120 @@
+//! Renders the UI on the host with the software renderer, as on the board but without ESP-IDF,
+//! and checks the colors of the rendered pixels, see the README.
+
+// Each test has its own platform, on its own thread, with the standard library only
+#![cfg(feature = "sim")]
+
+use std::rc::Rc;
+
+use slint::platform::software_renderer::{
+    MinimalSoftwareWindow, RepaintBufferType, Rgb565Pixel, TargetPixel,
+};
+use slint::{ComponentHandle, Model, Rgb8Pixel, SharedPixelBuffer};
+
+slint::include_modules!();
+
+/// Of the display, in physical pixels
+const WIDTH: usize = 800;
+const HEIGHT: usize = 480;
+
+struct TestPlatform {
+    window: Rc<MinimalSoftwareWindow>,
+}
+
+impl slint::platform::Platform for TestPlatform {
+    fn create_window_adapter(
+        &self,
+    ) -> Result<Rc<dyn slint::platform::WindowAdapter>, slint::PlatformError> {
+        Ok(self.window.clone())
+    }
+}
+
+/// Shows a new `MainWindow` in a window of the size of the display. Each test runs on its own
+/// thread, which gets its own platform.
+fn show_main_window() -> (MainWindow, Rc<MinimalSoftwareWindow>) {
+    let window = MinimalSoftwareWindow::new(RepaintBufferType::NewBuffer);
+    window.set_size(slint::PhysicalSize::new(WIDTH as u32, HEIGHT as u32));
+    slint::platform::set_platform(Box::new(TestPlatform {
+        window: window.clone(),
+    }))
+    .unwrap();
+    let main_window = MainWindow::new().unwrap();
+    main_window.show().unwrap();
+    (main_window, window)
+}
+
+/// Shows the page with that label, whose index changes when pages are added before it
+fn show_page(main_window: &MainWindow, label: &str) {
+    let index = main_window
+        .get_pages()
+        .iter()
+        .position(|page| page == label)
+        .unwrap();
+    main_window.set_current_page(index as i32);
+}
+
+/// Renders the whole window, after the property changes were applied
+fn render(window: &MinimalSoftwareWindow) -> Vec<Rgb565Pixel> {
+    slint::platform::update_timers_and_animations();
+    let mut frame = vec![Rgb565Pixel(0); WIDTH * HEIGHT];
+    window.request_redraw();
+    window.draw_if_needed(|renderer| {
+        renderer.render(&mut frame, WIDTH);
+    });
+    frame
+}
+
+/// Fails with the actual color if the physical pixel at `x`, `y` isn't `rgb`
+fn check_pixel(frame: &[Rgb565Pixel], x: usize, y: usize, rgb: [u8; 3]) -> Result<(), String> {
+    let expected = Rgb565Pixel::from_rgb(rgb[0], rgb[1], rgb[2]);
+    let pixel = frame[y * WIDTH + x];
+    if pixel == expected {
+        Ok(())
+    } else {
+        Err(format!(
+            "the pixel at {x}, {y} is {:#06x} instead of {:#06x}",
+            pixel.0, expected.0
+        ))
+    }
+}
+
+#[test]
+fn low_memory_shows_the_warning_banner() {
+    let (main_window, window) = show_main_window();
+    let events = main_window.global::<SystemEvents>();
+    // Under the status bar and the header, where the text of the banner doesn't reach
+    let (x, y) = (4, 188);
+    let banner = [0xb0, 0x00, 0x20];
+    assert!(check_pixel(&render(&window), x, y, banner).is_err());
+
+    events.invoke_low_memory();
+    check_pixel(&render(&window), x, y, banner).unwrap();
+
+    events.invoke_dismiss_warning();
+    assert!(check_pixel(&render(&window), x, y, banner).is_err());
+}
+
+#[test]
+fn animated_background_leaves_the_key_color_to_the_platform() {
+    let (main_window, window) = show_main_window();
+    show_page(&main_window, "Background");
+    let frame = render(&window);
+    // Around the panel in the middle of the page
+    check_pixel(&frame, 8, 400, [0xff, 0x00, 0xff]).unwrap();
+    check_pixel(&frame, 792, 204, [0xff, 0x00, 0xff]).unwrap();
+}
+
+#[test]
+fn canvas_shows_the_image_set_from_rust() {
+    let (main_window, window) = show_main_window();
+    let mut buffer = SharedPixelBuffer::<Rgb8Pixel>::new(400, 240);
+    buffer
+        .make_mut_slice()
+        .fill(Rgb8Pixel::new(0x43, 0xa0, 0x47));
+    main_window
+        .global::<CanvasState>()
+        .set_image(slint::Image::from_rgb8(buffer));
+    show_page(&main_window, "Canvas");
+    // The middle of the canvas
+    check_pixel(&render(&window), 400, 232, [0x43, 0xa0, 0x47]).unwrap();
+}
@@ -0,0 +1,144 @@
+//! `cargo xtask flash-and-monitor` builds the firmware, flashes it with espflash and shows the logs
+//! of the board, like `cargo run`, but with the chip and the baud rate of the
+//! `[package.metadata.espflash]` of `Cargo.toml`, and the partition table of `espflash.toml`:
+//!
+//! ```bash
+//! cargo xtask flash-and-monitor --release
+//! cargo xtask flash-and-monitor --release --features gamma -- --port /dev/ttyACM0
+//! ```
+//!
+//! The arguments after `--` are passed to espflash. It runs on the host, the alias of
+//! `.cargo/config.toml` builds it for the host instead of the board.
+
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitCode};
+
+const USAGE: &str = "Usage: cargo xtask flash-and-monitor [--release] [--features <features>] \
+                     [-- <espflash arguments>]";
+
+fn main() -> ExitCode {
+    let mut args = std::env::args().skip(1);
+    let result = match args.next().as_deref() {
+        Some("flash-and-monitor") => flash_and_monitor(args),
+        _ => Err(USAGE.into()),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("{err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn flash_and_monitor(mut args: impl Iterator<Item = String>) -> Result<(), String> {
+    let mut release = false;
+    let mut features = None;
+    let mut espflash_args = Vec::new();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--release" => release = true,
+            "--features" => features = Some(args.next().ok_or(USAGE)?),
+            "--" => espflash_args.extend(args.by_ref()),
+            _ => return Err(format!("Unknown argument `{arg}`\n{USAGE}")),
+        }
+    }
+
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).parent().unwrap();
+    let manifest_path = root.join("Cargo.toml");
+    let manifest = read_toml(&manifest_path)?;
+    // The firmware is the binary named after the package
+    let binary = string(&manifest, &manifest_path, &["package", "name"])?;
+    let chip = string(
+        &manifest,
+        &manifest_path,
+        &["package", "metadata", "espflash", "chip"],
+    )?;
+    let baud = value(
+        &manifest,
+        &manifest_path,
+        &["package", "metadata", "espflash", "baud"],
+    )?
+    .as_integer()
+    .ok_or_else(|| format!("The baud of {} isn't a number", manifest_path.display()))?;
+    let espflash_path = root.join("espflash.toml");
+    let partition_table = string(
+        &read_toml(&espflash_path)?,
+        &espflash_path,
+        &["partition_table"],
+    )?;
+    let config_path = root.join(".cargo").join("config.toml");
+    let target = string(
+        &read_toml(&config_path)?,
+        &config_path,
+        &["build", "target"],
+    )?;
+
+    let mut build = Command::new(std::env::var("CARGO").unwrap_or_else(|_| "cargo".into()));
+    build.current_dir(root).args(["build", "--bin", &binary]);
+    if release {
+        build.arg("--release");
+    }
+    if let Some(features) = &features {
+        build.args(["--features", features]);
+    }
+    run(build)?;
+
+    let target_dir =
+        std::env::var_os("CARGO_TARGET_DIR").map_or_else(|| root.join("target"), PathBuf::from);
+    let profile = if release { "release" } else { "debug" };
+    let mut flash = Command::new("espflash");
+    flash
+        .current_dir(root)
+        .args(["flash", "--monitor", "--chip", &chip])
+        .args(["--baud", &baud.to_string()])
+        .args(["--partition-table", &partition_table])
+        .args(&espflash_args)
+        .arg(target_dir.join(target).join(profile).join(binary));
+    run(flash)
+}
+
+fn read_toml(path: &Path) -> Result<toml::Table, String> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|err| format!("Cannot read {}: {err}", path.display()))?;
+    text.parse()
+        .map_err(|err| format!("Cannot parse {}: {err}", path.display()))
+}
+
+/// The value of the nested `keys` of `table`, read from `path`
+fn value<'a>(
+    table: &'a toml::Table,
+    path: &Path,
+    keys: &[&str],
+) -> Result<&'a toml::Value, String> {
+    let missing = || format!("{} has no `{}`", path.display(), keys.join("."));
+    let (last, tables) = keys.split_last().unwrap();
+    let mut table = table;
+    for key in tables {
+        table = table
+            .get(*key)
+            .and_then(toml::Value::as_table)
+            .ok_or_else(missing)?;
+    }
+    table.get(*last).ok_or_else(missing)
+}
+
+fn string(table: &toml::Table, path: &Path, keys: &[&str]) -> Result<String, String> {
+    value(table, path, keys)?
+        .as_str()
+        .map(str::to_owned)
+        .ok_or_else(|| format!("`{}` of {} isn't a string", keys.join("."), path.display()))
+}
+
+/// Runs `command`, which shows its output, and fails if it does
+fn run(mut command: Command) -> Result<(), String> {
+    let program = command.get_program().to_string_lossy().into_owned();
+    let status = command
+        .status()
+        .map_err(|err| format!("Cannot run {program}: {err}"))?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(format!("{program} failed: {status}"))
+    }
+}